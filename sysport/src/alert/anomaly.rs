@@ -0,0 +1,184 @@
+// Learned baselines for anomaly rules. A value is anomalous when it is more than
+// `sigma` standard deviations from the baseline's mean.
+use chrono::{DateTime, Local, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+
+// Gaps longer than this (e.g. while sysport was not running) count as this long
+const MAX_GAP_SECS: f64 = 60.0;
+// Time a baseline must have observed before it is trusted
+const MIN_OBSERVED_SECS: f64 = 300.0;
+// Each hour-of-day bucket remembers roughly a week of that hour
+const HOURLY_WINDOW_SECS: f64 = 7.0 * 3600.0;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum BaselineModel {
+    // Exponentially weighted mean and variance over roughly the last `window_secs`
+    Ewma { window_secs: u64 },
+    // One EWMA per local hour of day, for metrics with a daily pattern
+    Hourly,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Stats {
+    mean: f64,
+    var: f64,
+    samples: u64,
+    observed_secs: f64,
+    last: Option<DateTime<Utc>>,
+}
+
+impl Stats {
+    fn update(&mut self, value: f64, at: DateTime<Utc>, window_secs: f64) {
+        let dt = self
+            .last
+            .map(|t| (at - t).num_milliseconds() as f64 / 1000.0)
+            .unwrap_or(0.0)
+            .clamp(0.0, MAX_GAP_SECS);
+        self.last = Some(at);
+        self.observed_secs += dt;
+        if self.samples == 0 {
+            self.mean = value;
+            self.samples = 1;
+            return;
+        }
+        // Plain averaging until there are enough samples for the window to take over,
+        // so the first value does not dominate
+        let alpha = (1.0 - (-dt / window_secs.max(1.0)).exp()).max(1.0 / (self.samples + 1) as f64);
+        let diff = value - self.mean;
+        self.mean += alpha * diff;
+        self.var = (1.0 - alpha) * (self.var + alpha * diff * diff);
+        self.samples += 1;
+    }
+
+    // (deviation in standard deviations, mean, standard deviation), once warmed up
+    fn score(&self, value: f64) -> Option<(f64, f64, f64)> {
+        if self.observed_secs < MIN_OBSERVED_SECS {
+            return None;
+        }
+        // A perfectly flat history would make any change infinitely anomalous
+        let std = self.var.sqrt().max(self.mean.abs() * 0.01).max(1e-9);
+        Some(((value - self.mean) / std, self.mean, std))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Baseline {
+    model: BaselineModel,
+    overall: Stats,
+    hours: Vec<Stats>,
+}
+
+impl Baseline {
+    pub fn new(model: BaselineModel) -> Self {
+        let hours = match model {
+            BaselineModel::Hourly => vec![Stats::default(); 24],
+            BaselineModel::Ewma { .. } => Vec::new(),
+        };
+        Self { model, overall: Stats::default(), hours }
+    }
+
+    fn window_secs(&self) -> f64 {
+        match self.model {
+            BaselineModel::Ewma { window_secs } => window_secs as f64,
+            BaselineModel::Hourly => HOURLY_WINDOW_SECS,
+        }
+    }
+
+    fn slot(&self, at: DateTime<Utc>) -> Option<usize> {
+        match self.model {
+            BaselineModel::Ewma { .. } => None,
+            BaselineModel::Hourly => Some(at.with_timezone(&Local).hour() as usize),
+        }
+    }
+
+    pub fn update(&mut self, value: f64, at: DateTime<Utc>) {
+        let window_secs = self.window_secs();
+        let stats = match self.slot(at) {
+            Some(hour) => &mut self.hours[hour],
+            None => &mut self.overall,
+        };
+        stats.update(value, at, window_secs);
+    }
+
+    // How far `value` is from normal at `at`; None while the baseline is still learning
+    pub fn score(&self, value: f64, at: DateTime<Utc>) -> Option<(f64, f64, f64)> {
+        match self.slot(at) {
+            Some(hour) => self.hours[hour].score(value),
+            None => self.overall.score(value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeDelta, TimeZone};
+
+    fn local(hour: u32, min: u32, sec: u32) -> DateTime<Utc> {
+        Local.with_ymd_and_hms(2024, 3, 1, hour, min, sec).unwrap().with_timezone(&Utc)
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn ewma_averages_the_first_samples_then_follows_the_window() {
+        let start = local(9, 0, 0);
+        let mut stats = Stats::default();
+        stats.update(10.0, start, 60.0);
+        stats.update(20.0, start + TimeDelta::seconds(1), 60.0);
+        assert!(close(stats.mean, 15.0) && close(stats.var, 25.0), "{:?}", stats);
+        stats.update(15.0, start + TimeDelta::seconds(2), 60.0);
+        assert!(close(stats.mean, 15.0) && close(stats.var, 25.0 * 2.0 / 3.0), "{:?}", stats);
+
+        // Once 1/n is smaller than the window's weight, each step moves by 1 - e^(-dt/window)
+        for i in 3..200 {
+            stats.update(15.0, start + TimeDelta::seconds(i), 60.0);
+        }
+        let before = stats.mean;
+        stats.update(115.0, start + TimeDelta::seconds(200), 60.0);
+        let alpha = 1.0 - (-1.0f64 / 60.0).exp();
+        assert!(close(stats.mean, before + alpha * (115.0 - before)), "{:?}", stats);
+
+        // A long gap weighs no more than MAX_GAP_SECS
+        let before = stats.mean;
+        stats.update(15.0, start + TimeDelta::hours(5), 60.0);
+        let alpha = 1.0 - (-MAX_GAP_SECS / 60.0).exp();
+        assert!(close(stats.mean, before + alpha * (15.0 - before)), "{:?}", stats);
+    }
+
+    #[test]
+    fn scores_stay_quiet_until_warmed_up() {
+        let start = local(9, 0, 0);
+        let mut baseline = Baseline::new(BaselineModel::Ewma { window_secs: 600 });
+        for i in 0..300 {
+            baseline.update(50.0, start + TimeDelta::seconds(i));
+        }
+        assert_eq!(baseline.score(500.0, start + TimeDelta::seconds(299)), None);
+        baseline.update(50.0, start + TimeDelta::seconds(300));
+        // A flat history uses 1% of the mean as its deviation
+        assert_eq!(baseline.score(50.5, start + TimeDelta::seconds(300)), Some((1.0, 50.0, 0.5)));
+
+        // Time away from the machine does not count towards warming up
+        let mut baseline = Baseline::new(BaselineModel::Ewma { window_secs: 600 });
+        baseline.update(50.0, start);
+        baseline.update(50.0, start + TimeDelta::hours(2));
+        assert_eq!(baseline.score(500.0, start + TimeDelta::hours(2)), None);
+    }
+
+    #[test]
+    fn hourly_baselines_learn_each_hour_separately() {
+        let mut baseline = Baseline::new(BaselineModel::Hourly);
+        for i in 0..=36 {
+            baseline.update(10.0, local(9, 0, 0) + TimeDelta::seconds(i * 10));
+            baseline.update(100.0, local(15, 0, 0) + TimeDelta::seconds(i * 10));
+        }
+
+        let (sigma, mean, _) = baseline.score(100.0, local(9, 30, 0)).unwrap();
+        assert!(sigma > 100.0 && close(mean, 10.0), "{} {}", sigma, mean);
+        assert_eq!(baseline.score(100.0, local(15, 30, 0)), Some((0.0, 100.0, 1.0)));
+        // Hours with no history are still learning
+        assert_eq!(baseline.score(100.0, local(3, 0, 0)), None);
+    }
+}
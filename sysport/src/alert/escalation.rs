@@ -0,0 +1,93 @@
+// Escalation policies: alerts that stay firing without being acknowledged are
+// raised in level and sent to more channels, step by step
+use super::silence::AlertSelector;
+use super::{Alert, AlertLevel};
+use chrono::Duration;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EscalationStep {
+    // Time since the alert fired
+    pub after_secs: u64,
+    // Raises the alert to this level; never lowers it
+    #[serde(default)]
+    pub level: Option<AlertLevel>,
+    // Notification channels (by name) that get the alert from this step on.
+    // Empty notifies the channels that accept the raised alert anyway.
+    #[serde(default)]
+    pub channels: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EscalationPolicy {
+    pub name: String,
+    // Matched against the alert as its rule raised it, before any escalation
+    #[serde(flatten)]
+    pub selector: AlertSelector,
+    pub steps: Vec<EscalationStep>,
+}
+
+impl EscalationPolicy {
+    // Applies every step that is due for an alert that fired `elapsed` ago;
+    // true if any was
+    pub fn apply(&self, alert: &mut Alert, elapsed: Duration) -> bool {
+        let mut applied = false;
+        while let Some(step) = self.steps.get(alert.escalation) {
+            if elapsed < Duration::seconds(step.after_secs as i64) {
+                break;
+            }
+            if let Some(level) = step.level.as_ref().filter(|level| **level > alert.level) {
+                alert.level = level.clone();
+            }
+            for channel in &step.channels {
+                if !alert.routed_to.contains(channel) {
+                    alert.routed_to.push(channel.clone());
+                }
+            }
+            alert.escalation += 1;
+            applied = true;
+        }
+        applied
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> EscalationPolicy {
+        EscalationPolicy {
+            name: "unacked warnings".to_string(),
+            selector: AlertSelector { level: Some(AlertLevel::Warning), ..Default::default() },
+            steps: vec![
+                EscalationStep { after_secs: 900, level: Some(AlertLevel::Critical), channels: vec!["pager".to_string()] },
+                EscalationStep { after_secs: 1800, level: Some(AlertLevel::Info), channels: vec!["pager".to_string(), "lead".to_string()] },
+            ],
+        }
+    }
+
+    #[test]
+    fn applies_steps_as_they_come_due() {
+        let mut alert = Alert::test("CpuUsage", AlertLevel::Warning);
+        assert!(!policy().apply(&mut alert, Duration::seconds(899)));
+        assert_eq!((alert.level.clone(), alert.escalation), (AlertLevel::Warning, 0));
+
+        assert!(policy().apply(&mut alert, Duration::minutes(15)));
+        assert_eq!((alert.level.clone(), alert.escalation), (AlertLevel::Critical, 1));
+        assert_eq!(alert.routed_to, vec!["pager"]);
+        assert!(!policy().apply(&mut alert, Duration::minutes(20)));
+
+        // The second step adds a channel once and does not lower the level
+        assert!(policy().apply(&mut alert, Duration::minutes(30)));
+        assert_eq!((alert.level.clone(), alert.escalation), (AlertLevel::Critical, 2));
+        assert_eq!(alert.routed_to, vec!["pager", "lead"]);
+        assert!(!policy().apply(&mut alert, Duration::hours(5)));
+    }
+
+    #[test]
+    fn overdue_steps_are_applied_together() {
+        let mut alert = Alert::test("CpuUsage", AlertLevel::Warning);
+        assert!(policy().apply(&mut alert, Duration::hours(1)));
+        assert_eq!((alert.level, alert.escalation), (AlertLevel::Critical, 2));
+    }
+}
@@ -0,0 +1,953 @@
+// Alert rule expressions, for example
+//   cpu_total > 85 and mem_pct > 70 for 2m
+//   disk["/var"].used_pct > 90
+//   rate(iface["eth0"].rx_errors) > 10
+// Numbers take size units (B, KB, MB, GB, TB, 1024-based, optionally "/s") or %;
+// the `for` clause takes s, m, h or d.
+use crate::metrics::Metrics;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub message: String,
+    pub column: usize, // 1-based
+}
+
+impl ParseError {
+    fn new(message: impl Into<String>, column: usize) -> Self {
+        Self { message: message.into(), column }
+    }
+
+    // The source with a caret under the offending column, for monospace display
+    pub fn render(&self, source: &str) -> String {
+        format!("{}\n{}^ {}", source, " ".repeat(self.column.saturating_sub(1)), self.message)
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (column {})", self.message, self.column)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Unit {
+    Percent,
+    Bytes,
+    BytesPerSec,
+    Count,
+    PerSec,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Scalar {
+    CpuTotal,
+    MemPct,
+    MemUsed,
+    MemTotal,
+    MemAvailable,
+    NetRx,
+    NetTx,
+    ProcessCount,
+}
+
+const SCALARS: &[(&str, Scalar)] = &[
+    ("cpu_total", Scalar::CpuTotal),
+    ("mem_pct", Scalar::MemPct),
+    ("mem_used", Scalar::MemUsed),
+    ("mem_total", Scalar::MemTotal),
+    ("mem_available", Scalar::MemAvailable),
+    ("net_rx", Scalar::NetRx),
+    ("net_tx", Scalar::NetTx),
+    ("process_count", Scalar::ProcessCount),
+];
+
+const DISK_FIELDS: &[&str] = &[
+    "used_pct", "used", "available", "total", "inodes_pct", "read_bytes", "write_bytes", "read_iops", "write_iops", "queue_depth", "service_time_ms",
+];
+
+const IFACE_FIELDS: &[&str] = &["rx", "tx", "rx_packets", "tx_packets", "rx_errors", "tx_errors", "rx_dropped", "tx_dropped"];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Source {
+    Scalar(Scalar),
+    Core(usize),
+    Disk { mount: String, field: &'static str },
+    Iface { name: String, field: &'static str },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Var {
+    name: String, // as written back in messages, e.g. disk["/var"].used_pct
+    source: Source,
+}
+
+impl Var {
+    // Values that are already per-second; rate() of these is the value itself
+    fn is_rate(&self) -> bool {
+        match &self.source {
+            Source::Scalar(s) => matches!(s, Scalar::NetRx | Scalar::NetTx),
+            Source::Disk { field, .. } => field.ends_with("_bytes") || field.ends_with("_iops"),
+            Source::Iface { field, .. } => matches!(*field, "rx" | "tx"),
+            Source::Core(_) => false,
+        }
+    }
+
+    fn unit(&self) -> Unit {
+        match &self.source {
+            Source::Scalar(Scalar::CpuTotal | Scalar::MemPct) | Source::Core(_) => Unit::Percent,
+            Source::Scalar(Scalar::MemUsed | Scalar::MemTotal | Scalar::MemAvailable) => Unit::Bytes,
+            Source::Scalar(Scalar::NetRx | Scalar::NetTx) => Unit::BytesPerSec,
+            Source::Scalar(Scalar::ProcessCount) => Unit::Count,
+            Source::Disk { field, .. } => match *field {
+                "used_pct" | "inodes_pct" => Unit::Percent,
+                "used" | "available" | "total" => Unit::Bytes,
+                "read_bytes" | "write_bytes" => Unit::BytesPerSec,
+                _ => Unit::Count,
+            },
+            Source::Iface { field, .. } => match *field {
+                "rx" | "tx" => Unit::BytesPerSec,
+                _ => Unit::Count,
+            },
+        }
+    }
+
+    fn value(&self, m: &Metrics) -> Option<f64> {
+        match &self.source {
+            Source::Scalar(s) => Some(match s {
+                Scalar::CpuTotal => m.cpu_total as f64,
+                Scalar::MemPct => {
+                    if m.mem_total == 0 {
+                        return None;
+                    }
+                    m.mem_used as f64 / m.mem_total as f64 * 100.0
+                }
+                // Metrics reports memory in KB
+                Scalar::MemUsed => m.mem_used as f64 * 1024.0,
+                Scalar::MemTotal => m.mem_total as f64 * 1024.0,
+                Scalar::MemAvailable => m.mem_total.saturating_sub(m.mem_used) as f64 * 1024.0,
+                Scalar::NetRx => m.net_rx as f64,
+                Scalar::NetTx => m.net_tx as f64,
+                Scalar::ProcessCount => m.processes.len() as f64,
+            }),
+            Source::Core(i) => m.cpu_usage.get(*i).map(|u| *u as f64),
+            Source::Disk { mount, field } => {
+                let d = m.disks.iter().find(|d| &d.mount_point == mount)?;
+                Some(match *field {
+                    "used_pct" => (d.total - d.available) as f64 / d.total.max(1) as f64 * 100.0,
+                    "used" => (d.total - d.available) as f64,
+                    "available" => d.available as f64,
+                    "total" => d.total as f64,
+                    "inodes_pct" => {
+                        if d.inodes_total == 0 {
+                            return None;
+                        }
+                        (d.inodes_total - d.inodes_free) as f64 / d.inodes_total as f64 * 100.0
+                    }
+                    "read_bytes" => d.read_bytes as f64,
+                    "write_bytes" => d.write_bytes as f64,
+                    "read_iops" => d.read_iops,
+                    "write_iops" => d.write_iops,
+                    "queue_depth" => d.queue_depth,
+                    _ => d.service_time_ms,
+                })
+            }
+            Source::Iface { name, field } => {
+                let i = m.net_per_interface.iter().find(|i| &i.name == name)?;
+                Some(match *field {
+                    "rx" => i.rx,
+                    "tx" => i.tx,
+                    "rx_packets" => i.rx_packets,
+                    "tx_packets" => i.tx_packets,
+                    "rx_errors" => i.rx_errors,
+                    "tx_errors" => i.tx_errors,
+                    "rx_dropped" => i.rx_dropped,
+                    _ => i.tx_dropped,
+                } as f64)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+    And,
+    Or,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Num(f64, Option<Unit>), // unit when written with one, e.g. 50MB/s
+    Var(Var),
+    Rate(Var),
+    Neg(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Type {
+    Num,
+    Bool,
+}
+
+// A parsed rule: a boolean expression plus an optional `for` duration
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    expr: Expr,
+    pub for_secs: Option<u64>,
+}
+
+// Remembers the previous sample of every variable passed to rate()
+#[derive(Default)]
+pub struct RateTracker {
+    previous: HashMap<String, (DateTime<Utc>, f64)>,
+    current: HashMap<String, (DateTime<Utc>, f64)>,
+}
+
+impl RateTracker {
+    fn rate(&mut self, key: &str, value: f64, now: DateTime<Utc>) -> Option<f64> {
+        self.current.insert(key.to_string(), (now, value));
+        let (then, previous) = self.previous.get(key)?;
+        let secs = (now - *then).num_milliseconds() as f64 / 1000.0;
+        if secs <= 0.0 {
+            return None;
+        }
+        Some((value - previous) / secs)
+    }
+
+    // Call once per sample after every rule has been evaluated
+    pub fn advance(&mut self) {
+        self.previous.extend(self.current.drain());
+    }
+}
+
+impl Rule {
+    // None when a referenced metric is missing (e.g. an unmounted disk) or rate()
+    // has no previous sample yet; the rule is then treated as not matching
+    pub fn evaluate(&self, metrics: &Metrics, rates: &mut RateTracker) -> Option<bool> {
+        eval(&self.expr, metrics, rates).map(|v| v != 0.0)
+    }
+
+    // "name = value" for every metric the rule reads, for alert messages
+    pub fn describe(&self, metrics: &Metrics) -> String {
+        let mut vars = Vec::new();
+        collect_vars(&self.expr, &mut vars);
+        vars.dedup_by(|a, b| a.name == b.name);
+        vars.iter()
+            .map(|v| match v.value(metrics) {
+                Some(value) => format!("{} = {}", v.name, format_value(value, v.unit())),
+                None => format!("{} = n/a", v.name),
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+fn eval(expr: &Expr, m: &Metrics, rates: &mut RateTracker) -> Option<f64> {
+    let bool_value = |b: bool| if b { 1.0 } else { 0.0 };
+    Some(match expr {
+        Expr::Num(n, _) => *n,
+        Expr::Var(v) => v.value(m)?,
+        Expr::Rate(v) => {
+            let value = v.value(m)?;
+            if v.is_rate() {
+                value
+            } else {
+                rates.rate(&v.name, value, m.timestamp)?
+            }
+        }
+        Expr::Neg(e) => -eval(e, m, rates)?,
+        Expr::Not(e) => bool_value(eval(e, m, rates)? == 0.0),
+        Expr::Binary(op, l, r) => {
+            let l = eval(l, m, rates)?;
+            let r = eval(r, m, rates)?;
+            match op {
+                BinOp::Add => l + r,
+                BinOp::Sub => l - r,
+                BinOp::Mul => l * r,
+                BinOp::Div => {
+                    if r == 0.0 {
+                        return None;
+                    }
+                    l / r
+                }
+                BinOp::Lt => bool_value(l < r),
+                BinOp::Le => bool_value(l <= r),
+                BinOp::Gt => bool_value(l > r),
+                BinOp::Ge => bool_value(l >= r),
+                BinOp::Eq => bool_value(l == r),
+                BinOp::Ne => bool_value(l != r),
+                BinOp::And => bool_value(l != 0.0 && r != 0.0),
+                BinOp::Or => bool_value(l != 0.0 || r != 0.0),
+            }
+        }
+    })
+}
+
+fn collect_vars<'a>(expr: &'a Expr, out: &mut Vec<&'a Var>) {
+    match expr {
+        Expr::Num(..) => {}
+        Expr::Var(v) | Expr::Rate(v) => out.push(v),
+        Expr::Neg(e) | Expr::Not(e) => collect_vars(e, out),
+        Expr::Binary(_, l, r) => {
+            collect_vars(l, out);
+            collect_vars(r, out);
+        }
+    }
+}
+
+fn format_value(value: f64, unit: Unit) -> String {
+    let size = |v: f64| {
+        let mut v = v;
+        let mut unit = 0;
+        while v.abs() >= 1024.0 && unit < 4 {
+            v /= 1024.0;
+            unit += 1;
+        }
+        format!("{:.1} {}", v, ["B", "KB", "MB", "GB", "TB"][unit])
+    };
+    match unit {
+        Unit::Percent => format!("{:.1}%", value),
+        Unit::Bytes => size(value),
+        Unit::BytesPerSec => format!("{}/s", size(value)),
+        Unit::Count => format!("{:.0}", value),
+        Unit::PerSec => format!("{:.2}/s", value),
+    }
+}
+
+fn unit_name(unit: Unit) -> &'static str {
+    match unit {
+        Unit::Percent => "a percentage",
+        Unit::Bytes => "a size",
+        Unit::BytesPerSec => "a size per second",
+        Unit::Count => "a count",
+        Unit::PerSec => "a rate",
+    }
+}
+
+// Why a metric cannot be compared with a number written in another unit, e.g. `cpu_total > 50MB`
+fn unit_mismatch(metric: &Expr, number: &Expr) -> Option<String> {
+    let (Some(unit), Expr::Num(_, Some(written))) = (unit_of(metric), number) else {
+        return None;
+    };
+    let compatible = match written {
+        Unit::Percent => unit == Unit::Percent,
+        // Sizes without /s are accepted for throughput, as in `net_rx > 50MB`
+        Unit::Bytes => matches!(unit, Unit::Bytes | Unit::BytesPerSec),
+        _ => unit == *written,
+    };
+    if compatible {
+        return None;
+    }
+    let name = match metric {
+        Expr::Rate(v) => format!("rate({})", v.name),
+        Expr::Var(v) => v.name.clone(),
+        _ => return None,
+    };
+    Some(format!("`{}` is {}, not {}", name, unit_name(unit), unit_name(*written)))
+}
+
+// Unit of a bare metric or rate(metric); computed values are shown as plain numbers
+fn unit_of(expr: &Expr) -> Option<Unit> {
+    match expr {
+        Expr::Var(v) => Some(v.unit()),
+        Expr::Rate(v) if v.is_rate() => Some(v.unit()),
+        Expr::Rate(v) => Some(if v.unit() == Unit::Bytes { Unit::BytesPerSec } else { Unit::PerSec }),
+        _ => None,
+    }
+}
+
+// A numeric expression such as `net_rx` or `rate(iface["eth0"].rx_errors)`,
+// used where a rule watches a value rather than a condition
+#[derive(Debug, Clone, PartialEq)]
+pub struct Metric {
+    expr: Expr,
+}
+
+impl Metric {
+    pub fn value(&self, metrics: &Metrics, rates: &mut RateTracker) -> Option<f64> {
+        eval(&self.expr, metrics, rates)
+    }
+
+    pub fn format(&self, value: f64) -> String {
+        match unit_of(&self.expr) {
+            Some(unit) => format_value(value, unit),
+            None => format!("{:.2}", value),
+        }
+    }
+}
+
+pub fn parse_metric(source: &str) -> Result<Metric, ParseError> {
+    let tokens = tokenize(source)?;
+    let mut parser = Parser { tokens, pos: 0, end: source.chars().count() + 1 };
+    let expr = parser.expr()?;
+    if let Some(tok) = parser.peek() {
+        return Err(ParseError::new(format!("unexpected {}", tok.token.describe()), tok.column));
+    }
+    if type_of(&expr) != Type::Num {
+        return Err(ParseError::new("expected a metric, e.g. `net_rx`, not a condition", 1));
+    }
+    Ok(Metric { expr })
+}
+
+pub fn parse(source: &str) -> Result<Rule, ParseError> {
+    let tokens = tokenize(source)?;
+    let mut parser = Parser { tokens, pos: 0, end: source.chars().count() + 1 };
+    let expr = parser.expr()?;
+    let for_secs = if parser.eat_keyword("for") {
+        Some(parser.duration()?)
+    } else {
+        None
+    };
+    if let Some(tok) = parser.peek() {
+        return Err(ParseError::new(format!("unexpected {}", tok.token.describe()), tok.column));
+    }
+    if type_of(&expr) != Type::Bool {
+        return Err(ParseError::new("a rule must be a condition, e.g. `cpu_total > 90`", 1));
+    }
+    Ok(Rule { expr, for_secs })
+}
+
+// Operand types were already checked while parsing, so this only looks at the top node
+fn type_of(expr: &Expr) -> Type {
+    match expr {
+        Expr::Not(_) => Type::Bool,
+        Expr::Binary(op, _, _) if !matches!(op, BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div) => Type::Bool,
+        _ => Type::Num,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Num(f64, String), // value and unit suffix as written
+    Ident(String),
+    Str(String),
+    Op(&'static str),
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Num(n, unit) => format!("number `{}{}`", n, unit),
+            Token::Ident(s) => format!("`{}`", s),
+            Token::Str(s) => format!("string \"{}\"", s),
+            Token::Op(op) => format!("`{}`", op),
+        }
+    }
+}
+
+struct Tok {
+    token: Token,
+    column: usize,
+}
+
+const OPERATORS: &[&str] = &["<=", ">=", "==", "!=", "&&", "||", "<", ">", "!", "+", "-", "*", "/", "(", ")", "[", "]", "."];
+
+fn tokenize(source: &str) -> Result<Vec<Tok>, ParseError> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let column = i + 1;
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().collect();
+            let value = text.parse::<f64>().map_err(|_| ParseError::new(format!("invalid number `{}`", text), column))?;
+            let unit_start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphabetic() || chars[i] == '%') {
+                i += 1;
+            }
+            // "/s" belongs to the unit only when it directly follows one, as in 50MB/s
+            if i > unit_start && i + 1 < chars.len() && chars[i] == '/' && chars[i + 1] == 's' && chars.get(i + 2).is_none_or(|c| !c.is_alphanumeric()) {
+                i += 2;
+            }
+            tokens.push(Tok { token: Token::Num(value, chars[unit_start..i].iter().collect()), column });
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Tok { token: Token::Ident(chars[start..i].iter().collect()), column });
+        } else if c == '"' || c == '\'' {
+            let start = i + 1;
+            i += 1;
+            while i < chars.len() && chars[i] != c {
+                i += 1;
+            }
+            if i >= chars.len() {
+                return Err(ParseError::new("unterminated string", column));
+            }
+            tokens.push(Tok { token: Token::Str(chars[start..i].iter().collect()), column });
+            i += 1;
+        } else {
+            let rest: String = chars[i..chars.len().min(i + 2)].iter().collect();
+            match OPERATORS.iter().find(|op| rest.starts_with(*op)) {
+                Some(op) => {
+                    tokens.push(Tok { token: Token::Op(op), column });
+                    i += op.len();
+                }
+                None => return Err(ParseError::new(format!("unexpected character `{}`", c), column)),
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Tok>,
+    pos: usize,
+    end: usize, // column just past the input, for "unexpected end" errors
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Tok> {
+        self.tokens.get(self.pos)
+    }
+
+    fn column(&self) -> usize {
+        self.peek().map(|t| t.column).unwrap_or(self.end)
+    }
+
+    fn next(&mut self) -> Result<Tok, ParseError> {
+        if self.pos >= self.tokens.len() {
+            return Err(ParseError::new("unexpected end of rule", self.end));
+        }
+        let tok = std::mem::replace(&mut self.tokens[self.pos], Tok { token: Token::Op(""), column: 0 });
+        self.pos += 1;
+        Ok(tok)
+    }
+
+    fn eat_op(&mut self, op: &str) -> bool {
+        if matches!(self.peek(), Some(Tok { token: Token::Op(o), .. }) if *o == op) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        if matches!(self.peek(), Some(Tok { token: Token::Ident(s), .. }) if s.eq_ignore_ascii_case(keyword)) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect_op(&mut self, op: &str) -> Result<(), ParseError> {
+        if self.eat_op(op) {
+            return Ok(());
+        }
+        let found = self.peek().map(|t| t.token.describe()).unwrap_or_else(|| "end of rule".to_string());
+        Err(ParseError::new(format!("expected `{}`, found {}", op, found), self.column()))
+    }
+
+    fn expr(&mut self) -> Result<Expr, ParseError> {
+        self.or()
+    }
+
+    fn or(&mut self) -> Result<Expr, ParseError> {
+        let mut left = self.and()?;
+        loop {
+            let column = self.column();
+            if !(self.eat_keyword("or") || self.eat_op("||")) {
+                return Ok(left);
+            }
+            let right = self.and()?;
+            left = self.logical(BinOp::Or, left, right, column)?;
+        }
+    }
+
+    fn and(&mut self) -> Result<Expr, ParseError> {
+        let mut left = self.not()?;
+        loop {
+            let column = self.column();
+            if !(self.eat_keyword("and") || self.eat_op("&&")) {
+                return Ok(left);
+            }
+            let right = self.not()?;
+            left = self.logical(BinOp::And, left, right, column)?;
+        }
+    }
+
+    fn logical(&self, op: BinOp, left: Expr, right: Expr, column: usize) -> Result<Expr, ParseError> {
+        if type_of(&left) != Type::Bool || type_of(&right) != Type::Bool {
+            return Err(ParseError::new("`and`/`or` need a condition on both sides, e.g. `cpu_total > 80 and mem_pct > 70`", column));
+        }
+        Ok(Expr::Binary(op, Box::new(left), Box::new(right)))
+    }
+
+    fn not(&mut self) -> Result<Expr, ParseError> {
+        let column = self.column();
+        if self.eat_keyword("not") || self.eat_op("!") {
+            let inner = self.not()?;
+            if type_of(&inner) != Type::Bool {
+                return Err(ParseError::new("`not` needs a condition", column));
+            }
+            return Ok(Expr::Not(Box::new(inner)));
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr, ParseError> {
+        let left = self.sum()?;
+        let column = self.column();
+        let op = match self.peek().map(|t| &t.token) {
+            Some(Token::Op("<")) => BinOp::Lt,
+            Some(Token::Op("<=")) => BinOp::Le,
+            Some(Token::Op(">")) => BinOp::Gt,
+            Some(Token::Op(">=")) => BinOp::Ge,
+            Some(Token::Op("==")) => BinOp::Eq,
+            Some(Token::Op("!=")) => BinOp::Ne,
+            _ => return Ok(left),
+        };
+        self.pos += 1;
+        let right = self.sum()?;
+        if type_of(&left) != Type::Num || type_of(&right) != Type::Num {
+            return Err(ParseError::new("comparisons need a number on both sides", column));
+        }
+        if let Some(message) = unit_mismatch(&left, &right).or_else(|| unit_mismatch(&right, &left)) {
+            return Err(ParseError::new(message, column));
+        }
+        Ok(Expr::Binary(op, Box::new(left), Box::new(right)))
+    }
+
+    fn sum(&mut self) -> Result<Expr, ParseError> {
+        let mut left = self.term()?;
+        loop {
+            let column = self.column();
+            let op = if self.eat_op("+") {
+                BinOp::Add
+            } else if self.eat_op("-") {
+                BinOp::Sub
+            } else {
+                return Ok(left);
+            };
+            let right = self.term()?;
+            left = self.arithmetic(op, left, right, column)?;
+        }
+    }
+
+    fn term(&mut self) -> Result<Expr, ParseError> {
+        let mut left = self.unary()?;
+        loop {
+            let column = self.column();
+            let op = if self.eat_op("*") {
+                BinOp::Mul
+            } else if self.eat_op("/") {
+                BinOp::Div
+            } else {
+                return Ok(left);
+            };
+            let right = self.unary()?;
+            left = self.arithmetic(op, left, right, column)?;
+        }
+    }
+
+    fn arithmetic(&self, op: BinOp, left: Expr, right: Expr, column: usize) -> Result<Expr, ParseError> {
+        if type_of(&left) != Type::Num || type_of(&right) != Type::Num {
+            return Err(ParseError::new("arithmetic needs a number on both sides, not a condition", column));
+        }
+        Ok(Expr::Binary(op, Box::new(left), Box::new(right)))
+    }
+
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        let column = self.column();
+        if self.eat_op("-") {
+            let inner = self.unary()?;
+            if type_of(&inner) != Type::Num {
+                return Err(ParseError::new("`-` needs a number, not a condition", column));
+            }
+            return Ok(Expr::Neg(Box::new(inner)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, ParseError> {
+        let tok = self.next()?;
+        match tok.token {
+            Token::Num(value, unit) => {
+                let scale = value_unit(&unit).ok_or_else(|| ParseError::new(format!("unknown unit `{}`; use %, B, KB, MB, GB or TB (optionally with /s)", unit), tok.column))?;
+                Ok(Expr::Num(value * scale, written_unit(&unit)))
+            }
+            Token::Op("(") => {
+                let inner = self.expr()?;
+                self.expect_op(")")?;
+                Ok(inner)
+            }
+            Token::Ident(name) if name == "rate" && matches!(self.peek(), Some(Tok { token: Token::Op("("), .. })) => {
+                self.pos += 1;
+                let column = self.column();
+                let var = match self.next()? {
+                    Tok { token: Token::Ident(name), column } => self.variable(name, column)?,
+                    _ => return Err(ParseError::new("rate() takes a metric name, e.g. `rate(net_tx)`", column)),
+                };
+                self.expect_op(")")?;
+                Ok(Expr::Rate(var))
+            }
+            Token::Ident(name) => Ok(Expr::Var(self.variable(name, tok.column)?)),
+            other => Err(ParseError::new(format!("expected a metric or number, found {}", other.describe()), tok.column)),
+        }
+    }
+
+    // name, name[index] or name["key"].field
+    fn variable(&mut self, name: String, column: usize) -> Result<Var, ParseError> {
+        if let Some((_, scalar)) = SCALARS.iter().find(|(n, _)| *n == name) {
+            return Ok(Var { name, source: Source::Scalar(*scalar) });
+        }
+        match name.as_str() {
+            "cpu" => {
+                self.expect_op("[")?;
+                let index_column = self.column();
+                let index = match self.next()?.token {
+                    Token::Num(n, unit) if unit.is_empty() && n.fract() == 0.0 && n >= 0.0 => n as usize,
+                    _ => return Err(ParseError::new("expected a core number, e.g. `cpu[0]`", index_column)),
+                };
+                self.expect_op("]")?;
+                Ok(Var { name: format!("cpu[{}]", index), source: Source::Core(index) })
+            }
+            "disk" | "iface" => {
+                self.expect_op("[")?;
+                let key_column = self.column();
+                let key = match self.next()?.token {
+                    Token::Str(s) => s,
+                    _ => {
+                        let example = if name == "disk" { "disk[\"/var\"]" } else { "iface[\"eth0\"]" };
+                        return Err(ParseError::new(format!("expected a quoted name, e.g. `{}`", example), key_column));
+                    }
+                };
+                self.expect_op("]")?;
+                self.expect_op(".")?;
+                let field_column = self.column();
+                let field = match self.next()?.token {
+                    Token::Ident(f) => f,
+                    _ => return Err(ParseError::new("expected a field name", field_column)),
+                };
+                let fields = if name == "disk" { DISK_FIELDS } else { IFACE_FIELDS };
+                let Some(field) = fields.iter().find(|f| **f == field) else {
+                    return Err(ParseError::new(format!("unknown {} field `{}`{}", name, field, suggestion(&field, fields.iter().copied())), field_column));
+                };
+                let display = format!("{}[\"{}\"].{}", name, key, field);
+                let source = if name == "disk" {
+                    Source::Disk { mount: key, field }
+                } else {
+                    Source::Iface { name: key, field }
+                };
+                Ok(Var { name: display, source })
+            }
+            _ => {
+                let known = SCALARS.iter().map(|(n, _)| *n).chain(["cpu", "disk", "iface"]);
+                Err(ParseError::new(format!("unknown metric `{}`{}", name, suggestion(&name, known)), column))
+            }
+        }
+    }
+
+    fn duration(&mut self) -> Result<u64, ParseError> {
+        let column = self.column();
+        match self.next()?.token {
+            Token::Num(value, unit) => {
+                let scale = match unit.as_str() {
+                    "" | "s" => 1.0,
+                    "m" => 60.0,
+                    "h" => 3600.0,
+                    "d" => 86400.0,
+                    _ => return Err(ParseError::new(format!("unknown duration unit `{}`; use s, m, h or d", unit), column)),
+                };
+                Ok((value * scale) as u64)
+            }
+            _ => Err(ParseError::new("expected a duration after `for`, e.g. `for 2m`", column)),
+        }
+    }
+}
+
+fn value_unit(unit: &str) -> Option<f64> {
+    let unit = unit.strip_suffix("/s").unwrap_or(unit);
+    Some(match unit.to_ascii_lowercase().as_str() {
+        "" | "%" | "b" => 1.0,
+        "k" | "kb" | "kib" => 1024.0,
+        "m" | "mb" | "mib" => 1024.0 * 1024.0,
+        "g" | "gb" | "gib" => 1024.0 * 1024.0 * 1024.0,
+        "t" | "tb" | "tib" => 1024.0 * 1024.0 * 1024.0 * 1024.0,
+        _ => return None,
+    })
+}
+
+// The unit a number was written in; None for a plain number
+fn written_unit(unit: &str) -> Option<Unit> {
+    match unit {
+        "" => None,
+        "%" => Some(Unit::Percent),
+        _ if unit.ends_with("/s") => Some(Unit::BytesPerSec),
+        _ => Some(Unit::Bytes),
+    }
+}
+
+// "; did you mean `x`?" for the closest known name, if it is close enough
+fn suggestion<'a>(name: &str, known: impl Iterator<Item = &'a str>) -> String {
+    known
+        .map(|k| (edit_distance(name, k), k))
+        .filter(|(d, _)| *d <= 2)
+        .min()
+        .map(|(_, k)| format!("; did you mean `{}`?", k))
+        .unwrap_or_default()
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cur = row[j + 1];
+            row[j + 1] = if ca == *cb { prev } else { 1 + prev.min(row[j]).min(cur) };
+            prev = cur;
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::{DiskMetrics, NetInterfaceStats};
+
+    fn sample() -> Metrics {
+        Metrics {
+            cpu_total: 90.0,
+            cpu_usage: vec![99.5, 10.0],
+            mem_total: 4 * 1024 * 1024, // KB
+            mem_used: 3 * 1024 * 1024,
+            net_tx: 60 * 1024 * 1024,
+            disks: vec![DiskMetrics { mount_point: "/var".to_string(), total: 100, available: 5, ..Default::default() }],
+            net_per_interface: vec![NetInterfaceStats { name: "eth0".to_string(), rx_errors: 100, ..Default::default() }],
+            ..Default::default()
+        }
+    }
+
+    fn matches(source: &str, m: &Metrics) -> Option<bool> {
+        parse(source).unwrap().evaluate(m, &mut RateTracker::default())
+    }
+
+    fn error(source: &str) -> ParseError {
+        parse(source).unwrap_err()
+    }
+
+    #[test]
+    fn evaluates_the_documented_examples() {
+        let m = sample();
+        assert_eq!(matches("cpu_total > 85 and mem_pct > 70", &m), Some(true));
+        assert_eq!(matches("disk[\"/var\"].used_pct > 90", &m), Some(true));
+        assert_eq!(matches("rate(net_tx) > 50MB/s", &m), Some(true));
+        assert_eq!(matches("cpu[0] >= 99 or cpu[1] >= 99", &m), Some(true));
+        assert_eq!(matches("not cpu_total > 85", &m), Some(false));
+        assert_eq!(matches("mem_used / mem_total * 100 == mem_pct", &m), Some(true));
+        // Missing subjects make the rule not match instead of failing
+        assert_eq!(matches("disk[\"/srv\"].used_pct > 90", &m), None);
+        assert_eq!(matches("cpu[7] > 1", &m), None);
+    }
+
+    #[test]
+    fn parses_for_durations() {
+        assert_eq!(parse("cpu_total > 85 for 2m").unwrap().for_secs, Some(120));
+        assert_eq!(parse("cpu_total > 85 for 90").unwrap().for_secs, Some(90));
+        assert_eq!(parse("cpu_total > 85 for 30s").unwrap().for_secs, Some(30));
+        assert_eq!(parse("cpu_total > 85 FOR 1h").unwrap().for_secs, Some(3600));
+        assert_eq!(parse("cpu_total > 85 for 1d").unwrap().for_secs, Some(86400));
+        assert_eq!(parse("cpu_total > 85").unwrap().for_secs, None);
+        assert_eq!(error("cpu_total > 85 for 5w").column, 20);
+        assert_eq!(error("cpu_total > 85 for").message, "unexpected end of rule");
+    }
+
+    #[test]
+    fn applies_unit_suffixes() {
+        let mut m = sample();
+        assert_eq!(matches("mem_used > 2GB", &m), Some(true));
+        assert_eq!(matches("mem_used > 3gb", &m), Some(false));
+        assert_eq!(matches("mem_available >= 1024MiB", &m), Some(true));
+        assert_eq!(matches("net_tx > 50MB", &m), Some(true));
+        m.disks[0].total = 2 * 1024 * 1024 * 1024 * 1024;
+        assert_eq!(matches("disk[\"/var\"].total == 2TB", &m), Some(true));
+        assert_eq!(matches("disk[\"/var\"].used < 2T", &m), Some(true));
+        assert_eq!(matches("cpu_total > 50%", &m), Some(true));
+    }
+
+    #[test]
+    fn rejects_numbers_in_the_wrong_unit() {
+        assert_eq!(error("cpu_total > 50MB"), ParseError::new("`cpu_total` is a percentage, not a size", 11));
+        assert_eq!(error("10% < mem_used").message, "`mem_used` is a size, not a percentage");
+        assert_eq!(error("mem_used > 1GB/s").message, "`mem_used` is a size, not a size per second");
+        assert!(error("rate(iface[\"eth0\"].rx_errors) > 1KB").message.contains("is a rate"));
+        assert!(parse("process_count > 5").is_ok());
+    }
+
+    #[test]
+    fn type_checks_operands() {
+        assert_eq!(error("(cpu_total > 5) + 1 > 0"), ParseError::new("arithmetic needs a number on both sides, not a condition", 17));
+        assert_eq!(error("cpu_total * (mem_pct > 1) > 0").column, 11);
+        assert_eq!(error("-(cpu_total > 5)").column, 1);
+        assert_eq!(error("cpu_total > 5 and 3").column, 15);
+        assert_eq!(error("not cpu_total").column, 1);
+        assert_eq!(error("cpu_total + 1").message, "a rule must be a condition, e.g. `cpu_total > 90`");
+    }
+
+    #[test]
+    fn reports_error_columns_and_suggestions() {
+        assert_eq!(error("cpu_totl > 5"), ParseError::new("unknown metric `cpu_totl`; did you mean `cpu_total`?", 1));
+        assert_eq!(error("disk[\"/var\"].usedpct > 1"), ParseError::new("unknown disk field `usedpct`; did you mean `used_pct`?", 14));
+        assert_eq!(error("iface[\"eth0\"].rx_erors > 1").message, "unknown iface field `rx_erors`; did you mean `rx_errors`?");
+        assert_eq!(error("frobnicate > 1").message, "unknown metric `frobnicate`");
+        assert_eq!(error("cpu_total > 5XB").column, 13);
+        assert_eq!(error("cpu_total >"), ParseError::new("unexpected end of rule", 12));
+        assert_eq!(error("cpu_total > 5 )").column, 15);
+        assert_eq!(error("disk[var].used > 1").column, 6);
+        assert_eq!(error("cpu_total > 5 # x").column, 15);
+        assert_eq!(error("disk[\"/var].used > 1").message, "unterminated string");
+        assert_eq!(error("cpu_totl > 5").render("cpu_totl > 5"), "cpu_totl > 5\n^ unknown metric `cpu_totl`; did you mean `cpu_total`?");
+    }
+
+    #[test]
+    fn rate_needs_a_previous_sample() {
+        let rule = parse("rate(iface[\"eth0\"].rx_errors) > 10").unwrap();
+        let mut rates = RateTracker::default();
+        let mut m = sample();
+        assert_eq!(rule.evaluate(&m, &mut rates), None);
+        rates.advance();
+
+        m.timestamp += chrono::Duration::seconds(2);
+        m.net_per_interface[0].rx_errors += 30;
+        assert_eq!(rule.evaluate(&m, &mut rates), Some(true));
+        rates.advance();
+
+        m.timestamp += chrono::Duration::seconds(2);
+        m.net_per_interface[0].rx_errors += 10;
+        assert_eq!(rule.evaluate(&m, &mut rates), Some(false));
+    }
+
+    #[test]
+    fn rate_of_a_throughput_is_the_value_itself() {
+        let metric = parse_metric("rate(net_tx)").unwrap();
+        let m = sample();
+        assert_eq!(metric.value(&m, &mut RateTracker::default()), Some(m.net_tx as f64));
+        assert_eq!(metric.format(1536.0), "1.5 KB/s");
+        assert!(parse_metric("cpu_total > 5").is_err());
+    }
+}
@@ -0,0 +1,145 @@
+// Time-to-full forecasts for disks and memory, from a least-squares line through
+// recent free-space samples
+use crate::metrics::Metrics;
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{HashMap, VecDeque};
+
+// Trend window and spacing of the points kept for it
+const WINDOW_SECS: i64 = 6 * 3600;
+const POINT_SPACING_SECS: i64 = 30;
+// Less history than this gives forecasts that swing with every write
+const MIN_SPAN_SECS: i64 = 30 * 60;
+// Slower shrinking than this (a byte per hour) is rounding noise of a flat series
+const MIN_BYTES_PER_SEC: f64 = 1.0 / 3600.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Forecast {
+    // Positive while free space is shrinking
+    pub bytes_per_hour: f64,
+    // None while usage is flat or falling
+    pub time_to_full: Option<Duration>,
+}
+
+#[derive(Default)]
+struct Series {
+    points: VecDeque<(DateTime<Utc>, f64)>,
+}
+
+impl Series {
+    fn push(&mut self, at: DateTime<Utc>, available: f64) {
+        if self.points.back().is_some_and(|(last, _)| at - *last < Duration::seconds(POINT_SPACING_SECS)) {
+            return;
+        }
+        self.points.push_back((at, available));
+        while self.points.front().is_some_and(|(first, _)| at - *first > Duration::seconds(WINDOW_SECS)) {
+            self.points.pop_front();
+        }
+    }
+
+    fn forecast(&self) -> Option<Forecast> {
+        let (first, _) = self.points.front()?;
+        let (last, available) = self.points.back()?;
+        if *last - *first < Duration::seconds(MIN_SPAN_SECS) {
+            return None;
+        }
+        // Slope of available bytes over seconds since the first point
+        let n = self.points.len() as f64;
+        let xs = self.points.iter().map(|(t, _)| (*t - *first).num_seconds() as f64);
+        let mean_x = xs.clone().sum::<f64>() / n;
+        let mean_y = self.points.iter().map(|(_, y)| y).sum::<f64>() / n;
+        let (mut cov, mut var) = (0.0, 0.0);
+        for (x, (_, y)) in xs.zip(&self.points) {
+            cov += (x - mean_x) * (y - mean_y);
+            var += (x - mean_x) * (x - mean_x);
+        }
+        if var == 0.0 {
+            return None;
+        }
+        let slope = cov / var;
+        // Shrinking below the noise floor, or too slowly to represent, counts as not filling up
+        let time_to_full = (-slope >= MIN_BYTES_PER_SEC).then(|| Duration::try_seconds((available / -slope) as i64)).flatten();
+        Some(Forecast { bytes_per_hour: -slope * 3600.0, time_to_full })
+    }
+}
+
+// Tracks free space of every mount point and of memory
+#[derive(Default)]
+pub struct Forecaster {
+    disks: HashMap<String, Series>,
+    memory: Series,
+}
+
+impl Forecaster {
+    pub fn push(&mut self, m: &Metrics) {
+        for disk in m.disks.iter().filter(|d| d.total > 0) {
+            self.disks.entry(disk.mount_point.clone()).or_default().push(m.timestamp, disk.available as f64);
+        }
+        // Mounts that went away stop being forecast
+        self.disks.retain(|mount, _| m.disks.iter().any(|d| &d.mount_point == mount));
+        if m.mem_total > 0 {
+            // Metrics reports memory in KB
+            self.memory.push(m.timestamp, m.mem_total.saturating_sub(m.mem_used) as f64 * 1024.0);
+        }
+    }
+
+    pub fn disk(&self, mount_point: &str) -> Option<Forecast> {
+        self.disks.get(mount_point)?.forecast()
+    }
+
+    pub fn memory(&self) -> Option<Forecast> {
+        self.memory.forecast()
+    }
+}
+
+// "~3h", "~2d"; rounded since the forecast is only a trend
+pub fn format_eta(d: Duration) -> String {
+    let secs = d.num_seconds().max(0);
+    match secs {
+        s if s < 3600 => format!("~{}m", (s / 60).max(1)),
+        s if s < 2 * 86400 => format!("~{}h", (s + 1800) / 3600),
+        s => format!("~{}d", (s + 43200) / 86400),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn series(minutes: i64, available: impl Fn(i64) -> f64) -> Series {
+        let start = Utc::now();
+        let mut series = Series::default();
+        for minute in 0..=minutes {
+            // Samples never land exactly on the minute
+            let secs = minute * 60 + minute % 7;
+            series.push(start + Duration::seconds(secs), available(secs));
+        }
+        series
+    }
+
+    #[test]
+    fn flat_series_is_not_filling() {
+        // Large free space makes the mean inexact, leaving a slope of about ±1e-20
+        for available in [1.0, 123_456_789.0, 5.3e11, 52_322_777_815_798.0, 97_173_755_435_063.0] {
+            let forecast = series(6 * 60, |_| available).forecast().unwrap();
+            assert_eq!(forecast.time_to_full, None, "available {}", available);
+            assert!(forecast.bytes_per_hour.abs() < 1.0);
+        }
+    }
+
+    #[test]
+    fn falling_series_reaches_zero() {
+        const GB: f64 = 1024.0 * 1024.0 * 1024.0;
+        // 10 GB free, shrinking 1 GB per hour; 8 GB are left after two hours
+        let forecast = series(120, |secs| 10.0 * GB - secs as f64 * GB / 3600.0).forecast().unwrap();
+        assert!((forecast.bytes_per_hour - GB).abs() < 1.0);
+        let eta = forecast.time_to_full.unwrap();
+        assert!((eta - Duration::hours(8)).num_seconds().abs() <= 1, "{:?}", eta);
+        assert_eq!(format_eta(eta), "~8h");
+    }
+
+    #[test]
+    fn growing_or_short_series_has_no_eta() {
+        assert_eq!(series(120, |secs| 1.0e9 + secs as f64).forecast().unwrap().time_to_full, None);
+        assert_eq!(series(10, |secs| 1.0e9 - secs as f64 * 1.0e6).forecast(), None);
+    }
+}
@@ -0,0 +1,1217 @@
+pub mod anomaly;
+pub mod escalation;
+pub mod expr;
+pub mod forecast;
+pub mod process;
+pub mod silence;
+
+use crate::metrics::Metrics;
+use crate::notifications::queue::DeliveryLog;
+use crate::notifications::NotificationManager;
+use crate::report;
+use chrono::{DateTime, Duration, Utc};
+use anomaly::{Baseline, BaselineModel};
+use escalation::EscalationPolicy;
+use forecast::Forecaster;
+use process::{ProcessMatcher, Selector};
+use silence::{MaintenanceWindow, Silence};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+
+// Resolved alerts kept in memory and in the history file
+const MAX_HISTORY: usize = 500;
+
+// Ordered by severity, so `level >= AlertLevel::Warning` works as a filter
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum AlertLevel {
+    Info,
+    Warning,
+    Critical,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AlertState {
+    // Condition holds but not yet for long enough (or still cooling down)
+    Pending,
+    Firing,
+    // Still firing, but an operator has seen it
+    Acknowledged,
+    Resolved,
+}
+
+// One occurrence of a rule firing for one subject, from first breach to recovery.
+// The id stays the same through every state change.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Alert {
+    pub id: u64,
+    // Rule kind and subject, e.g. "DiskUsage /var"; used to route notifications
+    #[serde(default)]
+    pub source: String,
+    pub message: String,
+    pub level: AlertLevel,
+    pub state: AlertState,
+    pub started_at: DateTime<Utc>,
+    pub fired_at: Option<DateTime<Utc>>,
+    pub ended_at: Option<DateTime<Utc>>,
+    pub acknowledged_at: Option<DateTime<Utc>>,
+    pub snoozed_until: Option<DateTime<Utc>>,
+    // Copied from the rule, for silences and routing
+    #[serde(default)]
+    pub rule_name: Option<String>,
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    // Why notifications were held back, if a silence or maintenance window matched
+    #[serde(default)]
+    pub silenced_by: Option<String>,
+    // Escalation steps applied so far, and the channels they routed the alert to
+    #[serde(default)]
+    pub escalation: usize,
+    #[serde(default)]
+    pub routed_to: Vec<String>,
+}
+
+impl Alert {
+    pub fn is_firing(&self) -> bool {
+        matches!(self.state, AlertState::Firing | AlertState::Acknowledged)
+    }
+
+    pub fn is_snoozed(&self, now: DateTime<Utc>) -> bool {
+        self.snoozed_until.is_some_and(|until| until > now)
+    }
+
+    // A firing alert for tests; override fields with struct update syntax
+    #[cfg(test)]
+    pub fn test(source: &str, level: AlertLevel) -> Self {
+        let now = Utc::now();
+        Self {
+            id: 1,
+            source: source.to_string(),
+            message: format!("{} alert", source),
+            level,
+            state: AlertState::Firing,
+            started_at: now,
+            fired_at: Some(now),
+            ended_at: None,
+            acknowledged_at: None,
+            snoozed_until: None,
+            rule_name: None,
+            labels: BTreeMap::new(),
+            silenced_by: None,
+            escalation: 0,
+            routed_to: Vec::new(),
+        }
+    }
+}
+
+// What a rule measures. Percentages are 0-100 (process CPU is per core and may exceed
+// 100), network thresholds are bytes/s and process RSS thresholds bytes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum AlertCondition {
+    CpuUsage { threshold: f32 },
+    MemUsage { threshold: f32 },
+    DiskUsage { threshold: f32 },
+    NetRx { threshold: u64 },
+    NetTx { threshold: u64 },
+    // A custom rule such as `cpu_total > 85 and mem_pct > 70 for 2m`, see `expr`.
+    // It is evaluated as 1.0 when it matches and 0.0 otherwise.
+    Expr { expr: String },
+    // Process rules yield one subject per matching pid
+    ProcessCpu { matcher: ProcessMatcher, threshold: f32 },
+    ProcessRss { matcher: ProcessMatcher, threshold: u64 },
+    // Fires while the number of matching processes is outside min..=max
+    ProcessCount { matcher: ProcessMatcher, min: usize, max: Option<usize> },
+    // Fires while nothing matches, e.g. a daemon that has died
+    ProcessMissing { matcher: ProcessMatcher },
+    // Fires when `metric` (e.g. `net_rx`, see `expr::parse_metric`) is more than `sigma`
+    // standard deviations from its learned baseline; evaluated as that distance
+    Anomaly { metric: String, sigma: f64, model: BaselineModel },
+    // Fire while free space is forecast to run out within `within_secs`, per mount point
+    DiskFull { within_secs: u64 },
+    MemoryFull { within_secs: u64 },
+}
+
+impl AlertCondition {
+    // The variant name as written in the config, e.g. "DiskUsage"
+    pub fn name(&self) -> &'static str {
+        match self {
+            AlertCondition::CpuUsage { .. } => "CpuUsage",
+            AlertCondition::MemUsage { .. } => "MemUsage",
+            AlertCondition::DiskUsage { .. } => "DiskUsage",
+            AlertCondition::NetRx { .. } => "NetRx",
+            AlertCondition::NetTx { .. } => "NetTx",
+            AlertCondition::Expr { .. } => "Expr",
+            AlertCondition::ProcessCpu { .. } => "ProcessCpu",
+            AlertCondition::ProcessRss { .. } => "ProcessRss",
+            AlertCondition::ProcessCount { .. } => "ProcessCount",
+            AlertCondition::ProcessMissing { .. } => "ProcessMissing",
+            AlertCondition::Anomaly { .. } => "Anomaly",
+            AlertCondition::DiskFull { .. } => "DiskFull",
+            AlertCondition::MemoryFull { .. } => "MemoryFull",
+        }
+    }
+
+    pub fn threshold(&self) -> f64 {
+        match self {
+            AlertCondition::CpuUsage { threshold } | AlertCondition::MemUsage { threshold } | AlertCondition::DiskUsage { threshold } => *threshold as f64,
+            AlertCondition::NetRx { threshold } | AlertCondition::NetTx { threshold } => *threshold as f64,
+            AlertCondition::ProcessCpu { threshold, .. } => *threshold as f64,
+            AlertCondition::ProcessRss { threshold, .. } => *threshold as f64,
+            AlertCondition::Anomaly { sigma, .. } => *sigma,
+            // Evaluated as 1.0 while violated, like expressions
+            AlertCondition::Expr { .. }
+            | AlertCondition::ProcessCount { .. }
+            | AlertCondition::ProcessMissing { .. }
+            | AlertCondition::DiskFull { .. }
+            | AlertCondition::MemoryFull { .. } => 0.5,
+        }
+    }
+
+    pub fn matcher(&self) -> Option<&ProcessMatcher> {
+        match self {
+            AlertCondition::ProcessCpu { matcher, .. }
+            | AlertCondition::ProcessRss { matcher, .. }
+            | AlertCondition::ProcessCount { matcher, .. }
+            | AlertCondition::ProcessMissing { matcher } => Some(matcher),
+            _ => None,
+        }
+    }
+
+    // Current value per subject; disk rules yield one entry per mount point, the rest a single one.
+    // Expression rules need compiled state and are evaluated by `AlertManager` instead;
+    // process rules select with the compiled `selector` of their matcher.
+    fn evaluate(&self, metrics: &Metrics, selector: Option<&Selector>) -> Vec<(String, f64)> {
+        let select = || selector.map(|s| s.select(&metrics.processes)).unwrap_or_default();
+        match self {
+            AlertCondition::CpuUsage { .. } => vec![(String::new(), metrics.cpu_total as f64)],
+            AlertCondition::MemUsage { .. } => {
+                if metrics.mem_total == 0 {
+                    return Vec::new();
+                }
+                vec![(String::new(), metrics.mem_used as f64 / metrics.mem_total as f64 * 100.0)]
+            }
+            AlertCondition::DiskUsage { .. } => metrics
+                .disks
+                .iter()
+                .filter(|disk| disk.total > 0)
+                .map(|disk| {
+                    let used = disk.total - disk.available;
+                    (disk.mount_point.clone(), used as f64 / disk.total as f64 * 100.0)
+                })
+                .collect(),
+            AlertCondition::NetRx { .. } => vec![(String::new(), metrics.net_rx as f64)],
+            AlertCondition::NetTx { .. } => vec![(String::new(), metrics.net_tx as f64)],
+            AlertCondition::Expr { .. } | AlertCondition::Anomaly { .. } | AlertCondition::DiskFull { .. } | AlertCondition::MemoryFull { .. } => Vec::new(),
+            AlertCondition::ProcessCpu { .. } => select()
+                .into_iter()
+                .map(|p| (process::subject(p), p.cpu_usage as f64))
+                .collect(),
+            // Metrics reports RSS in KB
+            AlertCondition::ProcessRss { .. } => select()
+                .into_iter()
+                .map(|p| (process::subject(p), p.rss as f64 * 1024.0))
+                .collect(),
+            AlertCondition::ProcessCount { min, max, .. } => {
+                let count = select().len();
+                let outside = count < *min || max.is_some_and(|max| count > max);
+                vec![(String::new(), if outside { 1.0 } else { 0.0 })]
+            }
+            AlertCondition::ProcessMissing { .. } => {
+                let missing = select().is_empty();
+                vec![(String::new(), if missing { 1.0 } else { 0.0 })]
+            }
+        }
+    }
+
+    fn message(&self, subject: &str, value: f64, metrics: &Metrics, selector: Option<&Selector>) -> String {
+        match self {
+            AlertCondition::CpuUsage { .. } => format!("CPU usage high: {:.1}%", value),
+            AlertCondition::MemUsage { .. } => format!("Memory usage high: {:.1}%", value),
+            AlertCondition::DiskUsage { .. } => format!("Disk {} usage high: {:.1}%", subject, value),
+            AlertCondition::NetRx { .. } => format!("High network download{}: {:.2} KB/s", interface_suffix(metrics), value / 1024.0),
+            AlertCondition::NetTx { .. } => format!("High network upload{}: {:.2} KB/s", interface_suffix(metrics), value / 1024.0),
+            AlertCondition::Expr { expr } => format!("Rule `{}` matched", expr),
+            AlertCondition::ProcessCpu { .. } => format!("Process {} CPU usage high: {:.1}%", subject, value),
+            AlertCondition::ProcessRss { .. } => format!("Process {} memory high: {:.1} MB", subject, value / 1024.0 / 1024.0),
+            AlertCondition::ProcessCount { matcher, min, max } => {
+                let count = selector.map_or(0, |s| s.select(&metrics.processes).len());
+                let expected = match max {
+                    Some(max) => format!("{}-{}", min, max),
+                    None => format!("at least {}", min),
+                };
+                format!("{} processes matching {}, expected {}", count, matcher.describe(), expected)
+            }
+            AlertCondition::ProcessMissing { matcher } => format!("Process {} is not running", matcher.describe()),
+            AlertCondition::Anomaly { metric, .. } => format!("{} is {:.1}σ from normal", metric, value),
+            AlertCondition::DiskFull { .. } => format!("Disk {} is filling up", subject),
+            AlertCondition::MemoryFull { .. } => "Memory is filling up".to_string(),
+        }
+    }
+}
+
+// A rule fires once its condition has held for `for_secs`, keeps firing until the
+// value drops to `clear_threshold` (the threshold itself if unset) and will not fire
+// again within `cooldown_secs` of the previous time it fired.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlertRule {
+    pub condition: AlertCondition,
+    pub level: AlertLevel,
+    // Disabled rules are kept in the config but never evaluated
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub for_secs: u64,
+    #[serde(default)]
+    pub clear_threshold: Option<f64>,
+    #[serde(default)]
+    pub cooldown_secs: u64,
+    // Lets silences and escalation policies pick out this rule, e.g. "api-latency"
+    #[serde(default)]
+    pub name: Option<String>,
+    // Free-form tags such as "team": "db", matched by silences
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+}
+
+impl AlertRule {
+    pub fn new(condition: AlertCondition, level: AlertLevel) -> Self {
+        Self { condition, level, enabled: true, for_secs: 0, clear_threshold: None, cooldown_secs: 0, name: None, labels: BTreeMap::new() }
+    }
+
+    // Rules that fail validation are kept but never fire
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(matcher) = self.condition.matcher() {
+            matcher.validate()?;
+        }
+        match &self.condition {
+            AlertCondition::Expr { expr: source } => expr::parse(source).map(|_| ()).map_err(|e| e.to_string()),
+            AlertCondition::ProcessCount { min, max: Some(max), .. } if max < min => Err(format!("process count range {}-{} is empty", min, max)),
+            AlertCondition::Anomaly { sigma, .. } if *sigma <= 0.0 => Err("sigma must be positive".to_string()),
+            AlertCondition::Anomaly { metric, .. } => expr::parse_metric(metric).map(|_| ()).map_err(|e| e.to_string()),
+            _ => Ok(()),
+        }
+    }
+
+    // Messages for every subject that is over the threshold right now, ignoring the
+    // duration and cooldown; Err if the rule is invalid
+    pub fn preview(&self, metrics: &Metrics) -> Result<Vec<String>, String> {
+        self.validate()?;
+        if let AlertCondition::Expr { expr: source } = &self.condition {
+            let rule = expr::parse(source).map_err(|e| e.to_string())?;
+            // Without a previous sample rate() of a counter cannot be evaluated
+            return Ok(match rule.evaluate(metrics, &mut expr::RateTracker::default()) {
+                Some(true) => vec![format!("Rule `{}` matched: {}", source, rule.describe(metrics))],
+                _ => Vec::new(),
+            });
+        }
+        let threshold = self.condition.threshold();
+        let selector = self.condition.matcher().map(ProcessMatcher::compile).transpose()?;
+        Ok(self
+            .condition
+            .evaluate(metrics, selector.as_ref())
+            .into_iter()
+            .filter(|(_, value)| *value > threshold)
+            .map(|(subject, value)| self.condition.message(&subject, value, metrics, selector.as_ref()))
+            .collect())
+    }
+
+    // Advances the alert for one subject; returns it once it has resolved.
+    // `for_secs` is the rule's own unless an expression's `for` clause overrides it.
+    fn update(&self, state: &mut RuleState, value: f64, message: String, for_secs: u64, now: DateTime<Utc>, next_id: &mut u64) -> Option<Alert> {
+        let threshold = self.condition.threshold();
+        let Some(alert) = &mut state.alert else {
+            if value > threshold {
+                state.alert = Some(Alert {
+                    id: *next_id,
+                    source: state.source.clone(),
+                    message: message.clone(),
+                    level: self.level.clone(),
+                    state: AlertState::Pending,
+                    started_at: now,
+                    fired_at: None,
+                    ended_at: None,
+                    acknowledged_at: None,
+                    snoozed_until: None,
+                    rule_name: self.name.clone(),
+                    labels: self.labels.clone(),
+                    silenced_by: None,
+                    escalation: 0,
+                    routed_to: Vec::new(),
+                });
+                *next_id += 1;
+                // With no duration configured the new alert fires straight away
+                return self.update(state, value, message, for_secs, now, next_id);
+            }
+            return None;
+        };
+        alert.message = message;
+        if alert.is_firing() {
+            // A clear threshold above the trigger would make the alert flap, so cap it
+            if value <= self.clear_threshold.unwrap_or(threshold).min(threshold) {
+                alert.state = AlertState::Resolved;
+                alert.ended_at = Some(now);
+                return state.alert.take();
+            }
+        } else if value > threshold {
+            let sustained = now - alert.started_at >= Duration::seconds(for_secs as i64);
+            let cooled = state.last_fired.is_none_or(|t| now - t >= Duration::seconds(self.cooldown_secs as i64));
+            if sustained && cooled {
+                alert.state = AlertState::Firing;
+                alert.fired_at = Some(now);
+                state.last_fired = Some(now);
+            }
+        } else {
+            // Never fired, so there is nothing worth keeping in the history
+            state.alert = None;
+        }
+        None
+    }
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+// Evaluation state of one rule for one subject (e.g. one mount point)
+struct RuleState {
+    source: String,
+    alert: Option<Alert>,
+    last_fired: Option<DateTime<Utc>>,
+}
+
+pub struct AlertManager {
+    pub rules: Vec<AlertRule>,
+    // Pending, firing and acknowledged alerts, oldest first
+    pub active_alerts: Vec<Alert>,
+    // Resolved alerts, oldest first
+    pub history: VecDeque<Alert>,
+    states: BTreeMap<(usize, String), RuleState>,
+    next_id: u64,
+    history_path: Option<PathBuf>,
+    // Expression rules by source text, parsed once when the rules are set
+    compiled: HashMap<String, Result<expr::Rule, expr::ParseError>>,
+    compiled_metrics: HashMap<String, Result<expr::Metric, expr::ParseError>>,
+    // Process rules by index, compiled once when the rules are set; invalid ones are left out
+    selectors: HashMap<usize, Selector>,
+    rates: expr::RateTracker,
+    // Learned baselines of anomaly rules by metric and model, kept across rule edits
+    baselines: HashMap<(String, BaselineModel), Baseline>,
+    forecaster: Forecaster,
+    notifier: Option<NotificationManager>,
+    pub silences: Vec<Silence>,
+    pub maintenance_windows: Vec<MaintenanceWindow>,
+    pub escalation_policies: Vec<EscalationPolicy>,
+}
+
+impl AlertManager {
+    pub fn new() -> Self {
+        Self::with_rules(vec![
+            AlertRule { for_secs: 30, clear_threshold: Some(80.0), cooldown_secs: 300, ..AlertRule::new(AlertCondition::CpuUsage { threshold: 90.0 }, AlertLevel::Warning) },
+            AlertRule { for_secs: 60, clear_threshold: Some(85.0), cooldown_secs: 300, ..AlertRule::new(AlertCondition::MemUsage { threshold: 90.0 }, AlertLevel::Warning) },
+            AlertRule { for_secs: 60, clear_threshold: Some(93.0), cooldown_secs: 600, ..AlertRule::new(AlertCondition::DiskUsage { threshold: 95.0 }, AlertLevel::Warning) },
+            // 1MB/s, cleared below 512KB/s
+            AlertRule { for_secs: 10, clear_threshold: Some(512.0 * 1024.0), cooldown_secs: 300, ..AlertRule::new(AlertCondition::NetRx { threshold: 1024 * 1024 }, AlertLevel::Info) },
+            AlertRule { for_secs: 10, clear_threshold: Some(512.0 * 1024.0), cooldown_secs: 300, ..AlertRule::new(AlertCondition::NetTx { threshold: 1024 * 1024 }, AlertLevel::Info) },
+            AlertRule { for_secs: 300, cooldown_secs: 3600, ..AlertRule::new(AlertCondition::DiskFull { within_secs: 12 * 3600 }, AlertLevel::Warning) },
+        ])
+    }
+
+    pub fn with_rules(rules: Vec<AlertRule>) -> Self {
+        let mut manager = Self {
+            rules: Vec::new(),
+            active_alerts: Vec::new(),
+            history: VecDeque::new(),
+            states: BTreeMap::new(),
+            next_id: 1,
+            history_path: None,
+            compiled: HashMap::new(),
+            compiled_metrics: HashMap::new(),
+            selectors: HashMap::new(),
+            rates: expr::RateTracker::default(),
+            baselines: HashMap::new(),
+            forecaster: Forecaster::default(),
+            notifier: None,
+            silences: Vec::new(),
+            maintenance_windows: Vec::new(),
+            escalation_policies: Vec::new(),
+        };
+        manager.set_rules(rules);
+        manager
+    }
+
+    // Loads resolved alerts from `path` and appends newly resolved ones to it.
+    // The id counter is kept next to it, since alerts still active at shutdown
+    // never reach the history and their ids must not be handed out again.
+    pub fn with_history(mut self, path: PathBuf) -> Self {
+        match load_history(&path) {
+            Ok(history) => {
+                self.next_id = history.iter().map(|a| a.id + 1).max().unwrap_or(1);
+                self.history = history;
+            }
+            Err(e) => report::error(format!("Failed to load alert history: {}", e)),
+        }
+        if let Some(id) = fs::read_to_string(path.with_extension("next_id")).ok().and_then(|s| s.trim().parse::<u64>().ok()) {
+            self.next_id = self.next_id.max(id);
+        }
+        self.history_path = Some(path);
+        self
+    }
+
+    pub fn with_notifier(mut self, notifier: NotificationManager) -> Self {
+        self.notifier = Some(notifier);
+        self
+    }
+
+    // Replaces the notification channels, e.g. after the config was edited
+    pub fn set_notifier(&mut self, notifier: Option<NotificationManager>) {
+        self.notifier = notifier;
+    }
+
+    pub fn with_silences(mut self, silences: Vec<Silence>, maintenance_windows: Vec<MaintenanceWindow>) -> Self {
+        self.set_silences(silences, maintenance_windows);
+        self
+    }
+
+    // Windows with unparseable times are kept but never match
+    pub fn set_silences(&mut self, silences: Vec<Silence>, maintenance_windows: Vec<MaintenanceWindow>) {
+        for window in &maintenance_windows {
+            if let Err(e) = window.validate() {
+                report::error(format!("Maintenance window ignored: {}", e));
+            }
+        }
+        self.silences = silences;
+        self.maintenance_windows = maintenance_windows;
+    }
+
+    pub fn with_escalation_policies(mut self, policies: Vec<EscalationPolicy>) -> Self {
+        self.set_escalation_policies(policies);
+        self
+    }
+
+    // Alerts already escalated keep their level and routing
+    pub fn set_escalation_policies(&mut self, mut policies: Vec<EscalationPolicy>) {
+        for policy in &mut policies {
+            policy.steps.sort_by_key(|step| step.after_secs);
+        }
+        self.escalation_policies = policies;
+    }
+
+    // The silence or maintenance window covering `alert` at `now`, described for display
+    pub fn silenced_by(&self, alert: &Alert, now: DateTime<Utc>) -> Option<String> {
+        if let Some(silence) = self.silences.iter().find(|s| s.is_active(now) && s.selector.matches(alert)) {
+            let until = silence.ends_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M");
+            return Some(match silence.comment.as_str() {
+                "" => format!("silenced until {}", until),
+                comment => format!("silenced until {}: {}", until, comment),
+            });
+        }
+        self.maintenance_windows
+            .iter()
+            .find(|w| w.is_active(now) && w.selector.matches(alert))
+            .map(|w| format!("maintenance window {}", w.name))
+    }
+
+    // Recent notification deliveries, if any channels are configured
+    pub fn delivery_log(&self) -> Option<DeliveryLog> {
+        self.notifier.as_ref().map(|n| n.log())
+    }
+
+    // Replaces the rules. Rules that are still present unchanged keep their alerts;
+    // firing alerts of removed or edited rules are resolved.
+    pub fn set_rules(&mut self, rules: Vec<AlertRule>) {
+        let now = Utc::now();
+        let states = std::mem::take(&mut self.states);
+        // Where each old rule ended up in the new list, if anywhere
+        let mut taken = HashSet::new();
+        let moved: Vec<Option<usize>> = self
+            .rules
+            .iter()
+            .map(|old| {
+                let index = (0..rules.len()).find(|i| !taken.contains(i) && rules[*i] == *old)?;
+                taken.insert(index);
+                Some(index)
+            })
+            .collect();
+        for ((index, subject), state) in states {
+            match moved.get(index).copied().flatten() {
+                Some(target) => {
+                    self.states.insert((target, subject), state);
+                }
+                None => {
+                    if let Some(mut alert) = state.alert.filter(Alert::is_firing) {
+                        alert.state = AlertState::Resolved;
+                        alert.ended_at = Some(now);
+                        self.close(alert);
+                    }
+                }
+            }
+        }
+        self.compiled = rules
+            .iter()
+            .filter_map(|rule| match &rule.condition {
+                AlertCondition::Expr { expr } => Some((expr.clone(), expr::parse(expr))),
+                _ => None,
+            })
+            .collect();
+        self.compiled_metrics = rules
+            .iter()
+            .filter_map(|rule| match &rule.condition {
+                AlertCondition::Anomaly { metric, .. } => Some((metric.clone(), expr::parse_metric(metric))),
+                _ => None,
+            })
+            .collect();
+        self.selectors = rules
+            .iter()
+            .enumerate()
+            .filter_map(|(index, rule)| {
+                let matcher = rule.condition.matcher()?;
+                rule.validate().ok()?;
+                Some((index, matcher.compile().ok()?))
+            })
+            .collect();
+        self.baselines.retain(|key, _| rules.iter().any(|rule| anomaly_key(rule).as_ref() == Some(key)));
+        self.rules = rules;
+        self.refresh_active();
+    }
+
+    // Rules that fail validation, as (rule index, error); such rules never fire
+    pub fn rule_errors(&self) -> Vec<(usize, String)> {
+        self.rules.iter().enumerate().filter_map(|(index, rule)| rule.validate().err().map(|e| (index, e))).collect()
+    }
+
+    // Feeds stored samples (oldest first) into the anomaly baselines and forecasts so
+    // they do not have to be learned from scratch on every start
+    pub fn learn_from(&mut self, samples: &[Metrics]) {
+        let mut rates = expr::RateTracker::default();
+        for sample in samples {
+            self.forecaster.push(sample);
+            let mut learned = HashSet::new();
+            for rule in self.rules.iter().filter(|rule| rule.enabled) {
+                let Some(key) = anomaly_key(rule).filter(|key| !learned.contains(key)) else { continue };
+                let Some(Ok(metric)) = self.compiled_metrics.get(&key.0) else { continue };
+                if let Some(value) = metric.value(sample, &mut rates) {
+                    self.baselines.entry(key.clone()).or_insert_with(|| Baseline::new(key.1.clone())).update(value, sample.timestamp);
+                }
+                learned.insert(key);
+            }
+            rates.advance();
+        }
+    }
+
+    // The current deviation of an anomaly rule, for previews:
+    // (value as text, deviation in standard deviations), None while still learning
+    pub fn anomaly_score(&self, rule: &AlertRule, metrics: &Metrics) -> Option<(String, f64)> {
+        let key = anomaly_key(rule)?;
+        let metric = expr::parse_metric(&key.0).ok()?;
+        let value = metric.value(metrics, &mut expr::RateTracker::default())?;
+        let (sigmas, _, _) = self.baselines.get(&key)?.score(value, metrics.timestamp)?;
+        Some((metric.format(value), sigmas))
+    }
+
+    pub fn forecaster(&self) -> &Forecaster {
+        &self.forecaster
+    }
+
+    // Like `AlertRule::preview`, but also covers rules that depend on learned state
+    pub fn preview(&self, rule: &AlertRule, metrics: &Metrics) -> Result<Vec<String>, String> {
+        match rule.condition {
+            AlertCondition::DiskFull { .. } | AlertCondition::MemoryFull { .. } => Ok(forecast_samples(&rule.condition, metrics, &self.forecaster)
+                .into_iter()
+                .filter(|(_, value, _)| *value > 0.5)
+                .map(|(_, _, message)| message)
+                .collect()),
+            _ => rule.preview(metrics),
+        }
+    }
+
+    pub fn check(&mut self, metrics: &Metrics) {
+        self.check_at(metrics, Utc::now());
+    }
+
+    fn check_at(&mut self, metrics: &Metrics, now: DateTime<Utc>) {
+        let mut seen = HashSet::new();
+        let mut resolved = Vec::new();
+        let mut learned = HashSet::new();
+        let mut fired = Vec::new();
+        let first_id = self.next_id;
+        self.forecaster.push(metrics);
+        for (index, rule) in self.rules.iter().enumerate().filter(|(_, rule)| rule.enabled) {
+            let mut for_secs = rule.for_secs;
+            let samples: Vec<(String, f64, String)> = match &rule.condition {
+                AlertCondition::Expr { expr } => {
+                    let Some(Ok(compiled)) = self.compiled.get(expr) else { continue };
+                    for_secs = compiled.for_secs.unwrap_or(for_secs);
+                    // A missing metric yields no sample, so a firing alert resolves
+                    match compiled.evaluate(metrics, &mut self.rates) {
+                        Some(matched) => {
+                            let message = format!("Rule `{}` matched: {}", expr, compiled.describe(metrics));
+                            vec![(String::new(), if matched { 1.0 } else { 0.0 }, message)]
+                        }
+                        None => Vec::new(),
+                    }
+                }
+                AlertCondition::Anomaly { metric: source, .. } => {
+                    let Some(key) = anomaly_key(rule) else { continue };
+                    let Some(Ok(metric)) = self.compiled_metrics.get(source) else { continue };
+                    let Some(value) = metric.value(metrics, &mut self.rates) else { continue };
+                    let baseline = self.baselines.entry(key.clone()).or_insert_with(|| Baseline::new(key.1.clone()));
+                    // Scored before learning the value, so an outlier is not part of its own baseline
+                    let score = baseline.score(value, metrics.timestamp);
+                    if learned.insert(key) {
+                        baseline.update(value, metrics.timestamp);
+                    }
+                    match score {
+                        Some((sigmas, mean, std)) => {
+                            let direction = if sigmas >= 0.0 { "above" } else { "below" };
+                            let message = format!(
+                                "{} is {}, {:.1}σ {} normal ({} ± {})",
+                                source,
+                                metric.format(value),
+                                sigmas.abs(),
+                                direction,
+                                metric.format(mean),
+                                metric.format(std)
+                            );
+                            vec![(String::new(), sigmas.abs(), message)]
+                        }
+                        None => Vec::new(),
+                    }
+                }
+                AlertCondition::DiskFull { .. } | AlertCondition::MemoryFull { .. } => forecast_samples(&rule.condition, metrics, &self.forecaster),
+                // Invalid process rules (bad regex, empty range) have no selector and never fire
+                condition if condition.matcher().is_some() && !self.selectors.contains_key(&index) => Vec::new(),
+                condition => condition
+                    .evaluate(metrics, self.selectors.get(&index))
+                    .into_iter()
+                    .map(|(subject, value)| {
+                        let message = condition.message(&subject, value, metrics, self.selectors.get(&index));
+                        (subject, value, message)
+                    })
+                    .collect(),
+            };
+            for (subject, value, message) in samples {
+                let state = self.states.entry((index, subject.clone())).or_insert_with(|| RuleState {
+                    source: format!("{} {}", rule.condition.name(), subject).trim_end().to_string(),
+                    alert: None,
+                    last_fired: None,
+                });
+                let was_firing = state.alert.as_ref().is_some_and(Alert::is_firing);
+                resolved.extend(rule.update(state, value, message, for_secs, now, &mut self.next_id));
+                if let Some(alert) = state.alert.as_ref().filter(|a| a.is_firing() && !was_firing) {
+                    fired.push(alert.clone());
+                }
+                seen.insert((index, subject));
+            }
+        }
+        self.rates.advance();
+        if self.next_id != first_id {
+            self.save_next_id();
+        }
+        // Subjects that disappeared (e.g. an unmounted disk) resolve their alerts
+        let gone: Vec<(usize, String)> = self.states.keys().filter(|key| !seen.contains(*key)).cloned().collect();
+        for key in gone {
+            if let Some(mut alert) = self.states.remove(&key).and_then(|s| s.alert) {
+                if alert.is_firing() {
+                    alert.state = AlertState::Resolved;
+                    alert.ended_at = Some(now);
+                    resolved.push(alert);
+                }
+            }
+        }
+        self.recheck_silences(now);
+        for alert in fired {
+            match self.silenced_by(&alert, now) {
+                Some(reason) => {
+                    if let Some(active) = self.find_active(alert.id) {
+                        active.silenced_by = Some(reason);
+                    }
+                }
+                None => self.notify(&alert),
+            }
+        }
+        self.escalate(now);
+        for alert in resolved {
+            self.close(alert);
+        }
+        self.refresh_active();
+    }
+
+    pub fn acknowledge(&mut self, id: u64) {
+        let now = Utc::now();
+        if let Some(alert) = self.find_active(id) {
+            if alert.state == AlertState::Firing {
+                alert.state = AlertState::Acknowledged;
+                alert.acknowledged_at = Some(now);
+            }
+        }
+        self.refresh_active();
+    }
+
+    // Hides the alert until `until`; it stays active and shows again if still firing then
+    pub fn snooze(&mut self, id: u64, until: Option<DateTime<Utc>>) {
+        if let Some(alert) = self.find_active(id) {
+            alert.snoozed_until = until;
+        }
+        self.refresh_active();
+    }
+
+    fn find_active(&mut self, id: u64) -> Option<&mut Alert> {
+        self.states.values_mut().filter_map(|s| s.alert.as_mut()).find(|a| a.id == id)
+    }
+
+    fn refresh_active(&mut self) {
+        let mut active: Vec<Alert> = self.states.values().filter_map(|s| s.alert.clone()).collect();
+        active.sort_by_key(|a| a.id);
+        self.active_alerts = active;
+    }
+
+    // Hands an alert that started firing or resolved to the notification channels.
+    // Snoozed alerts stay quiet.
+    fn notify(&self, alert: &Alert) {
+        if let Some(notifier) = &self.notifier {
+            if !alert.is_snoozed(Utc::now()) {
+                notifier.dispatch(alert);
+            }
+        }
+    }
+
+    // Alerts silenced when they fired stay silenced only while a silence or window
+    // still covers them. Once it lapses they are notified, as if they had just fired,
+    // so escalation and the recovery reach the channels too.
+    fn recheck_silences(&mut self, now: DateTime<Utc>) {
+        let silenced: Vec<(u64, Option<String>)> = self
+            .states
+            .values()
+            .filter_map(|s| s.alert.as_ref())
+            .filter(|a| a.is_firing() && a.silenced_by.is_some())
+            .map(|a| (a.id, self.silenced_by(a, now)))
+            .collect();
+        for (id, reason) in silenced {
+            let Some(alert) = self.find_active(id) else { continue };
+            let lapsed = reason.is_none();
+            alert.silenced_by = reason;
+            if lapsed {
+                let alert = alert.clone();
+                self.notify(&alert);
+            }
+        }
+    }
+
+    // Moves unacknowledged firing alerts through their escalation policy and
+    // notifies about every alert that was escalated
+    fn escalate(&mut self, now: DateTime<Utc>) {
+        let mut escalated = Vec::new();
+        for ((index, _), state) in self.states.iter_mut() {
+            let Some(alert) = state.alert.as_mut().filter(|a| a.state == AlertState::Firing) else {
+                continue;
+            };
+            let (Some(fired_at), Some(rule)) = (alert.fired_at, self.rules.get(*index)) else {
+                continue;
+            };
+            let original = Alert { level: rule.level.clone(), ..alert.clone() };
+            let Some(policy) = self.escalation_policies.iter().find(|p| p.selector.matches(&original)) else {
+                continue;
+            };
+            if policy.apply(alert, now - fired_at) {
+                escalated.push(alert.clone());
+            }
+        }
+        for alert in escalated {
+            if alert.silenced_by.is_none() && self.silenced_by(&alert, now).is_none() {
+                self.notify(&alert);
+            }
+        }
+    }
+
+    // Notifies and records a resolved alert. Alerts that fired while silenced
+    // also resolve quietly, so channels never see a recovery without its alert;
+    // alerts that were notified always get their recovery, even if a silence
+    // has started since.
+    fn close(&mut self, alert: Alert) {
+        if alert.silenced_by.is_none() {
+            self.notify(&alert);
+        }
+        self.record(alert);
+    }
+
+    fn save_next_id(&self) {
+        if let Some(path) = &self.history_path {
+            let written = path.parent().map_or(Ok(()), fs::create_dir_all).and_then(|_| fs::write(path.with_extension("next_id"), self.next_id.to_string()));
+            if let Err(e) = written {
+                report::error(format!("Failed to write alert id counter: {}", e));
+            }
+        }
+    }
+
+    fn record(&mut self, alert: Alert) {
+        if let Some(path) = &self.history_path {
+            if let Err(e) = append_history(path, &alert) {
+                report::error(format!("Failed to write alert history: {}", e));
+            }
+        }
+        if self.history.len() >= MAX_HISTORY {
+            self.history.pop_front();
+        }
+        self.history.push_back(alert);
+    }
+}
+
+// The history file holds one resolved alert per JSON line. It is trimmed to the
+// newest MAX_HISTORY entries on load so it cannot grow without bound.
+fn load_history(path: &std::path::Path) -> std::io::Result<VecDeque<Alert>> {
+    if !path.exists() {
+        return Ok(VecDeque::new());
+    }
+    let reader = BufReader::new(File::open(path)?);
+    let mut lines = 0;
+    let mut history = VecDeque::new();
+    for line in reader.lines().map_while(Result::ok) {
+        lines += 1;
+        if let Ok(alert) = serde_json::from_str::<Alert>(&line) {
+            if history.len() >= MAX_HISTORY {
+                history.pop_front();
+            }
+            history.push_back(alert);
+        }
+    }
+    if lines > MAX_HISTORY {
+        let tmp = path.with_extension("tmp");
+        {
+            let mut file = File::create(&tmp)?;
+            for alert in &history {
+                writeln!(file, "{}", serde_json::to_string(alert)?)?;
+            }
+        }
+        fs::rename(&tmp, path)?;
+    }
+    Ok(history)
+}
+
+fn append_history(path: &std::path::Path, alert: &Alert) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(alert)?)
+}
+
+// One sample per forecast subject, 1.0 when it is due to fill up within the rule's horizon.
+// Subjects without enough history to forecast are left out.
+fn forecast_samples(condition: &AlertCondition, metrics: &Metrics, forecaster: &Forecaster) -> Vec<(String, f64, String)> {
+    let describe = |what: &str, within_secs: u64, f: forecast::Forecast| {
+        let soon = f.time_to_full.is_some_and(|t| t <= Duration::seconds(within_secs as i64));
+        let message = match f.time_to_full {
+            Some(t) => format!(
+                "{} will be full in {} (free space shrinking {:.2} GB/h)",
+                what,
+                forecast::format_eta(t),
+                f.bytes_per_hour / 1024.0 / 1024.0 / 1024.0
+            ),
+            None => format!("{} is no longer filling up", what),
+        };
+        (if soon { 1.0 } else { 0.0 }, message)
+    };
+    match condition {
+        AlertCondition::DiskFull { within_secs } => metrics
+            .disks
+            .iter()
+            .filter_map(|disk| {
+                let (value, message) = describe(&disk.mount_point, *within_secs, forecaster.disk(&disk.mount_point)?);
+                Some((disk.mount_point.clone(), value, message))
+            })
+            .collect(),
+        AlertCondition::MemoryFull { within_secs } => forecaster
+            .memory()
+            .map(|f| {
+                let (value, message) = describe("Memory", *within_secs, f);
+                (String::new(), value, message)
+            })
+            .into_iter()
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn anomaly_key(rule: &AlertRule) -> Option<(String, BaselineModel)> {
+    match &rule.condition {
+        AlertCondition::Anomaly { metric, model, .. } => Some((metric.clone(), model.clone())),
+        _ => None,
+    }
+}
+
+fn interface_suffix(metrics: &Metrics) -> String {
+    metrics.selected_interface.as_ref().map(|i| format!(" on {}", i)).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::temp_dir;
+    use silence::AlertSelector;
+    use std::sync::mpsc::Receiver;
+
+    fn cpu(usage: f32) -> Metrics {
+        Metrics { cpu_total: usage, ..Default::default() }
+    }
+
+    fn manager(rules: Vec<AlertRule>) -> (AlertManager, Receiver<Alert>) {
+        let (notifier, rx) = NotificationManager::capture();
+        (AlertManager::with_rules(rules).with_notifier(notifier), rx)
+    }
+
+    fn cpu_rule(threshold: f32) -> AlertRule {
+        AlertRule::new(AlertCondition::CpuUsage { threshold }, AlertLevel::Warning)
+    }
+
+    // Drives one rule for one subject, the way `check` does
+    struct Driver {
+        rule: AlertRule,
+        state: RuleState,
+        next_id: u64,
+        t0: DateTime<Utc>,
+    }
+
+    impl Driver {
+        fn new(rule: AlertRule) -> Self {
+            Self { rule, state: RuleState { source: "CpuUsage".to_string(), alert: None, last_fired: None }, next_id: 1, t0: Utc::now() }
+        }
+
+        fn step(&mut self, secs: i64, value: f64) -> Option<Alert> {
+            let now = self.t0 + Duration::seconds(secs);
+            self.rule.update(&mut self.state, value, format!("value {}", value), self.rule.for_secs, now, &mut self.next_id)
+        }
+
+        fn alert_state(&self) -> Option<AlertState> {
+            self.state.alert.as_ref().map(|a| a.state)
+        }
+    }
+
+    #[test]
+    fn fires_once_the_condition_held_for_long_enough() {
+        let mut d = Driver::new(AlertRule { for_secs: 60, ..cpu_rule(50.0) });
+        assert!(d.step(0, 40.0).is_none());
+        assert_eq!(d.alert_state(), None);
+        d.step(10, 90.0);
+        assert_eq!(d.alert_state(), Some(AlertState::Pending));
+        d.step(60, 90.0);
+        assert_eq!(d.alert_state(), Some(AlertState::Pending));
+        d.step(70, 95.0);
+        let alert = d.state.alert.clone().unwrap();
+        assert_eq!((alert.id, alert.state, alert.message.as_str()), (1, AlertState::Firing, "value 95"));
+        assert_eq!(alert.started_at, d.t0 + Duration::seconds(10));
+        assert_eq!(alert.fired_at, Some(d.t0 + Duration::seconds(70)));
+        assert_eq!(d.next_id, 2);
+    }
+
+    #[test]
+    fn pending_alert_that_recovers_is_dropped() {
+        let mut d = Driver::new(AlertRule { for_secs: 60, ..cpu_rule(50.0) });
+        d.step(0, 90.0);
+        assert!(d.step(30, 40.0).is_none());
+        assert_eq!(d.alert_state(), None);
+        // The next breach starts over with a new id
+        d.step(40, 90.0);
+        assert_eq!(d.state.alert.as_ref().map(|a| a.id), Some(2));
+    }
+
+    #[test]
+    fn resolves_only_below_the_clear_threshold() {
+        let mut d = Driver::new(AlertRule { clear_threshold: Some(80.0), ..cpu_rule(90.0) });
+        d.step(0, 95.0);
+        assert_eq!(d.alert_state(), Some(AlertState::Firing));
+        assert!(d.step(10, 85.0).is_none());
+        assert_eq!(d.alert_state(), Some(AlertState::Firing));
+        let resolved = d.step(20, 80.0).unwrap();
+        assert_eq!((resolved.state, resolved.ended_at), (AlertState::Resolved, Some(d.t0 + Duration::seconds(20))));
+        assert_eq!(d.alert_state(), None);
+    }
+
+    #[test]
+    fn clear_threshold_above_the_trigger_is_capped() {
+        let mut d = Driver::new(AlertRule { clear_threshold: Some(95.0), ..cpu_rule(90.0) });
+        d.step(0, 99.0);
+        assert!(d.step(10, 92.0).is_none());
+        assert!(d.step(20, 90.0).is_some());
+    }
+
+    #[test]
+    fn cooldown_delays_firing_again() {
+        let mut d = Driver::new(AlertRule { cooldown_secs: 300, ..cpu_rule(50.0) });
+        d.step(0, 90.0);
+        assert!(d.step(10, 10.0).is_some());
+        d.step(20, 90.0);
+        assert_eq!(d.alert_state(), Some(AlertState::Pending));
+        d.step(299, 90.0);
+        assert_eq!(d.alert_state(), Some(AlertState::Pending));
+        d.step(300, 90.0);
+        let alert = d.state.alert.clone().unwrap();
+        assert_eq!((alert.id, alert.state), (2, AlertState::Firing));
+        // Started when the value first went back over, not when the cooldown ended
+        assert_eq!(alert.started_at, d.t0 + Duration::seconds(20));
+    }
+
+    #[test]
+    fn acknowledges_only_firing_alerts() {
+        let t0 = Utc::now();
+        let (mut am, _rx) = manager(vec![cpu_rule(50.0), AlertRule { for_secs: 60, ..cpu_rule(70.0) }]);
+        am.check_at(&cpu(90.0), t0);
+        let (firing, pending) = (am.active_alerts[0].id, am.active_alerts[1].id);
+        am.acknowledge(firing);
+        am.acknowledge(pending);
+        assert_eq!(am.active_alerts[0].state, AlertState::Acknowledged);
+        assert!(am.active_alerts[0].acknowledged_at.is_some());
+        assert_eq!((am.active_alerts[1].state, am.active_alerts[1].acknowledged_at), (AlertState::Pending, None));
+
+        // Acknowledged alerts keep their state until they resolve
+        am.check_at(&cpu(90.0), t0 + Duration::seconds(10));
+        assert_eq!(am.active_alerts[0].state, AlertState::Acknowledged);
+        am.check_at(&cpu(10.0), t0 + Duration::seconds(20));
+        assert!(am.active_alerts.is_empty());
+        let resolved = am.history.front().unwrap();
+        assert_eq!((resolved.id, resolved.state), (firing, AlertState::Resolved));
+        assert!(resolved.acknowledged_at.is_some());
+    }
+
+    #[test]
+    fn snoozed_alerts_stay_quiet_until_the_snooze_ends() {
+        let now = Utc::now();
+        let (mut am, rx) = manager(vec![cpu_rule(50.0), cpu_rule(60.0)]);
+        am.check_at(&cpu(90.0), now);
+        assert_eq!(rx.try_iter().count(), 2);
+        let (snoozed, expired) = (am.active_alerts[0].id, am.active_alerts[1].id);
+        am.snooze(snoozed, Some(now + Duration::hours(1)));
+        am.snooze(expired, Some(now - Duration::seconds(1)));
+        assert!(am.active_alerts[0].is_snoozed(now));
+        assert!(!am.active_alerts[0].is_snoozed(now + Duration::hours(1)));
+        assert!(!am.active_alerts[1].is_snoozed(now));
+
+        // Only the alert whose snooze has run out reports its recovery
+        am.check_at(&cpu(10.0), now + Duration::seconds(10));
+        let sent: Vec<u64> = rx.try_iter().map(|a| a.id).collect();
+        assert_eq!(sent, vec![expired]);
+        assert_eq!(am.history.len(), 2);
+
+        // Snoozing with no end clears it
+        am.check_at(&cpu(90.0), now + Duration::seconds(20));
+        let id = am.active_alerts[0].id;
+        am.snooze(id, Some(now + Duration::hours(1)));
+        am.snooze(id, None);
+        assert_eq!(am.active_alerts[0].snoozed_until, None);
+    }
+
+    #[test]
+    fn ids_and_history_survive_a_restart() {
+        let path = temp_dir("alert-ids").join("alerts.jsonl");
+        let t0 = Utc::now();
+        let mut am = AlertManager::with_rules(vec![cpu_rule(50.0), cpu_rule(80.0)]).with_history(path.clone());
+        am.check_at(&cpu(90.0), t0);
+        am.check_at(&cpu(60.0), t0 + Duration::seconds(10));
+        // Alert 1 is still active at shutdown; alert 2 resolved and was recorded
+        assert_eq!(am.active_alerts.iter().map(|a| a.id).collect::<Vec<_>>(), vec![1]);
+        assert_eq!(fs::read_to_string(path.with_extension("next_id")).unwrap(), "3");
+        drop(am);
+
+        let mut am = AlertManager::with_rules(vec![cpu_rule(50.0)]).with_history(path.clone());
+        assert_eq!(am.history.iter().map(|a| (a.id, a.state)).collect::<Vec<_>>(), vec![(2, AlertState::Resolved)]);
+        am.check_at(&cpu(90.0), t0 + Duration::seconds(20));
+        assert_eq!(am.active_alerts[0].id, 3);
+        am.check_at(&cpu(10.0), t0 + Duration::seconds(30));
+        drop(am);
+
+        let am = AlertManager::with_rules(Vec::new()).with_history(path.clone());
+        assert_eq!(am.history.iter().map(|a| a.id).collect::<Vec<_>>(), vec![2, 3]);
+        assert_eq!(am.next_id, 4);
+    }
+
+    fn escalating(rules: Vec<AlertRule>) -> (AlertManager, Receiver<Alert>) {
+        let step = escalation::EscalationStep { after_secs: 900, level: Some(AlertLevel::Critical), channels: vec!["pager".to_string()] };
+        let policy = EscalationPolicy {
+            name: "unacked warnings".to_string(),
+            selector: AlertSelector { level: Some(AlertLevel::Warning), ..Default::default() },
+            steps: vec![step],
+        };
+        let (am, rx) = manager(rules);
+        (am.with_escalation_policies(vec![policy]), rx)
+    }
+
+    #[test]
+    fn unacknowledged_warning_escalates_after_15_minutes() {
+        let t0 = Utc::now();
+        let (mut am, rx) = escalating(vec![cpu_rule(50.0)]);
+        am.check_at(&cpu(90.0), t0);
+        let fired = rx.try_recv().unwrap();
+        assert_eq!((fired.level, fired.escalation), (AlertLevel::Warning, 0));
+
+        am.check_at(&cpu(90.0), t0 + Duration::minutes(14));
+        assert!(rx.try_recv().is_err());
+        am.check_at(&cpu(90.0), t0 + Duration::minutes(15));
+        let escalated = rx.try_recv().unwrap();
+        assert_eq!((escalated.id, escalated.level, escalated.escalation), (fired.id, AlertLevel::Critical, 1));
+        assert_eq!(escalated.routed_to, vec!["pager"]);
+        am.check_at(&cpu(90.0), t0 + Duration::minutes(30));
+        assert!(rx.try_recv().is_err());
+
+        // The recovery carries the routing, so the pager hears about it too
+        am.check_at(&cpu(10.0), t0 + Duration::minutes(31));
+        let resolved = rx.try_recv().unwrap();
+        assert_eq!((resolved.state, resolved.level), (AlertState::Resolved, AlertLevel::Critical));
+        assert_eq!(resolved.routed_to, vec!["pager"]);
+    }
+
+    #[test]
+    fn acknowledging_or_resolving_stops_escalation() {
+        let t0 = Utc::now();
+        let (mut am, rx) = escalating(vec![cpu_rule(50.0), AlertRule { clear_threshold: Some(75.0), ..cpu_rule(80.0) }]);
+        am.check_at(&cpu(90.0), t0);
+        assert_eq!(rx.try_iter().count(), 2);
+        let acked = am.active_alerts[0].id;
+        am.acknowledge(acked);
+        am.check_at(&cpu(70.0), t0 + Duration::minutes(5));
+        assert_eq!(rx.try_recv().unwrap().state, AlertState::Resolved);
+
+        am.check_at(&cpu(70.0), t0 + Duration::minutes(20));
+        assert!(rx.try_recv().is_err());
+        assert_eq!(am.active_alerts[0].id, acked);
+        assert_eq!((am.active_alerts[0].level.clone(), am.active_alerts[0].escalation), (AlertLevel::Warning, 0));
+    }
+
+    #[test]
+    fn policies_match_the_level_the_rule_raised() {
+        let t0 = Utc::now();
+        let info = AlertRule::new(AlertCondition::CpuUsage { threshold: 50.0 }, AlertLevel::Info);
+        let (mut am, rx) = escalating(vec![info]);
+        am.check_at(&cpu(90.0), t0);
+        am.check_at(&cpu(90.0), t0 + Duration::hours(1));
+        assert_eq!(rx.try_iter().count(), 1);
+        assert_eq!(am.active_alerts[0].escalation, 0);
+    }
+
+    #[test]
+    fn lapsed_silence_notifies_a_still_firing_alert() {
+        let t0 = Utc::now();
+        let silence = Silence {
+            selector: AlertSelector { rule: Some("CpuUsage".to_string()), ..Default::default() },
+            starts_at: t0,
+            ends_at: t0 + Duration::hours(1),
+            comment: "patching".to_string(),
+        };
+        let (mut am, rx) = manager(vec![cpu_rule(50.0)]);
+        am.set_silences(vec![silence], Vec::new());
+
+        am.check_at(&cpu(90.0), t0 + Duration::minutes(1));
+        am.check_at(&cpu(90.0), t0 + Duration::minutes(59));
+        assert!(rx.try_recv().is_err());
+        assert!(am.active_alerts[0].silenced_by.as_deref().is_some_and(|s| s.ends_with("patching")));
+
+        am.check_at(&cpu(90.0), t0 + Duration::minutes(61));
+        let alert = rx.try_recv().unwrap();
+        assert_eq!((alert.state, alert.silenced_by), (AlertState::Firing, None));
+        assert_eq!(am.active_alerts[0].silenced_by, None);
+
+        am.check_at(&cpu(10.0), t0 + Duration::minutes(62));
+        assert_eq!(rx.try_recv().unwrap().state, AlertState::Resolved);
+        assert!(rx.try_recv().is_err());
+    }
+}
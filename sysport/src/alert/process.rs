@@ -0,0 +1,145 @@
+// Selecting processes for per-process alert rules
+use crate::metrics::ProcessMetrics;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ProcessMatcher {
+    // Exact process name, e.g. "nginx" (case-insensitive)
+    Name(String),
+    // Regular expression searched in the command line, or the name if that is empty
+    Regex(String),
+    // File holding the pid, e.g. "/run/nginx.pid"; re-read on every check
+    PidFile(String),
+}
+
+impl ProcessMatcher {
+    pub fn validate(&self) -> Result<(), String> {
+        self.compile().map(|_| ())
+    }
+
+    // Built once when the rules are set, so a regex is not recompiled on every check
+    pub fn compile(&self) -> Result<Selector, String> {
+        match self {
+            ProcessMatcher::Name(name) if name.trim().is_empty() => Err("process name is empty".to_string()),
+            ProcessMatcher::Name(name) => Ok(Selector::Name(name.trim().to_string())),
+            ProcessMatcher::Regex(pattern) => Regex::new(pattern).map(Selector::Regex).map_err(|e| e.to_string()),
+            ProcessMatcher::PidFile(path) if path.trim().is_empty() => Err("pid file path is empty".to_string()),
+            ProcessMatcher::PidFile(path) => Ok(Selector::PidFile(path.clone())),
+        }
+    }
+
+    // Short form for alert messages
+    pub fn describe(&self) -> String {
+        match self {
+            ProcessMatcher::Name(name) => name.clone(),
+            ProcessMatcher::Regex(pattern) => format!("/{}/", pattern),
+            ProcessMatcher::PidFile(path) => format!("pid file {}", path),
+        }
+    }
+}
+
+// A compiled ProcessMatcher
+pub enum Selector {
+    Name(String),
+    Regex(Regex),
+    PidFile(String),
+}
+
+impl Selector {
+    // Processes currently matching; none if the pid file is unreadable
+    pub fn select<'a>(&self, processes: &'a [ProcessMetrics]) -> Vec<&'a ProcessMetrics> {
+        match self {
+            Selector::Name(name) => processes.iter().filter(|p| p.name.eq_ignore_ascii_case(name)).collect(),
+            Selector::Regex(re) => processes
+                .iter()
+                .filter(|p| if p.cmdline.is_empty() { re.is_match(&p.name) } else { re.is_match(&p.cmdline) })
+                .collect(),
+            Selector::PidFile(path) => {
+                let Some(pid) = fs::read_to_string(path).ok().and_then(|s| s.trim().parse::<u32>().ok()) else {
+                    return Vec::new();
+                };
+                processes.iter().filter(|p| p.pid == pid).collect()
+            }
+        }
+    }
+}
+
+// Subject for one process, so each matching pid gets its own alert
+pub fn subject(process: &ProcessMetrics) -> String {
+    format!("{} [{}]", process.name, process.pid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alert::{AlertCondition, AlertLevel, AlertManager, AlertRule, AlertState};
+    use crate::metrics::Metrics;
+    use crate::testutil::temp_dir;
+    use chrono::Utc;
+
+    fn process(pid: u32, name: &str, cmdline: &str) -> ProcessMetrics {
+        ProcessMetrics { pid, name: name.to_string(), cmdline: cmdline.to_string(), ..Default::default() }
+    }
+
+    fn processes() -> Vec<ProcessMetrics> {
+        vec![
+            process(100, "nginx", "nginx: master process /usr/sbin/nginx"),
+            process(101, "nginx", "nginx: worker process"),
+            process(200, "Postgres", ""),
+            process(300, "python3", "python3 /srv/app/worker.py --queue mail"),
+        ]
+    }
+
+    fn pids(matcher: ProcessMatcher) -> Vec<u32> {
+        matcher.compile().unwrap().select(&processes()).iter().map(|p| p.pid).collect()
+    }
+
+    #[test]
+    fn names_match_exactly_ignoring_case() {
+        assert_eq!(pids(ProcessMatcher::Name(" nginx ".into())), [100, 101]);
+        assert_eq!(pids(ProcessMatcher::Name("postgres".into())), [200]);
+        assert_eq!(pids(ProcessMatcher::Name("ngin".into())), Vec::<u32>::new());
+        assert!(ProcessMatcher::Name("  ".into()).validate().is_err());
+    }
+
+    #[test]
+    fn regexes_search_the_command_line_or_else_the_name() {
+        assert_eq!(pids(ProcessMatcher::Regex(r"worker\.py --queue (mail|sms)".into())), [300]);
+        assert_eq!(pids(ProcessMatcher::Regex("^nginx: worker".into())), [101]);
+        assert_eq!(pids(ProcessMatcher::Regex("^Post".into())), [200]);
+        assert!(ProcessMatcher::Regex("(".into()).validate().is_err());
+    }
+
+    #[test]
+    fn pid_files_are_read_on_every_check() {
+        let path = temp_dir("process-pidfile").join("app.pid");
+        let matcher = ProcessMatcher::PidFile(path.to_str().unwrap().to_string());
+        let selector = matcher.compile().unwrap();
+        assert!(selector.select(&processes()).is_empty());
+        fs::write(&path, "300\n").unwrap();
+        assert_eq!(selector.select(&processes())[0].pid, 300);
+        fs::write(&path, "101").unwrap();
+        assert_eq!(selector.select(&processes())[0].pid, 101);
+        fs::write(&path, "not a pid").unwrap();
+        assert!(selector.select(&processes()).is_empty());
+        assert!(ProcessMatcher::PidFile(String::new()).validate().is_err());
+    }
+
+    #[test]
+    fn missing_processes_fire_until_one_matches() {
+        let rule = AlertRule::new(AlertCondition::ProcessMissing { matcher: ProcessMatcher::Name("redis-server".into()) }, AlertLevel::Critical);
+        let mut manager = AlertManager::with_rules(vec![rule]);
+        let now = Utc::now();
+        let mut metrics = Metrics { processes: processes(), ..Default::default() };
+
+        manager.check_at(&metrics, now);
+        let alert = &manager.active_alerts[0];
+        assert_eq!((alert.state, alert.message.as_str()), (AlertState::Firing, "Process redis-server is not running"));
+
+        metrics.processes.push(process(400, "redis-server", "redis-server *:6379"));
+        manager.check_at(&metrics, now + chrono::Duration::seconds(10));
+        assert!(manager.active_alerts.is_empty());
+    }
+}
@@ -0,0 +1,157 @@
+// Silences and maintenance windows: matching alerts are still tracked and kept
+// in the history, but are not sent to notification channels
+use super::{Alert, AlertLevel};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveTime, TimeZone, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// Which alerts a silence applies to. Unset fields match everything, so an empty
+// selector silences all alerts.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AlertSelector {
+    // Rule name ("api-latency"), or rule kind optionally with its subject:
+    // "DiskUsage" or "DiskUsage /var"
+    pub rule: Option<String>,
+    pub level: Option<AlertLevel>,
+    // Every label must be set on the alert's rule with this value
+    pub labels: BTreeMap<String, String>,
+}
+
+impl AlertSelector {
+    pub fn matches(&self, alert: &Alert) -> bool {
+        let rule_matches = self.rule.as_deref().is_none_or(|rule| {
+            alert.rule_name.as_deref() == Some(rule)
+                || alert.source == rule
+                || alert.source.strip_prefix(rule).is_some_and(|rest| rest.starts_with(' '))
+        });
+        rule_matches
+            && self.level.as_ref().is_none_or(|level| *level == alert.level)
+            && self.labels.iter().all(|(key, value)| alert.labels.get(key) == Some(value))
+    }
+
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(rule) = &self.rule {
+            parts.push(rule.clone());
+        }
+        if let Some(level) = &self.level {
+            parts.push(format!("{:?}", level));
+        }
+        parts.extend(self.labels.iter().map(|(key, value)| format!("{}={}", key, value)));
+        if parts.is_empty() {
+            "all alerts".to_string()
+        } else {
+            parts.join(", ")
+        }
+    }
+}
+
+// A one-off silence, e.g. for planned work
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Silence {
+    #[serde(flatten)]
+    pub selector: AlertSelector,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    #[serde(default)]
+    pub comment: String,
+}
+
+impl Silence {
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.starts_at <= now && now < self.ends_at
+    }
+}
+
+// A weekly recurring window in local time, e.g. Sundays 02:00-04:00.
+// An end before the start runs past midnight.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MaintenanceWindow {
+    pub name: String,
+    #[serde(flatten)]
+    pub selector: AlertSelector,
+    // Days the window starts on ("Sun", "Monday", ...); empty means every day
+    #[serde(default)]
+    pub days: Vec<Weekday>,
+    // "HH:MM"
+    pub start: String,
+    pub end: String,
+}
+
+impl MaintenanceWindow {
+    fn times(&self) -> Result<(NaiveTime, NaiveTime), String> {
+        let parse = |s: &str| NaiveTime::parse_from_str(s.trim(), "%H:%M").map_err(|_| format!("maintenance window {}: expected HH:MM, got {:?}", self.name, s));
+        Ok((parse(&self.start)?, parse(&self.end)?))
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        self.times().map(|_| ())
+    }
+
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        let Ok((start, end)) = self.times() else {
+            return false;
+        };
+        let local = now.with_timezone(&Local);
+        // A window that started yesterday may still be running past midnight
+        [local.date_naive(), local.date_naive() - Duration::days(1)].into_iter().any(|day| {
+            if !self.days.is_empty() && !self.days.contains(&day.weekday()) {
+                return false;
+            }
+            let end_day = if end > start { day } else { day + Duration::days(1) };
+            // Skipped or repeated local times around DST changes resolve to the earliest
+            let (Some(from), Some(until)) = (
+                Local.from_local_datetime(&day.and_time(start)).earliest(),
+                Local.from_local_datetime(&end_day.and_time(end)).earliest(),
+            ) else {
+                return false;
+            };
+            from <= local && local < until
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alert::{AlertCondition, AlertManager, AlertRule};
+    use crate::metrics::{DiskMetrics, Metrics};
+
+    // The alerts an expression rule and a disk rule actually raise
+    fn alerts(name: Option<&str>) -> Vec<Alert> {
+        let expr = AlertRule { name: name.map(str::to_string), ..AlertRule::new(AlertCondition::Expr { expr: "cpu_total > 50".to_string() }, AlertLevel::Warning) };
+        let disk = AlertRule::new(AlertCondition::DiskUsage { threshold: 90.0 }, AlertLevel::Critical);
+        let metrics = Metrics {
+            cpu_total: 90.0,
+            disks: vec![DiskMetrics { mount_point: "/var".to_string(), total: 100, available: 5, ..Default::default() }],
+            ..Default::default()
+        };
+        let mut am = AlertManager::with_rules(vec![expr, disk]);
+        am.check_at(&metrics, Utc::now());
+        am.active_alerts
+    }
+
+    fn rule(rule: &str) -> AlertSelector {
+        AlertSelector { rule: Some(rule.to_string()), ..Default::default() }
+    }
+
+    #[test]
+    fn rule_matches_name_kind_or_subject() {
+        let named = alerts(Some("api-latency"));
+        let (expr, disk) = (&named[0], &named[1]);
+        assert_eq!((expr.source.as_str(), disk.source.as_str()), ("Expr", "DiskUsage /var"));
+        assert!(rule("api-latency").matches(expr));
+        assert!(rule("Expr").matches(expr));
+        assert!(!rule("api").matches(expr));
+        assert!(!rule("Exp").matches(expr));
+        assert!(!rule("db-latency").matches(expr));
+        assert!(!rule("api-latency").matches(&alerts(None)[0]));
+
+        assert!(rule("DiskUsage").matches(disk));
+        assert!(rule("DiskUsage /var").matches(disk));
+        assert!(!rule("DiskUsage /va").matches(disk));
+        assert!(!rule("DiskUsage /home").matches(disk));
+        assert!(!rule("Expr").matches(disk));
+    }
+}
@@ -1,21 +1,34 @@
-use crate::metrics::{Metrics, DiskMetrics};
-use crate::alert::{AlertManager, AlertLevel, AlertRule};
-use crate::export::{export_log, export_metrics, ExportFormat};
+use crate::metrics::{Metrics, ProcessMetrics};
+use crate::history::{MetricsHistory, Resolution};
+use crate::storage::{self, StorageConfig};
+use crate::collector::Collector;
+use crate::capture::{self, RawPacketInfo};
+use crate::config::{Config, CONFIG_VERSION};
+use crate::alert::anomaly::BaselineModel;
+use crate::alert::forecast::{format_eta, Forecast};
+use crate::alert::process::ProcessMatcher;
+use crate::alert::silence::{AlertSelector, Silence};
+use crate::alert::{expr, AlertCondition, AlertLevel, AlertManager, AlertRule, AlertState};
+use crate::notifications::queue::{DeliveryRecord, DeliveryStatus};
+use crate::export::{export_capture, export_log, export_metrics, format_for_path, import_file, Capture, ExportFormat};
 use crate::theme::CustomTheme;
 use crate::packet_stats::{PacketStats, decode_protocol};
 use crate::remote::ExampleServers;
 use crate::plugins::PluginSystem;
+#[cfg(feature = "prometheus")]
+use crate::prometheus;
+use crate::push;
 
 use eframe::{egui, epi};
-use sysinfo::{System, SystemExt, DiskExt, NetworkExt, NetworksExt, ProcessorExt};
+use egui::plot::{Line, Plot, Value, Values};
+use sysinfo::{System, SystemExt};
 use std::sync::{Arc, Mutex};
-use std::thread;
 use std::time::{Duration, Instant};
 use std::collections::VecDeque;
 use std::net::IpAddr;
-use pcap::Capture;
 use maxminddb::geoip2;
 use std::fs;
+use std::path::PathBuf;
 use regex::Regex;
 
 // In plotting, use:
@@ -36,28 +49,31 @@ fn draw_gauge_arc(painter: &egui::Painter, center: egui::Pos2, radius: f32, star
     painter.add(egui::Shape::line(points, egui::Stroke::new(thickness, color)));
 }
 
-pub struct RawPacketInfo {
-    pub timestamp: std::time::Instant,
-    pub data: Vec<u8>,
-    pub src: Option<IpAddr>,
-    pub dst: Option<IpAddr>,
-    pub src_port: Option<u16>,
-    pub dst_port: Option<u16>,
-    pub protocol: String,
-    pub country: Option<String>,
-    pub app: Option<String>,
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProcessSortKey {
+    Pid,
+    Name,
+    User,
+    Cpu,
+    Memory,
+    VirtualMemory,
+    DiskRead,
+    DiskWrite,
+    StartTime,
 }
 
 pub struct SysPortApp {
     pub metrics: Arc<Mutex<Metrics>>,
-    pub history: Arc<Mutex<Vec<Metrics>>>,
+    pub history: Arc<Mutex<MetricsHistory>>,
+    pub history_resolution: Resolution,
     pub update_interval: f32, // seconds
     pub paused: bool,
     pub last_update: Instant,
     pub system: System,
-    pub alert_manager: AlertManager,
+    pub alert_manager: Arc<Mutex<AlertManager>>,
     pub export_status: Option<String>,
     pub selected_interface: Option<String>,
+    pub interface_selection: Arc<Mutex<Option<String>>>,
     pub protocol_tcp: bool,
     pub protocol_udp: bool,
     pub protocol_icmp: bool,
@@ -75,29 +91,41 @@ pub struct SysPortApp {
     pub packet_search: String,
     pub custom_theme: CustomTheme,
     pub use_custom_theme: bool,
-    pub stats: PacketStats,
+    pub stats: Arc<Mutex<PacketStats>>,
     pub plugin_system: PluginSystem,
     pub raw_packets: Arc<Mutex<VecDeque<RawPacketInfo>>>,
     pub geoip_reader: Option<maxminddb::Reader<Vec<u8>>>,
+    pub process_search: String,
+    pub process_sort: ProcessSortKey,
+    pub process_sort_desc: bool,
+    pub imported_history: Vec<Metrics>,
+    pub storage_config: StorageConfig,
+    pub export_stored: bool,
+    pub geoip_path: String,
+    pub plugin_dir: String,
+    pub remote_addr: String,
+    pub config_path: PathBuf,
+    // Settings as last applied; `file_config` is what the file holds, without command-line overrides
+    pub saved_config: Config,
+    pub file_config: Config,
+    pub config_changed_at: Option<Instant>,
+    pub config_status: Option<String>,
+    pub alert_rules: Vec<AlertRule>,
+    pub silences: Vec<Silence>,
+    pub rule_input: String,
+    pub rule_level: AlertLevel,
 }
 
-impl Default for SysPortApp {
-    fn default() -> Self {
-        let metrics = Arc::new(Mutex::new(Metrics::default()));
-        let history = Arc::new(Mutex::new(Vec::with_capacity(300)));
-        let update_interval = 1.0;
+impl SysPortApp {
+    pub fn new(config: Config, file_config: Config, config_path: PathBuf) -> Self {
+        let history_resolution = Resolution::Raw;
+        let update_interval = config.update_interval;
         let paused = false;
         let last_update = Instant::now();
         let mut system = System::new_all();
         system.refresh_all();
-        let alert_manager = AlertManager::new();
-        // Remove memory usage warnings
-        let alert_manager = {
-            let mut am = alert_manager;
-            am.rules.retain(|rule| !matches!(rule, AlertRule::MemUsage { .. }));
-            am
-        };
-        let custom_theme = CustomTheme::default();
+        let alert_manager = config.alert_manager();
+        let custom_theme = config.theme.clone();
         let export_status = None;
         let selected_interface = None;
         let protocol_tcp = true;
@@ -106,132 +134,53 @@ impl Default for SysPortApp {
         let protocol_arp = true;
         let packet_log = Arc::new(Mutex::new(VecDeque::with_capacity(1000)));
         let max_packet_log = 1000;
-        let proxy_port = 8888;
-        let reverse_proxy_port = 8889;
-        let reverse_proxy_target = "127.0.0.1:80".to_string();
-        let dns_port = 5353;
+        let proxy_port = config.proxy_port;
+        let reverse_proxy_port = config.reverse_proxy_port;
+        let reverse_proxy_target = config.reverse_proxy_target.clone();
+        let dns_port = config.dns_port;
         let proxy_running = false;
         let reverse_proxy_running = false;
         let dns_running = false;
-        let packet_filter = String::new();
+        let packet_filter = config.packet_filter.clone();
         let packet_search = String::new();
-        let use_custom_theme = true;
-        let stats = PacketStats::default();
+        let use_custom_theme = config.use_custom_theme;
+        let stats = Arc::new(Mutex::new(PacketStats::default()));
         let plugin_system = PluginSystem::new();
         let raw_packets = Arc::new(Mutex::new(VecDeque::with_capacity(10000)));
+        let process_search = String::new();
+        let process_sort = ProcessSortKey::Cpu;
+        let process_sort_desc = true;
+        let imported_history = Vec::new();
+        let storage_config = config.storage.clone();
+        let export_stored = false;
+        let geoip_path = config.geoip_path.clone();
+        let plugin_dir = config.plugin_dir.clone();
+        let remote_addr = config.remote_addr.clone();
+        let alert_rules = config.alert_rules.clone();
+        let silences = config.silences.clone();
         let mut geoip_reader = None;
-        if let Ok(data) = fs::read("GeoLite2-Country.mmdb") {
+        if let Ok(data) = fs::read(&geoip_path) {
             if let Ok(reader) = maxminddb::Reader::from_source(data) {
                 geoip_reader = Some(reader);
             }
         }
 
-        // Spawn background thread for polling system metrics
-        let metrics_clone = metrics.clone();
-        let history_clone = history.clone();
-        thread::spawn(move || {
-            let mut sys = System::new_all();
-            let mut last_rx = 0;
-            let mut last_tx = 0;
-            loop {
-                thread::sleep(Duration::from_millis(200));
-                sys.refresh_cpu();
-                sys.refresh_memory();
-                sys.refresh_disks_list();
-                sys.refresh_disks();
-                sys.refresh_networks();
-                let cpus = sys.processors();
-                let cpu_usages: Vec<f32> = cpus.iter().map(|c| c.cpu_usage()).collect();
-                let cpu_total = cpu_usages.iter().sum::<f32>() / cpu_usages.len().max(1) as f32;
-                let mem_total = sys.total_memory();
-                let mem_used = sys.used_memory();
-                let disks = sys.disks().iter().map(|d| DiskMetrics {
-                    name: d.name().to_string_lossy().to_string(),
-                    total: d.total_space(),
-                    available: d.available_space(),
-                }).collect();
-                let net = sys.networks();
-                let rx = net.iter().map(|(_, data)| data.received()).sum();
-                let tx = net.iter().map(|(_, data)| data.transmitted()).sum();
-                let net_rx = if last_rx == 0 { 0 } else { rx - last_rx };
-                let net_tx = if last_tx == 0 { 0 } else { tx - last_tx };
-                last_rx = rx;
-                last_tx = tx;
-                let m = Metrics {
-                    timestamp: Instant::now(),
-                    cpu_usage: cpu_usages,
-                    cpu_total,
-                    mem_total,
-                    mem_used,
-                    disks,
-                    net_rx,
-                    net_tx,
-                    selected_interface: None,
-                    interfaces: vec![],
-                    net_per_interface: vec![],
-                };
-                // Store latest metrics
-                if let Ok(mut lock) = metrics_clone.lock() {
-                    *lock = m.clone();
-                }
-                // Store history for plotting
-                if let Ok(mut hist) = history_clone.lock() {
-                    hist.push(m);
-                    if hist.len() > 300 { hist.remove(0); }
-                }
-            }
-        });
-
-        // Spawn background thread for global packet capture (scaffold)
-        let raw_packets_clone = raw_packets.clone();
-        let geoip_reader_for_thread = geoip_reader.take();
-        std::thread::spawn(move || {
-            let device = pcap::Device::lookup().unwrap().unwrap();
-            if let Ok(mut cap) = Capture::from_device(device.name.as_str()).unwrap().promisc(true).open() {
-                while let Ok(packet) = cap.next_packet() {
-                    let data = packet.data.to_vec();
-                    // Parse IP/port/protocol (IPv4 only for now)
-                    let (src, dst, src_port, dst_port, proto) = if data.len() > 34 && (data[12] == 0x08 && data[13] == 0x00) {
-                        // IPv4
-                        let src = IpAddr::from([data[26], data[27], data[28], data[29]]);
-                        let dst = IpAddr::from([data[30], data[31], data[32], data[33]]);
-                        let proto = data[23];
-                        let (src_port, dst_port, proto_str) = match proto {
-                            6 => (Some(u16::from_be_bytes([data[34], data[35]])), Some(u16::from_be_bytes([data[36], data[37]])), "TCP"),
-                            17 => (Some(u16::from_be_bytes([data[34], data[35]])), Some(u16::from_be_bytes([data[36], data[37]])), "UDP"),
-                            1 => (None, None, "ICMP"),
-                            _ => (None, None, "IPv4"),
-                        };
-                        (Some(src), Some(dst), src_port, dst_port, proto_str.to_string())
-                    } else {
-                        (None, None, None, None, "Other".to_string())
-                    };
-                    let country = src.and_then(|ip| geoip_reader_for_thread.as_ref().and_then(|g| {
-                        if let Ok(geo) = g.lookup::<geoip2::Country>(ip) {
-                            geo.country.and_then(|c| c.iso_code).map(|s| s.to_string())
-                        } else { None }
-                    }));
-                    let pkt = RawPacketInfo {
-                        timestamp: std::time::Instant::now(),
-                        data,
-                        src,
-                        dst,
-                        src_port,
-                        dst_port,
-                        protocol: proto,
-                        country,
-                        app: None, // TODO: per-app mapping
-                    };
-                    let mut lock = raw_packets_clone.lock().unwrap();
-                    if lock.len() > 10000 { lock.pop_front(); }
-                    lock.push_back(pkt);
-                }
-            }
-        });
+        let collector = Collector::spawn(Some(storage_config.clone()), alert_manager);
+        let metrics = collector.metrics.clone();
+        let history = collector.history.clone();
+        let interface_selection = collector.interface_selection.clone();
+        let alert_manager = collector.alert_manager.clone();
+        capture::spawn_capture(None, raw_packets.clone(), stats.clone(), geoip_reader.take());
+        #[cfg(feature = "prometheus")]
+        if let Some(addr) = &config.prometheus_addr {
+            prometheus::spawn(addr, prometheus::Sources { metrics: metrics.clone(), packets: stats.clone(), alert_manager: alert_manager.clone() });
+        }
+        push::start(&config.push, &collector);
 
         Self {
             metrics,
             history,
+            history_resolution,
             update_interval,
             paused,
             last_update,
@@ -239,6 +188,7 @@ impl Default for SysPortApp {
             alert_manager,
             export_status,
             selected_interface,
+            interface_selection,
             protocol_tcp,
             protocol_udp,
             protocol_icmp,
@@ -260,11 +210,571 @@ impl Default for SysPortApp {
             plugin_system,
             raw_packets,
             geoip_reader,
+            process_search,
+            process_sort,
+            process_sort_desc,
+            imported_history,
+            storage_config,
+            export_stored,
+            geoip_path,
+            plugin_dir,
+            remote_addr,
+            config_path,
+            saved_config: config,
+            file_config,
+            config_changed_at: None,
+            config_status: None,
+            alert_rules,
+            silences,
+            rule_input: String::new(),
+            rule_level: AlertLevel::Warning,
+        }
+    }
+
+    // Settings as currently shown in the window
+    fn current_config(&self) -> Config {
+        Config {
+            version: CONFIG_VERSION,
+            update_interval: self.update_interval,
+            proxy_port: self.proxy_port,
+            reverse_proxy_port: self.reverse_proxy_port,
+            reverse_proxy_target: self.reverse_proxy_target.clone(),
+            dns_port: self.dns_port,
+            packet_filter: self.packet_filter.clone(),
+            geoip_path: self.geoip_path.clone(),
+            plugin_dir: self.plugin_dir.clone(),
+            remote_addr: self.remote_addr.clone(),
+            prometheus_addr: self.saved_config.prometheus_addr.clone(),
+            push: self.saved_config.push.clone(),
+            alert_rules: self.alert_rules.clone(),
+            notifications: self.saved_config.notifications.clone(),
+            silences: self.silences.clone(),
+            maintenance_windows: self.saved_config.maintenance_windows.clone(),
+            escalation_policies: self.saved_config.escalation_policies.clone(),
+            use_custom_theme: self.use_custom_theme,
+            theme: self.custom_theme.clone(),
+            storage: self.storage_config.clone(),
+        }
+    }
+
+    fn apply_config(&mut self, config: Config) {
+        self.update_interval = config.update_interval;
+        self.proxy_port = config.proxy_port;
+        self.reverse_proxy_port = config.reverse_proxy_port;
+        self.reverse_proxy_target = config.reverse_proxy_target.clone();
+        self.dns_port = config.dns_port;
+        self.packet_filter = config.packet_filter.clone();
+        self.geoip_path = config.geoip_path.clone();
+        self.plugin_dir = config.plugin_dir.clone();
+        self.remote_addr = config.remote_addr.clone();
+        self.use_custom_theme = config.use_custom_theme;
+        self.custom_theme = config.theme.clone();
+        self.storage_config = config.storage.clone();
+        self.alert_rules = config.alert_rules.clone();
+        self.push_alert_rules();
+        if config.notifications != self.saved_config.notifications {
+            self.alert_manager.lock().unwrap().set_notifier(config.notifier());
+        }
+        self.silences = config.silences.clone();
+        self.alert_manager.lock().unwrap().set_silences(config.silences.clone(), config.maintenance_windows.clone());
+        if config.escalation_policies != self.saved_config.escalation_policies {
+            self.alert_manager.lock().unwrap().set_escalation_policies(config.escalation_policies.clone());
+        }
+        self.file_config = config.clone();
+        self.saved_config = config;
+    }
+
+    fn push_alert_rules(&self) {
+        self.alert_manager.lock().unwrap().set_rules(self.alert_rules.clone());
+    }
+
+    // Unlike rule edits, silences apply at once; the config is saved as usual
+    fn push_silences(&self) {
+        self.alert_manager.lock().unwrap().set_silences(self.silences.clone(), self.saved_config.maintenance_windows.clone());
+    }
+
+    fn save_config(&mut self) {
+        let config = self.current_config();
+        // Rule edits take effect once they have settled, like the save itself
+        if config.alert_rules != self.saved_config.alert_rules {
+            self.push_alert_rules();
+        }
+        // Only what was edited here is written; command-line overrides stay out of the file
+        let file_config = self.file_config.with_changes(&self.saved_config, &config);
+        self.config_status = Some(match file_config.save(&self.config_path) {
+            Ok(()) => format!("Settings saved to {}", self.config_path.display()),
+            Err(e) => format!("Failed to save settings: {}", e),
+        });
+        // Remember it even on failure so a read-only file is not retried every frame
+        self.file_config = file_config;
+        self.saved_config = config;
+        self.config_changed_at = None;
+    }
+
+    // Saves once edits have settled, so dragging a slider does not write on every frame
+    fn autosave_config(&mut self) {
+        if self.current_config() == self.saved_config {
+            self.config_changed_at = None;
+            return;
+        }
+        let changed_at = *self.config_changed_at.get_or_insert_with(Instant::now);
+        if changed_at.elapsed() >= Duration::from_secs(1) {
+            self.save_config();
+        }
+    }
+
+    // A full capture for JSON export: the samples, alerts raised since the first
+    // of them, and the packet counters
+    fn capture_for_export(&self) -> Capture {
+        let mut capture = Capture::new(self.export_history());
+        capture.host = self.system.host_name().unwrap_or_default();
+        if let Some(start) = capture.samples.first().map(|m| m.timestamp) {
+            let am = self.alert_manager.lock().unwrap();
+            capture.alerts = am.history.iter().chain(&am.active_alerts).filter(|a| a.ended_at.is_none_or(|t| t >= start)).cloned().collect();
+        }
+        let stats = self.stats.lock().unwrap().clone();
+        if stats.tcp_count + stats.udp_count + stats.icmp_count + stats.arp_count > 0 {
+            capture.packets = Some(stats);
+        }
+        capture
+    }
+
+    // Samples for export: the in-memory raw window, or everything retained on disk
+    fn export_history(&self) -> Vec<Metrics> {
+        if self.export_stored {
+            let window = chrono::Duration::seconds(self.storage_config.retention_secs as i64);
+            match storage::load_since(&self.storage_config.dir, window) {
+                Ok(samples) if !samples.is_empty() => return samples,
+                Ok(_) => {}
+                Err(e) => eprintln!("Failed to read stored history: {}", e),
+            }
+        }
+        self.history.lock().unwrap().raw_samples()
+    }
+
+    fn alerts_panel(&mut self, ui: &mut egui::Ui) {
+        let now = chrono::Utc::now();
+        let (active, history, silenced) = {
+            let am = self.alert_manager.lock().unwrap();
+            let silenced: Vec<Option<String>> = am.active_alerts.iter().map(|a| am.silenced_by(a, now).or_else(|| a.silenced_by.clone())).collect();
+            (am.active_alerts.clone(), am.history.iter().rev().take(50).cloned().collect::<Vec<_>>(), silenced)
+        };
+        let firing = active.iter().filter(|a| a.state == AlertState::Firing && !a.is_snoozed(now)).count();
+        let title = if firing > 0 { format!("Alerts ({} firing)", firing) } else { "Alerts".to_string() };
+        let level_color = |level: &AlertLevel| -> egui::Color32 {
+            match level {
+                AlertLevel::Info => self.custom_theme.alert_info.into(),
+                AlertLevel::Warning => self.custom_theme.alert_warning.into(),
+                AlertLevel::Critical => self.custom_theme.alert_critical.into(),
+            }
+        };
+        let mut acknowledge = None;
+        let mut snooze = None;
+        let mut silence = None;
+        egui::CollapsingHeader::new(title).id_source("alerts_panel").default_open(true).show(ui, |ui| {
+            if active.is_empty() {
+                ui.label("No active alerts");
+            } else {
+                egui::Grid::new("active_alerts").striped(true).show(ui, |ui| {
+                    for header in ["State", "Level", "Message", "Since", ""] {
+                        ui.label(header);
+                    }
+                    ui.end_row();
+                    for (alert, silenced_by) in active.iter().zip(&silenced) {
+                        match (alert.snoozed_until.filter(|_| alert.is_snoozed(now)), silenced_by) {
+                            (Some(until), _) => ui.label(format!("Snoozed until {}", until.with_timezone(&chrono::Local).format("%H:%M"))),
+                            (None, Some(reason)) => ui.label(format!("{:?}, silenced", alert.state)).on_hover_text(reason),
+                            (None, None) => ui.label(format!("{:?}", alert.state)),
+                        };
+                        if alert.escalation > 0 {
+                            let routed = if alert.routed_to.is_empty() { String::new() } else { format!(", sent to {}", alert.routed_to.join(", ")) };
+                            ui.label(egui::RichText::new(format!("{:?} (escalated)", alert.level)).color(level_color(&alert.level)))
+                                .on_hover_text(format!("Escalation step {}{}", alert.escalation, routed));
+                        } else {
+                            ui.label(egui::RichText::new(format!("{:?}", alert.level)).color(level_color(&alert.level)));
+                        }
+                        ui.label(&alert.message);
+                        ui.label(format_uptime((now - alert.started_at).num_seconds().max(0) as u64));
+                        ui.horizontal(|ui| {
+                            if alert.state == AlertState::Firing && ui.button("Acknowledge").clicked() {
+                                acknowledge = Some(alert.id);
+                            }
+                            if alert.is_snoozed(now) {
+                                if ui.button("Unsnooze").clicked() {
+                                    snooze = Some((alert.id, None));
+                                }
+                            } else if alert.is_firing() {
+                                if ui.button("Snooze 15m").clicked() {
+                                    snooze = Some((alert.id, Some(now + chrono::Duration::minutes(15))));
+                                }
+                                if ui.button("Snooze 1h").clicked() {
+                                    snooze = Some((alert.id, Some(now + chrono::Duration::hours(1))));
+                                }
+                            }
+                            // Snoozing hides one alert; silencing mutes the rule's notifications
+                            if silenced_by.is_none() && ui.button("Silence 2h").on_hover_text("Stop notifications for this rule and subject for 2 hours").clicked() {
+                                silence = Some(alert.source.clone());
+                            }
+                        });
+                        ui.end_row();
+                    }
+                });
+            }
+            ui.collapsing(format!("History ({} resolved)", history.len()), |ui| {
+                egui::Grid::new("alert_history").striped(true).show(ui, |ui| {
+                    for header in ["Started", "Duration", "Level", "Message", "Acknowledged"] {
+                        ui.label(header);
+                    }
+                    ui.end_row();
+                    for alert in &history {
+                        let end = alert.ended_at.unwrap_or(now);
+                        ui.label(alert.started_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S").to_string());
+                        ui.label(format_uptime((end - alert.started_at).num_seconds().max(0) as u64));
+                        ui.label(egui::RichText::new(format!("{:?}", alert.level)).color(level_color(&alert.level)));
+                        match &alert.silenced_by {
+                            Some(reason) => ui.label(format!("{} (not notified)", alert.message)).on_hover_text(reason),
+                            None => ui.label(&alert.message),
+                        };
+                        ui.label(alert.acknowledged_at.map(|t| t.with_timezone(&chrono::Local).format("%H:%M:%S").to_string()).unwrap_or_default());
+                        ui.end_row();
+                    }
+                });
+            });
+        });
+        self.alert_rules_panel(ui);
+        self.silences_panel(ui);
+        self.notification_center(ui);
+        if let Some(id) = acknowledge {
+            self.alert_manager.lock().unwrap().acknowledge(id);
+        }
+        if let Some((id, until)) = snooze {
+            self.alert_manager.lock().unwrap().snooze(id, until);
+        }
+        if let Some(source) = silence {
+            self.silences.push(Silence {
+                selector: AlertSelector { rule: Some(source), ..AlertSelector::default() },
+                starts_at: now,
+                ends_at: now + chrono::Duration::hours(2),
+                comment: "Silenced from the Alerts panel".to_string(),
+            });
+            self.push_silences();
+        }
+    }
+
+    // Silences (editable) and maintenance windows (from the config file)
+    fn silences_panel(&mut self, ui: &mut egui::Ui) {
+        let now = chrono::Utc::now();
+        let local = |t: chrono::DateTime<chrono::Utc>| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string();
+        let windows = self.saved_config.maintenance_windows.clone();
+        let mut remove = None;
+        let mut changed = false;
+        ui.collapsing(format!("Silences ({})", self.silences.iter().filter(|s| s.is_active(now)).count()), |ui| {
+            if self.silences.is_empty() {
+                ui.label("No silences. Use \"Silence 2h\" on an active alert to add one.");
+            } else {
+                egui::Grid::new("silences").striped(true).show(ui, |ui| {
+                    for header in ["Matches", "From", "Until", "Status", "Comment", ""] {
+                        ui.label(header);
+                    }
+                    ui.end_row();
+                    for (index, silence) in self.silences.iter_mut().enumerate() {
+                        ui.label(silence.selector.describe());
+                        ui.label(local(silence.starts_at));
+                        ui.label(local(silence.ends_at));
+                        ui.label(if silence.is_active(now) {
+                            "Active"
+                        } else if silence.ends_at <= now {
+                            "Expired"
+                        } else {
+                            "Scheduled"
+                        });
+                        changed |= ui.text_edit_singleline(&mut silence.comment).changed();
+                        ui.horizontal(|ui| {
+                            if silence.is_active(now) && ui.button("+1h").clicked() {
+                                silence.ends_at += chrono::Duration::hours(1);
+                                changed = true;
+                            }
+                            if silence.is_active(now) && ui.button("End").clicked() {
+                                silence.ends_at = now;
+                                changed = true;
+                            }
+                            if ui.button("Delete").clicked() {
+                                remove = Some(index);
+                            }
+                        });
+                        ui.end_row();
+                    }
+                });
+            }
+            if !windows.is_empty() {
+                ui.separator();
+                ui.label("Maintenance windows (edit in the config file):");
+                egui::Grid::new("maintenance_windows").striped(true).show(ui, |ui| {
+                    for header in ["Name", "Days", "Time", "Matches", "Status"] {
+                        ui.label(header);
+                    }
+                    ui.end_row();
+                    for window in &windows {
+                        ui.label(&window.name);
+                        ui.label(if window.days.is_empty() {
+                            "Every day".to_string()
+                        } else {
+                            window.days.iter().map(|d| d.to_string()).collect::<Vec<_>>().join(", ")
+                        });
+                        ui.label(format!("{}-{}", window.start, window.end));
+                        ui.label(window.selector.describe());
+                        match window.validate() {
+                            Ok(()) => ui.label(if window.is_active(now) { "Active" } else { "" }),
+                            Err(e) => ui.colored_label(egui::Color32::RED, e),
+                        };
+                        ui.end_row();
+                    }
+                });
+            }
+        });
+        if let Some(index) = remove {
+            self.silences.remove(index);
+            changed = true;
+        }
+        if changed {
+            self.push_silences();
+        }
+    }
+
+    // Recent notification deliveries per channel, newest first
+    fn notification_center(&self, ui: &mut egui::Ui) {
+        let Some(log) = self.alert_manager.lock().unwrap().delivery_log() else {
+            return;
+        };
+        let records: Vec<DeliveryRecord> = log.lock().unwrap().iter().rev().take(50).cloned().collect();
+        let failed = records.iter().filter(|r| r.status == DeliveryStatus::Failed).count();
+        let title = if failed > 0 { format!("Notifications ({} failed)", failed) } else { "Notifications".to_string() };
+        ui.collapsing(title, |ui| {
+            if records.is_empty() {
+                ui.label("Nothing sent yet");
+                return;
+            }
+            egui::Grid::new("notification_center").striped(true).show(ui, |ui| {
+                for header in ["Time", "Channel", "Status", "Alert", "Detail"] {
+                    ui.label(header);
+                }
+                ui.end_row();
+                for record in &records {
+                    let color: egui::Color32 = match record.status {
+                        DeliveryStatus::Delivered => self.custom_theme.alert_info.into(),
+                        DeliveryStatus::Retrying | DeliveryStatus::Suppressed => self.custom_theme.alert_warning.into(),
+                        DeliveryStatus::Failed => self.custom_theme.alert_critical.into(),
+                    };
+                    ui.label(record.at.with_timezone(&chrono::Local).format("%H:%M:%S").to_string());
+                    ui.label(&record.channel);
+                    ui.label(egui::RichText::new(format!("{:?}", record.status)).color(color));
+                    ui.label(&record.summary);
+                    ui.label(&record.detail);
+                    ui.end_row();
+                }
+            });
+        });
+    }
+
+    // Lists every alert rule for editing. Changes are saved and applied to the
+    // alert manager once they settle (see `autosave_config`).
+    fn alert_rules_panel(&mut self, ui: &mut egui::Ui) {
+        let metrics = self.metrics.lock().unwrap().clone();
+        let levels = [AlertLevel::Info, AlertLevel::Warning, AlertLevel::Critical];
+        let mut remove = None;
+        let mut add_expr = false;
+        ui.collapsing(format!("Alert Rules ({})", self.alert_rules.len()), |ui| {
+            egui::Grid::new("alert_rules").striped(true).show(ui, |ui| {
+                for header in ["On", "Condition", "Threshold", "Level", "For (s)", "Clear at", "Cooldown (s)", "Now", ""] {
+                    ui.label(header);
+                }
+                ui.end_row();
+                for (index, rule) in self.alert_rules.iter_mut().enumerate() {
+                    ui.checkbox(&mut rule.enabled, "");
+                    egui::ComboBox::from_id_source(("rule_kind", index))
+                        .selected_text(condition_kind(&rule.condition))
+                        .show_ui(ui, |ui| {
+                            for kind in CONDITION_KINDS {
+                                if ui.selectable_label(condition_kind(&rule.condition) == kind, kind).clicked() && condition_kind(&rule.condition) != kind {
+                                    rule.condition = default_condition(kind);
+                                    rule.clear_threshold = None;
+                                }
+                            }
+                        });
+                    // Network thresholds are stored in bytes/s but edited in KB/s, RSS in MB
+                    let scale = match rule.condition {
+                        AlertCondition::NetRx { .. } | AlertCondition::NetTx { .. } => 1024.0,
+                        AlertCondition::ProcessRss { .. } => 1024.0 * 1024.0,
+                        _ => 1.0,
+                    };
+                    match &mut rule.condition {
+                        AlertCondition::CpuUsage { threshold } | AlertCondition::MemUsage { threshold } | AlertCondition::DiskUsage { threshold } => {
+                            ui.add(egui::DragValue::new(threshold).clamp_range(0.0..=100.0).suffix("%"));
+                        }
+                        AlertCondition::NetRx { threshold } | AlertCondition::NetTx { threshold } => {
+                            let mut kb = *threshold as f64 / scale;
+                            if ui.add(egui::DragValue::new(&mut kb).clamp_range(0.0..=f64::MAX).suffix(" KB/s")).changed() {
+                                *threshold = (kb * scale) as u64;
+                            }
+                        }
+                        AlertCondition::Expr { expr: source } => {
+                            ui.add(egui::TextEdit::singleline(source).code_editor().desired_width(260.0));
+                        }
+                        AlertCondition::ProcessCpu { matcher, threshold } => {
+                            ui.horizontal(|ui| {
+                                matcher_editor(ui, index, matcher);
+                                ui.add(egui::DragValue::new(threshold).clamp_range(0.0..=f32::MAX).suffix("%"));
+                            });
+                        }
+                        AlertCondition::ProcessRss { matcher, threshold } => {
+                            ui.horizontal(|ui| {
+                                matcher_editor(ui, index, matcher);
+                                let mut mb = *threshold as f64 / scale;
+                                if ui.add(egui::DragValue::new(&mut mb).clamp_range(0.0..=f64::MAX).suffix(" MB")).changed() {
+                                    *threshold = (mb * scale) as u64;
+                                }
+                            });
+                        }
+                        AlertCondition::ProcessCount { matcher, min, max } => {
+                            ui.horizontal(|ui| {
+                                matcher_editor(ui, index, matcher);
+                                ui.label("min");
+                                ui.add(egui::DragValue::new(min));
+                                let mut limited = max.is_some();
+                                ui.checkbox(&mut limited, "max");
+                                if limited {
+                                    let value = max.get_or_insert(*min);
+                                    ui.add(egui::DragValue::new(value));
+                                } else {
+                                    *max = None;
+                                }
+                            });
+                        }
+                        AlertCondition::ProcessMissing { matcher } => {
+                            matcher_editor(ui, index, matcher);
+                        }
+                        AlertCondition::DiskFull { within_secs } | AlertCondition::MemoryFull { within_secs } => {
+                            ui.horizontal(|ui| {
+                                ui.label("full within");
+                                let mut hours = *within_secs as f64 / 3600.0;
+                                if ui.add(egui::DragValue::new(&mut hours).clamp_range(0.25..=24.0 * 90.0).speed(0.25).suffix(" h")).changed() {
+                                    *within_secs = (hours * 3600.0) as u64;
+                                }
+                            });
+                        }
+                        AlertCondition::Anomaly { metric, sigma, model } => {
+                            ui.horizontal(|ui| {
+                                ui.add(egui::TextEdit::singleline(metric).code_editor().desired_width(140.0));
+                                ui.add(egui::DragValue::new(sigma).clamp_range(0.5..=20.0).speed(0.1).suffix("σ"));
+                                let hourly = *model == BaselineModel::Hourly;
+                                egui::ComboBox::from_id_source(("rule_model", index))
+                                    .selected_text(if hourly { "Hourly" } else { "EWMA" })
+                                    .show_ui(ui, |ui| {
+                                        if ui.selectable_label(!hourly, "EWMA").clicked() && hourly {
+                                            *model = BaselineModel::Ewma { window_secs: 3600 };
+                                        }
+                                        if ui.selectable_label(hourly, "Hourly").clicked() {
+                                            *model = BaselineModel::Hourly;
+                                        }
+                                    });
+                                if let BaselineModel::Ewma { window_secs } = model {
+                                    ui.add(egui::DragValue::new(window_secs).clamp_range(60..=7 * 86400).suffix(" s"));
+                                }
+                            });
+                        }
+                    }
+                    egui::ComboBox::from_id_source(("rule_level", index))
+                        .selected_text(format!("{:?}", rule.level))
+                        .show_ui(ui, |ui| {
+                            for level in levels.iter().cloned() {
+                                let text = format!("{:?}", level);
+                                ui.selectable_value(&mut rule.level, level, text);
+                            }
+                        });
+                    ui.add(egui::DragValue::new(&mut rule.for_secs));
+                    // Conditions that are simply true or false have nothing to clear at
+                    if matches!(
+                        rule.condition,
+                        AlertCondition::Expr { .. } | AlertCondition::ProcessCount { .. } | AlertCondition::ProcessMissing { .. } | AlertCondition::DiskFull { .. } | AlertCondition::MemoryFull { .. }
+                    ) {
+                        ui.label("");
+                    } else {
+                        let mut clear = rule.clear_threshold.unwrap_or(rule.condition.threshold()) / scale;
+                        if ui.add(egui::DragValue::new(&mut clear).clamp_range(0.0..=f64::MAX)).changed() {
+                            rule.clear_threshold = Some(clear * scale);
+                        }
+                    }
+                    ui.add(egui::DragValue::new(&mut rule.cooldown_secs));
+                    let preview = self.alert_manager.lock().unwrap().preview(rule, &metrics);
+                    match preview {
+                        Err(e) => ui.colored_label(egui::Color32::RED, e),
+                        Ok(_) if !rule.enabled => ui.label("disabled"),
+                        Ok(_) if matches!(rule.condition, AlertCondition::Anomaly { .. }) => {
+                            match self.alert_manager.lock().unwrap().anomaly_score(rule, &metrics) {
+                                Some((value, sigmas)) if sigmas.abs() > rule.condition.threshold() => {
+                                    ui.colored_label(self.custom_theme.alert_warning, format!("would fire ({:.1}σ)", sigmas.abs())).on_hover_text(value)
+                                }
+                                Some((value, sigmas)) => ui.label(format!("ok ({:.1}σ)", sigmas.abs())).on_hover_text(value),
+                                None => ui.label("learning"),
+                            }
+                        }
+                        Ok(firing) if firing.is_empty() => ui.label("ok"),
+                        Ok(firing) => ui.colored_label(self.custom_theme.alert_warning, "would fire").on_hover_text(firing.join("\n")),
+                    };
+                    if ui.button("Delete").clicked() {
+                        remove = Some(index);
+                    }
+                    ui.end_row();
+                }
+            });
+            if ui.button("Add Threshold Rule").clicked() {
+                self.alert_rules.push(AlertRule::new(default_condition(CONDITION_KINDS[0]), AlertLevel::Warning));
+            }
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label("Expression:");
+                ui.add(egui::TextEdit::singleline(&mut self.rule_input).hint_text("cpu_total > 85 and mem_pct > 70 for 2m").desired_width(360.0));
+                egui::ComboBox::from_id_source("new_rule_level")
+                    .selected_text(format!("{:?}", self.rule_level))
+                    .show_ui(ui, |ui| {
+                        for level in levels.iter().cloned() {
+                            let text = format!("{:?}", level);
+                            ui.selectable_value(&mut self.rule_level, level, text);
+                        }
+                    });
+                let parsed = expr::parse(self.rule_input.trim());
+                add_expr = ui.add_enabled(parsed.is_ok(), egui::Button::new("Add Rule")).clicked();
+            });
+            let source = self.rule_input.trim();
+            if source.is_empty() {
+                ui.label("Metrics: cpu_total, cpu[N], mem_pct, mem_used, mem_total, mem_available, net_rx, net_tx, process_count, disk[\"/mount\"].used_pct, iface[\"eth0\"].rx_errors, rate(...)");
+            } else {
+                match expr::parse(source) {
+                    Ok(rule) => {
+                        // rate() of a counter needs two samples, so the preview may show it as unknown
+                        let result = match rule.evaluate(&metrics, &mut expr::RateTracker::default()) {
+                            Some(true) => "matches now",
+                            Some(false) => "does not match now",
+                            None => "cannot be evaluated yet",
+                        };
+                        ui.label(format!("Rule {} ({})", result, rule.describe(&metrics)));
+                    }
+                    Err(e) => {
+                        ui.label(egui::RichText::new(e.render(source)).monospace().color(egui::Color32::RED));
+                    }
+                }
+            }
+        });
+        if let Some(index) = remove {
+            self.alert_rules.remove(index);
+        }
+        if add_expr {
+            let condition = AlertCondition::Expr { expr: self.rule_input.trim().to_string() };
+            self.alert_rules.push(AlertRule::new(condition, self.rule_level.clone()));
+            self.rule_input.clear();
         }
     }
-}
 
-impl SysPortApp {
     pub fn load_geoip(&mut self, path: &str) {
         if let Ok(data) = fs::read(path) {
             if let Ok(reader) = maxminddb::Reader::from_source(data) {
@@ -312,15 +822,162 @@ impl epi::App for SysPortApp {
                 if let Some(msg) = &self.export_status {
                     ui.label(msg);
                 }
+                ui.collapsing("Export / Import", |ui| {
+                    ui.checkbox(&mut self.export_stored, "Include stored history from previous sessions");
+                    ui.horizontal(|ui| {
+                        if ui.button("Export JSON...").clicked() {
+                            if let Some(path) = rfd::FileDialog::new().add_filter("JSON", &["json"]).set_file_name("sysport_capture.json").save_file() {
+                                let capture = self.capture_for_export();
+                                self.export_status = Some(match export_capture(&capture, ExportFormat::Json, &path.to_string_lossy()) {
+                                    Ok(()) => format!("Exported {} samples and {} alerts to {}", capture.samples.len(), capture.alerts.len(), path.display()),
+                                    Err(e) => format!("Export failed: {}", e),
+                                });
+                            }
+                        }
+                        if ui.button("Export CSV...").clicked() {
+                            if let Some(path) = rfd::FileDialog::new().add_filter("CSV", &["csv"]).set_file_name("sysport_capture.csv").save_file() {
+                                let history = self.export_history();
+                                self.export_status = Some(match export_metrics(&history, ExportFormat::Csv, &path.to_string_lossy()) {
+                                    Ok(()) => format!("Exported {} samples to {}", history.len(), path.display()),
+                                    Err(e) => format!("Export failed: {}", e),
+                                });
+                            }
+                        }
+                        if ui.button("Import Capture...").clicked() {
+                            if let Some(path) = rfd::FileDialog::new().add_filter("Capture", &["json", "csv"]).pick_file() {
+                                match import_file(&path.to_string_lossy(), format_for_path(&path)) {
+                                    Ok(capture) => {
+                                        self.export_status = Some(format!("Imported {} samples and {} alerts from {}", capture.samples.len(), capture.alerts.len(), path.display()));
+                                        self.imported_history = capture.samples;
+                                    }
+                                    Err(e) => self.export_status = Some(format!("Import failed: {}", e)),
+                                }
+                            }
+                        }
+                    });
+                    if let (Some(first), Some(last)) = (self.imported_history.first(), self.imported_history.last()) {
+                        ui.label(format!(
+                            "Imported capture: {} samples from {} to {} UTC",
+                            self.imported_history.len(),
+                            first.timestamp.format("%Y-%m-%d %H:%M:%S"),
+                            last.timestamp.format("%Y-%m-%d %H:%M:%S"),
+                        ));
+                        let start = first.timestamp;
+                        let cpu_points: Vec<Value> = self.imported_history.iter()
+                            .map(|m| Value::new((m.timestamp - start).num_milliseconds() as f64 / 1000.0, m.cpu_total as f64))
+                            .collect();
+                        Plot::new("imported_cpu").height(120.0).show(ui, |plot_ui| {
+                            plot_ui.line(Line::new(Values::from_values(cpu_points)).name("CPU % (seconds since start)"));
+                        });
+                    }
+                });
+                ui.collapsing("Settings", |ui| {
+                    ui.label(format!("Config file: {}", self.config_path.display()));
+                    egui::Grid::new("settings_grid").num_columns(2).show(ui, |ui| {
+                        ui.label("Update interval (s):");
+                        ui.add(egui::Slider::new(&mut self.update_interval, 0.2..=5.0));
+                        ui.end_row();
+                        ui.label("Packet log filter:");
+                        ui.text_edit_singleline(&mut self.packet_filter);
+                        ui.end_row();
+                        ui.label("GeoIP database:");
+                        ui.text_edit_singleline(&mut self.geoip_path);
+                        ui.end_row();
+                        ui.label("Plugin directory:");
+                        ui.text_edit_singleline(&mut self.plugin_dir);
+                        ui.end_row();
+                        ui.label("Transparent proxy port:");
+                        ui.add(egui::DragValue::new(&mut self.proxy_port).clamp_range(1..=65535));
+                        ui.end_row();
+                        ui.label("Reverse proxy port:");
+                        ui.add(egui::DragValue::new(&mut self.reverse_proxy_port).clamp_range(1..=65535));
+                        ui.end_row();
+                        ui.label("Reverse proxy target:");
+                        ui.text_edit_singleline(&mut self.reverse_proxy_target);
+                        ui.end_row();
+                        ui.label("DNS server port:");
+                        ui.add(egui::DragValue::new(&mut self.dns_port).clamp_range(1..=65535));
+                        ui.end_row();
+                        ui.label("Remote server address (headless):");
+                        ui.text_edit_singleline(&mut self.remote_addr);
+                        ui.end_row();
+                        ui.label("History directory:");
+                        let mut dir = self.storage_config.dir.to_string_lossy().to_string();
+                        if ui.text_edit_singleline(&mut dir).changed() {
+                            self.storage_config.dir = PathBuf::from(dir);
+                        }
+                        ui.end_row();
+                        ui.label("History retention (days):");
+                        let mut days = self.storage_config.retention_secs / 86400;
+                        if ui.add(egui::DragValue::new(&mut days).clamp_range(1..=365)).changed() {
+                            self.storage_config.retention_secs = days * 86400;
+                        }
+                        ui.end_row();
+                        ui.label("History write interval (s):");
+                        ui.add(egui::DragValue::new(&mut self.storage_config.write_interval_secs).clamp_range(1..=3600));
+                        ui.end_row();
+                        ui.label("Theme:");
+                        ui.checkbox(&mut self.use_custom_theme, "Use custom theme (edit it in the Theme Editor)");
+                        ui.end_row();
+                    });
+                    ui.label(format!("{} alert rules loaded from the config file.", self.alert_rules.len()));
+                    ui.label("GeoIP, history and remote server changes take effect after a restart.");
+                    ui.horizontal(|ui| {
+                        if ui.button("Save").clicked() {
+                            self.save_config();
+                        }
+                        if ui.button("Reload").clicked() {
+                            match Config::load(&self.config_path) {
+                                Ok(config) => {
+                                    self.apply_config(config);
+                                    self.config_status = Some(format!("Settings reloaded from {}", self.config_path.display()));
+                                }
+                                Err(e) => self.config_status = Some(format!("Reload failed: {}", e)),
+                            }
+                        }
+                        if ui.button("Reset to Defaults").clicked() {
+                            self.apply_config(Config::default());
+                            self.save_config();
+                        }
+                        if ui.button("Import...").clicked() {
+                            if let Some(path) = rfd::FileDialog::new().add_filter("JSON", &["json"]).pick_file() {
+                                match Config::load(&path) {
+                                    Ok(config) => {
+                                        self.apply_config(config);
+                                        self.save_config();
+                                    }
+                                    Err(e) => self.config_status = Some(format!("Import failed: {}", e)),
+                                }
+                            }
+                        }
+                        if ui.button("Export...").clicked() {
+                            if let Some(path) = rfd::FileDialog::new().add_filter("JSON", &["json"]).set_file_name("sysport.json").save_file() {
+                                self.config_status = Some(match self.file_config.with_changes(&self.saved_config, &self.current_config()).save(&path) {
+                                    Ok(()) => format!("Settings exported to {}", path.display()),
+                                    Err(e) => format!("Export failed: {}", e),
+                                });
+                            }
+                        }
+                    });
+                    if let Some(msg) = &self.config_status {
+                        ui.label(msg);
+                    }
+                });
                 // Network interface selection
                 let interfaces = self.metrics.lock().unwrap().interfaces.clone();
                 egui::ComboBox::from_label("Network Interface")
                     .selected_text(self.selected_interface.clone().unwrap_or_else(|| "auto".to_string()))
                     .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.selected_interface, None, "auto");
                         for iface in &interfaces {
                             ui.selectable_value(&mut self.selected_interface, Some(iface.clone()), iface);
                         }
                     });
+                if let Ok(mut selection) = self.interface_selection.lock() {
+                    if *selection != self.selected_interface {
+                        *selection = self.selected_interface.clone();
+                    }
+                }
                 // Protocol toggles
                 ui.checkbox(&mut self.protocol_tcp, "TCP");
                 ui.checkbox(&mut self.protocol_udp, "UDP");
@@ -499,7 +1156,7 @@ impl epi::App for SysPortApp {
                     ui.label("Manage and run Rust plugins. Place plugin .so/.dylib/.dll files in the plugins directory.");
                     if ui.button("Reload Plugins").clicked() {
                         self.plugin_system.loaded_plugins.clear();
-                        self.plugin_system.load_plugins("../plugins/sample_plugin/target/release"); // Adjust path as needed
+                        self.plugin_system.load_plugins(&self.plugin_dir);
                     }
                     ui.label("Loaded plugins:");
                     for plugin in &self.plugin_system.loaded_plugins {
@@ -508,8 +1165,8 @@ impl epi::App for SysPortApp {
                 });
                 // Alert panel
                 let metrics = self.metrics.lock().unwrap().clone();
-                // Disable all alerts
-                self.alert_manager.active_alerts.clear();
+                ui.separator();
+                self.alerts_panel(ui);
                 // --- Web Monitor Section (scaffold) ---
                 ui.separator();
                 ui.heading("Web Monitor (Preview)");
@@ -518,7 +1175,7 @@ impl epi::App for SysPortApp {
                 // --- End Web Monitor Section ---
                 ui.separator();
                 // Main panel: metrics and charts
-                let history = self.history.lock().unwrap().clone();
+                let max_rx = self.history.lock().unwrap().raw().iter().map(|m| m.net_rx).max().unwrap_or(1) as f32 / 1024.0;
                 ui.heading("System Metrics");
                 ui.separator();
                     // CPU
@@ -554,6 +1211,9 @@ impl epi::App for SysPortApp {
                         let total_gb = metrics.mem_total as f64 / 1024.0 / 1024.0;
                         ui.label(format!("{:.2} GB / {:.2} GB", used_gb, total_gb));
                         ui.add(egui::ProgressBar::new(metrics.mem_used as f32 / metrics.mem_total as f32).text("Used"));
+                        if let Some(Forecast { time_to_full: Some(eta), .. }) = self.alert_manager.lock().unwrap().forecaster().memory() {
+                            ui.label(format!("At the current trend memory runs out in {}", format_eta(eta)));
+                        }
                         ui.horizontal(|ui| {
                             // REMOVE jagged line chart (Plot)
                             // REMOVE bar chart (Plot)
@@ -581,20 +1241,44 @@ impl epi::App for SysPortApp {
                             let used = disk.total - disk.available;
                             let used_gb = used as f64 / 1024.0 / 1024.0 / 1024.0;
                             let total_gb = disk.total as f64 / 1024.0 / 1024.0 / 1024.0;
-                            ui.label(format!("{}: {:.2} GB / {:.2} GB", disk.name, used_gb, total_gb));
-                            ui.add(egui::ProgressBar::new(used as f32 / disk.total as f32).text("Used"));
+                            ui.label(format!("{} on {} ({}): {:.2} GB / {:.2} GB", disk.name, disk.mount_point, disk.fs_type, used_gb, total_gb));
+                            let forecast = self.alert_manager.lock().unwrap().forecaster().disk(&disk.mount_point);
+                            ui.horizontal(|ui| {
+                                ui.add(egui::ProgressBar::new(used as f32 / disk.total as f32).text("Used").desired_width(300.0));
+                                match forecast {
+                                    Some(Forecast { time_to_full: Some(eta), bytes_per_hour }) => {
+                                        ui.label(format!("Full in {} ({:.2} GB/h)", format_eta(eta), bytes_per_hour / 1024.0 / 1024.0 / 1024.0));
+                                    }
+                                    Some(_) => {
+                                        ui.label("Not filling up");
+                                    }
+                                    None => {
+                                        ui.label("Forecast: collecting data");
+                                    }
+                                }
+                            });
+                            ui.horizontal(|ui| {
+                                ui.label(format!("Read: {:.2} MB/s ({:.0} IOPS)", disk.read_bytes as f64 / 1024.0 / 1024.0, disk.read_iops));
+                                ui.label(format!("Write: {:.2} MB/s ({:.0} IOPS)", disk.write_bytes as f64 / 1024.0 / 1024.0, disk.write_iops));
+                                ui.label(format!("Queue: {:.2}", disk.queue_depth));
+                                ui.label(format!("Service time: {:.1} ms", disk.service_time_ms));
+                                if disk.inodes_total > 0 {
+                                    let inodes_used = disk.inodes_total - disk.inodes_free;
+                                    ui.label(format!("Inodes: {:.1}% used", inodes_used as f64 / disk.inodes_total as f64 * 100.0));
+                                }
+                            });
                         }
                     });
                     ui.separator();
                     // Network
                     ui.collapsing("Network Throughput", |ui| {
-                        ui.label(format!("Download: {:.2} KB/s", metrics.net_rx as f64 / 1024.0));
-                        ui.label(format!("Upload: {:.2} KB/s", metrics.net_tx as f64 / 1024.0));
+                        let scope = metrics.selected_interface.clone().unwrap_or_else(|| "all interfaces".to_string());
+                        ui.label(format!("Download ({}): {:.2} KB/s", scope, metrics.net_rx as f64 / 1024.0));
+                        ui.label(format!("Upload ({}): {:.2} KB/s", scope, metrics.net_tx as f64 / 1024.0));
                         ui.horizontal(|ui| {
                             // REMOVE jagged line chart (Plot)
                             // REMOVE bar chart (Plot)
                             // Only show gauge
-                            let max_rx = history.iter().map(|m| m.net_rx).max().unwrap_or(1) as f32 / 1024.0;
                             let gauge = metrics.net_rx as f32 / 1024.0 / max_rx.max(1.0);
                             ui.allocate_ui(egui::vec2(80.0, 80.0), |ui| {
                                 let (rect, _response) = ui.allocate_exact_size(egui::vec2(80.0, 80.0), egui::Sense::hover());
@@ -610,6 +1294,137 @@ impl epi::App for SysPortApp {
                                 painter.text(center, egui::Align2::CENTER_CENTER, format!("{:.0} KB/s", metrics.net_rx as f64 / 1024.0), egui::FontId::proportional(14.0), egui::Color32::WHITE);
                             });
                         });
+                        egui::Grid::new("interface_grid").striped(true).show(ui, |ui| {
+                            for header in ["Interface", "State", "MTU", "RX KB/s", "TX KB/s", "RX pkts", "TX pkts", "Errors (rx/tx)", "Drops (rx/tx)", "Addresses"] {
+                                ui.label(header);
+                            }
+                            ui.end_row();
+                            for iface in &metrics.net_per_interface {
+                                if metrics.selected_interface.as_ref().is_some_and(|sel| sel != &iface.name) {
+                                    continue;
+                                }
+                                ui.label(&iface.name);
+                                ui.label(match iface.is_up {
+                                    Some(true) => "up",
+                                    Some(false) => "down",
+                                    None => "?",
+                                });
+                                ui.label(iface.mtu.map(|m| m.to_string()).unwrap_or_else(|| "?".to_string()));
+                                ui.label(format!("{:.2}", iface.rx as f64 / 1024.0));
+                                ui.label(format!("{:.2}", iface.tx as f64 / 1024.0));
+                                ui.label(iface.rx_packets.to_string());
+                                ui.label(iface.tx_packets.to_string());
+                                ui.label(format!("{}/{}", iface.rx_errors, iface.tx_errors));
+                                ui.label(format!("{}/{}", iface.rx_dropped, iface.tx_dropped));
+                                ui.label(iface.addresses.join(", "));
+                                ui.end_row();
+                            }
+                        });
+                    });
+                    ui.separator();
+                    // History
+                    ui.collapsing("History", |ui| {
+                        egui::ComboBox::from_label("Resolution")
+                            .selected_text(self.history_resolution.label())
+                            .show_ui(ui, |ui| {
+                                for res in Resolution::ALL {
+                                    ui.selectable_value(&mut self.history_resolution, res, res.label());
+                                }
+                            });
+                        let points = self.history.lock().unwrap().points(self.history_resolution);
+                        let now = chrono::Utc::now();
+                        let x = |p: &crate::history::RollupPoint| (p.start - now).num_milliseconds() as f64 / 1000.0;
+                        let line = |f: &dyn Fn(&crate::history::RollupPoint) -> f64| {
+                            Values::from_values(points.iter().map(|p| Value::new(x(p), f(p))).collect())
+                        };
+                        ui.label("CPU % (seconds relative to now)");
+                        Plot::new("history_cpu").height(120.0).show(ui, |plot_ui| {
+                            plot_ui.line(Line::new(line(&|p| p.cpu_total.max)).name("max").color(egui::Color32::DARK_RED));
+                            plot_ui.line(Line::new(line(&|p| p.cpu_total.avg)).name("avg").color(egui::Color32::LIGHT_BLUE));
+                            plot_ui.line(Line::new(line(&|p| p.cpu_total.min)).name("min").color(egui::Color32::DARK_GRAY));
+                        });
+                        ui.label("Memory %");
+                        Plot::new("history_mem").height(120.0).show(ui, |plot_ui| {
+                            let pct = |v: f64, p: &crate::history::RollupPoint| v / p.mem_total.max(1) as f64 * 100.0;
+                            plot_ui.line(Line::new(line(&|p| pct(p.mem_used.max, p))).name("max").color(egui::Color32::DARK_RED));
+                            plot_ui.line(Line::new(line(&|p| pct(p.mem_used.avg, p))).name("avg").color(egui::Color32::LIGHT_GREEN));
+                        });
+                        ui.label("Network KB/s");
+                        Plot::new("history_net").height(120.0).show(ui, |plot_ui| {
+                            plot_ui.line(Line::new(line(&|p| p.net_rx.avg / 1024.0)).name("rx avg").color(egui::Color32::LIGHT_YELLOW));
+                            plot_ui.line(Line::new(line(&|p| p.net_tx.avg / 1024.0)).name("tx avg").color(egui::Color32::LIGHT_RED));
+                        });
+                    });
+                    ui.separator();
+                    // Processes
+                    ui.collapsing("Processes", |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label("Search:");
+                            ui.text_edit_singleline(&mut self.process_search);
+                            ui.label(format!("{} processes", metrics.processes.len()));
+                        });
+                        let search = self.process_search.to_lowercase();
+                        let mut processes: Vec<&ProcessMetrics> = metrics.processes.iter()
+                            .filter(|p| search.is_empty()
+                                || p.name.to_lowercase().contains(&search)
+                                || p.cmdline.to_lowercase().contains(&search)
+                                || p.user.as_deref().unwrap_or("").to_lowercase().contains(&search)
+                                || p.pid.to_string() == search)
+                            .collect();
+                        sort_processes(&mut processes, self.process_sort, self.process_sort_desc);
+                        let now = std::time::SystemTime::now()
+                            .duration_since(std::time::UNIX_EPOCH)
+                            .map(|d| d.as_secs())
+                            .unwrap_or(0);
+                        egui::ScrollArea::vertical().id_source("process_table").max_height(400.0).show(ui, |ui| {
+                            egui::Grid::new("process_grid").striped(true).show(ui, |ui| {
+                                for (key, label) in [
+                                    (ProcessSortKey::Pid, "PID"),
+                                    (ProcessSortKey::Name, "Name"),
+                                    (ProcessSortKey::User, "User"),
+                                    (ProcessSortKey::Cpu, "CPU %"),
+                                    (ProcessSortKey::Memory, "RSS"),
+                                    (ProcessSortKey::VirtualMemory, "Virtual"),
+                                    (ProcessSortKey::DiskRead, "Read/s"),
+                                    (ProcessSortKey::DiskWrite, "Write/s"),
+                                    (ProcessSortKey::StartTime, "Uptime"),
+                                ] {
+                                    let selected = self.process_sort == key;
+                                    let text = if selected {
+                                        format!("{} {}", label, if self.process_sort_desc { "v" } else { "^" })
+                                    } else {
+                                        label.to_string()
+                                    };
+                                    if ui.selectable_label(selected, text).clicked() {
+                                        if selected {
+                                            self.process_sort_desc = !self.process_sort_desc;
+                                        } else {
+                                            self.process_sort = key;
+                                            self.process_sort_desc = !matches!(key, ProcessSortKey::Pid | ProcessSortKey::Name | ProcessSortKey::User);
+                                        }
+                                    }
+                                }
+                                ui.label("PPID");
+                                ui.label("State");
+                                ui.label("Command");
+                                ui.end_row();
+                                for p in processes.iter().take(200) {
+                                    ui.label(p.pid.to_string());
+                                    ui.label(&p.name);
+                                    ui.label(p.user.as_deref().unwrap_or("?"));
+                                    ui.label(format!("{:.1}", p.cpu_usage));
+                                    ui.label(format!("{:.1} MB", p.rss as f64 / 1024.0));
+                                    ui.label(format!("{:.1} MB", p.virtual_memory as f64 / 1024.0));
+                                    ui.label(format!("{:.1} KB/s", p.disk_read as f64 / 1024.0));
+                                    ui.label(format!("{:.1} KB/s", p.disk_write as f64 / 1024.0));
+                                    ui.label(format_uptime(now.saturating_sub(p.start_time)));
+                                    ui.label(p.ppid.map(|pid| pid.to_string()).unwrap_or_default());
+                                    ui.label(&p.state);
+                                    ui.label(&p.cmdline);
+                                    ui.end_row();
+                                }
+                            });
+                        });
                     });
                     ui.separator();
                     // In the Live Packet Log section:
@@ -715,6 +1530,8 @@ impl epi::App for SysPortApp {
             });
         });
 
+        self.autosave_config();
+
         // Control update interval and pause
         if !self.paused && self.last_update.elapsed().as_secs_f32() > self.update_interval {
             self.last_update = Instant::now();
@@ -724,6 +1541,95 @@ impl epi::App for SysPortApp {
     }
 }
 
+fn sort_processes(processes: &mut [&ProcessMetrics], key: ProcessSortKey, desc: bool) {
+    processes.sort_by(|a, b| {
+        let ord = match key {
+            ProcessSortKey::Pid => a.pid.cmp(&b.pid),
+            ProcessSortKey::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+            ProcessSortKey::User => a.user.cmp(&b.user),
+            ProcessSortKey::Cpu => a.cpu_usage.partial_cmp(&b.cpu_usage).unwrap_or(std::cmp::Ordering::Equal),
+            ProcessSortKey::Memory => a.rss.cmp(&b.rss),
+            ProcessSortKey::VirtualMemory => a.virtual_memory.cmp(&b.virtual_memory),
+            ProcessSortKey::DiskRead => a.disk_read.cmp(&b.disk_read),
+            ProcessSortKey::DiskWrite => a.disk_write.cmp(&b.disk_write),
+            ProcessSortKey::StartTime => b.start_time.cmp(&a.start_time),
+        };
+        if desc { ord.reverse() } else { ord }
+    });
+}
+
+fn format_uptime(secs: u64) -> String {
+    if secs >= 86400 {
+        format!("{}d {:02}h", secs / 86400, secs % 86400 / 3600)
+    } else if secs >= 3600 {
+        format!("{}h {:02}m", secs / 3600, secs % 3600 / 60)
+    } else {
+        format!("{}m {:02}s", secs / 60, secs % 60)
+    }
+}
+
+// Rule kinds offered in the alert rule editor, see `default_condition`
+const CONDITION_KINDS: [&str; 13] = ["CPU", "Memory", "Disk", "Net RX", "Net TX", "Expression", "Process CPU", "Process RSS", "Process Count", "Process Missing", "Anomaly", "Disk Full", "Memory Full"];
+
+fn condition_kind(condition: &AlertCondition) -> &'static str {
+    match condition {
+        AlertCondition::CpuUsage { .. } => "CPU",
+        AlertCondition::MemUsage { .. } => "Memory",
+        AlertCondition::DiskUsage { .. } => "Disk",
+        AlertCondition::NetRx { .. } => "Net RX",
+        AlertCondition::NetTx { .. } => "Net TX",
+        AlertCondition::Expr { .. } => "Expression",
+        AlertCondition::ProcessCpu { .. } => "Process CPU",
+        AlertCondition::ProcessRss { .. } => "Process RSS",
+        AlertCondition::ProcessCount { .. } => "Process Count",
+        AlertCondition::ProcessMissing { .. } => "Process Missing",
+        AlertCondition::Anomaly { .. } => "Anomaly",
+        AlertCondition::DiskFull { .. } => "Disk Full",
+        AlertCondition::MemoryFull { .. } => "Memory Full",
+    }
+}
+
+fn default_condition(kind: &str) -> AlertCondition {
+    match kind {
+        "Memory" => AlertCondition::MemUsage { threshold: 90.0 },
+        "Disk" => AlertCondition::DiskUsage { threshold: 95.0 },
+        "Net RX" => AlertCondition::NetRx { threshold: 1024 * 1024 },
+        "Net TX" => AlertCondition::NetTx { threshold: 1024 * 1024 },
+        "Expression" => AlertCondition::Expr { expr: "cpu_total > 90".to_string() },
+        // Left without a name so the editor asks for one
+        "Process CPU" => AlertCondition::ProcessCpu { matcher: ProcessMatcher::Name(String::new()), threshold: 80.0 },
+        "Process RSS" => AlertCondition::ProcessRss { matcher: ProcessMatcher::Name(String::new()), threshold: 1024 * 1024 * 1024 },
+        "Process Count" => AlertCondition::ProcessCount { matcher: ProcessMatcher::Name(String::new()), min: 1, max: None },
+        "Process Missing" => AlertCondition::ProcessMissing { matcher: ProcessMatcher::Name(String::new()) },
+        "Disk Full" => AlertCondition::DiskFull { within_secs: 24 * 3600 },
+        "Memory Full" => AlertCondition::MemoryFull { within_secs: 3600 },
+        "Anomaly" => AlertCondition::Anomaly { metric: "net_rx".to_string(), sigma: 4.0, model: BaselineModel::Ewma { window_secs: 3600 } },
+        _ => AlertCondition::CpuUsage { threshold: 90.0 },
+    }
+}
+
+// How a process rule picks its processes: by name, regex or pid file
+fn matcher_editor(ui: &mut egui::Ui, index: usize, matcher: &mut ProcessMatcher) {
+    let (kind, text) = match matcher {
+        ProcessMatcher::Name(s) => ("Name", s.clone()),
+        ProcessMatcher::Regex(s) => ("Regex", s.clone()),
+        ProcessMatcher::PidFile(s) => ("Pid file", s.clone()),
+    };
+    egui::ComboBox::from_id_source(("rule_matcher", index)).selected_text(kind).width(80.0).show_ui(ui, |ui| {
+        if ui.selectable_label(kind == "Name", "Name").clicked() {
+            *matcher = ProcessMatcher::Name(text.clone());
+        }
+        if ui.selectable_label(kind == "Regex", "Regex").clicked() {
+            *matcher = ProcessMatcher::Regex(text.clone());
+        }
+        if ui.selectable_label(kind == "Pid file", "Pid file").clicked() {
+            *matcher = ProcessMatcher::PidFile(text.clone());
+        }
+    });
+    let (ProcessMatcher::Name(s) | ProcessMatcher::Regex(s) | ProcessMatcher::PidFile(s)) = matcher;
+    ui.add(egui::TextEdit::singleline(s).desired_width(140.0));
+}
+
 fn packet_matches_filter(pkt: &RawPacketInfo, filter: &str, search: &str) -> bool {
     use regex::Regex;
     let hex = pkt.data.iter().map(|b| format!("{:02X} ", b)).collect::<String>();
@@ -0,0 +1,161 @@
+use crate::packet_stats::PacketStats;
+use crate::report;
+use maxminddb::geoip2;
+use pcap::{Capture, Linktype};
+use std::collections::VecDeque;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+pub const MAX_RAW_PACKETS: usize = 10000;
+
+#[derive(Clone)]
+pub struct RawPacketInfo {
+    pub timestamp: std::time::Instant,
+    pub data: Vec<u8>,
+    pub src: Option<IpAddr>,
+    pub dst: Option<IpAddr>,
+    pub src_port: Option<u16>,
+    pub dst_port: Option<u16>,
+    pub protocol: String,
+    pub country: Option<String>,
+    pub app: Option<String>,
+}
+
+// Spawn background thread for global packet capture on `device`, or the default device.
+// `stats` counts every packet since the capture started.
+pub fn spawn_capture(
+    device: Option<String>,
+    raw_packets: Arc<Mutex<VecDeque<RawPacketInfo>>>,
+    stats: Arc<Mutex<PacketStats>>,
+    geoip_reader: Option<maxminddb::Reader<Vec<u8>>>,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let device = match device {
+            Some(name) => name,
+            None => match pcap::Device::lookup() {
+                Ok(Some(device)) => device.name,
+                Ok(None) => {
+                    report::error("Packet capture disabled: no capture device found".to_string());
+                    return;
+                }
+                Err(e) => {
+                    report::error(format!("Packet capture disabled: {}", e));
+                    return;
+                }
+            },
+        };
+        let cap = Capture::from_device(device.as_str()).and_then(|c| c.promisc(true).open());
+        match cap {
+            Ok(mut cap) => {
+                let linktype = cap.get_datalink();
+                while let Ok(packet) = cap.next_packet() {
+                    stats.lock().unwrap().update(packet.data, linktype);
+                    let pkt = parse_packet(packet.data.to_vec(), linktype, geoip_reader.as_ref());
+                    let mut lock = raw_packets.lock().unwrap();
+                    if lock.len() > MAX_RAW_PACKETS { lock.pop_front(); }
+                    lock.push_back(pkt);
+                }
+            }
+            Err(e) => report::error(format!("Packet capture on {} failed: {}", device, e)),
+        }
+    })
+}
+
+// `linktype` is the capture's link-layer header type
+pub fn parse_packet(data: Vec<u8>, linktype: Linktype, geoip_reader: Option<&maxminddb::Reader<Vec<u8>>>) -> RawPacketInfo {
+    // Parse IP/port/protocol (IPv4 only for now)
+    let (src, dst, src_port, dst_port, proto) = match ipv4_packet(&data, linktype) {
+        Some(ip) => {
+            let src = IpAddr::from([ip[12], ip[13], ip[14], ip[15]]);
+            let dst = IpAddr::from([ip[16], ip[17], ip[18], ip[19]]);
+            // Ports follow the IP header, whose length is in 32-bit words; truncated packets have none
+            let ports = ip.get((ip[0] & 0x0f) as usize * 4..).and_then(|rest| rest.get(..4));
+            let (src_port, dst_port) = match ports {
+                Some(p) => (Some(u16::from_be_bytes([p[0], p[1]])), Some(u16::from_be_bytes([p[2], p[3]]))),
+                None => (None, None),
+            };
+            let (src_port, dst_port, proto_str) = match ip[9] {
+                6 => (src_port, dst_port, "TCP"),
+                17 => (src_port, dst_port, "UDP"),
+                1 => (None, None, "ICMP"),
+                _ => (None, None, "IPv4"),
+            };
+            (Some(src), Some(dst), src_port, dst_port, proto_str.to_string())
+        }
+        None => (None, None, None, None, "Other".to_string()),
+    };
+    let country = src.and_then(|ip| geoip_reader.and_then(|g| {
+        if let Ok(geo) = g.lookup::<geoip2::Country>(ip) {
+            geo.country.and_then(|c| c.iso_code).map(|s| s.to_string())
+        } else { None }
+    }));
+    RawPacketInfo {
+        timestamp: std::time::Instant::now(),
+        data,
+        src,
+        dst,
+        src_port,
+        dst_port,
+        protocol: proto,
+        country,
+        app: None, // TODO: per-app mapping
+    }
+}
+
+// The IPv4 packet inside a captured frame, with at least a full base header
+fn ipv4_packet(data: &[u8], linktype: Linktype) -> Option<&[u8]> {
+    let ip = match linktype {
+        Linktype::ETHERNET if data.get(12..14) == Some(&[0x08, 0x00]) => &data[14..],
+        Linktype::ETHERNET => return None,
+        _ => data,
+    };
+    (ip.len() >= 20 && ip[0] >> 4 == 4).then_some(ip)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // IPv4 header from 10.0.0.1 to 8.8.8.8, then ports 40000 -> 443
+    fn ipv4(protocol: u8) -> Vec<u8> {
+        let mut pkt = vec![0x45, 0, 0, 40, 0, 0, 0, 0, 64, protocol, 0, 0, 10, 0, 0, 1, 8, 8, 8, 8];
+        pkt.extend([0x9c, 0x40, 0x01, 0xbb]);
+        pkt
+    }
+
+    fn ethernet(payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0xff; 12];
+        frame.extend([0x08, 0x00]);
+        frame.extend(payload);
+        frame
+    }
+
+    fn summary(info: &RawPacketInfo) -> (String, Option<String>, Option<u16>, Option<u16>) {
+        (info.protocol.clone(), info.dst.map(|ip| ip.to_string()), info.src_port, info.dst_port)
+    }
+
+    #[test]
+    fn decodes_ethernet_and_raw_ip() {
+        let tcp = parse_packet(ethernet(&ipv4(6)), Linktype::ETHERNET, None);
+        assert_eq!(summary(&tcp), ("TCP".to_string(), Some("8.8.8.8".to_string()), Some(40000), Some(443)));
+        let udp = parse_packet(ipv4(17), Linktype::RAW, None);
+        assert_eq!(summary(&udp), ("UDP".to_string(), Some("8.8.8.8".to_string()), Some(40000), Some(443)));
+        // A bare IP packet is not mistaken for an Ethernet frame, or the other way round
+        assert_eq!(parse_packet(ipv4(6), Linktype::ETHERNET, None).protocol, "Other");
+        assert_eq!(parse_packet(ethernet(&ipv4(6)), Linktype::RAW, None).protocol, "Other");
+    }
+
+    #[test]
+    fn truncated_frames_do_not_panic() {
+        let frame = ethernet(&ipv4(6));
+        for len in 0..frame.len() {
+            let info = parse_packet(frame[..len].to_vec(), Linktype::ETHERNET, None);
+            if len < 34 {
+                assert_eq!(info.protocol, "Other");
+            } else if len < 38 {
+                assert_eq!(summary(&info), ("TCP".to_string(), Some("8.8.8.8".to_string()), None, None));
+            }
+        }
+    }
+}
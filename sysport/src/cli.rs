@@ -0,0 +1,394 @@
+use crate::alert::AlertManager;
+use crate::capture;
+use crate::collector::Collector;
+use crate::config::Config;
+use crate::export::{self, ExportFormat};
+use crate::metrics::Metrics;
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use sysinfo::{System, SystemExt};
+
+#[derive(Parser)]
+#[command(name = "sysport", version, about = "Cross-platform system monitor")]
+pub struct Cli {
+    /// Run the collector, alerts and servers without a window
+    #[arg(long)]
+    pub headless: bool,
+
+    /// Run the terminal interface instead of the window
+    #[arg(long, conflicts_with = "headless")]
+    pub tui: bool,
+
+    /// Config file to use instead of the per-user one
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub overrides: ConfigOverrides,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+// Settings that can be overridden for a single run without editing the config file
+#[derive(Args, Default)]
+pub struct ConfigOverrides {
+    /// Refresh interval of the window in seconds
+    #[arg(long, value_name = "SECS")]
+    pub update_interval: Option<f32>,
+    #[arg(long, value_name = "PORT")]
+    pub proxy_port: Option<u16>,
+    #[arg(long, value_name = "PORT")]
+    pub reverse_proxy_port: Option<u16>,
+    /// Upstream address of the reverse proxy, e.g. 127.0.0.1:80
+    #[arg(long, value_name = "ADDR")]
+    pub reverse_proxy_target: Option<String>,
+    #[arg(long, value_name = "PORT")]
+    pub dns_port: Option<u16>,
+    /// Packet log filter, e.g. "proto:tcp,country:US"
+    #[arg(long, value_name = "FILTER")]
+    pub packet_filter: Option<String>,
+    /// GeoIP country database (.mmdb)
+    #[arg(long, value_name = "PATH")]
+    pub geoip: Option<String>,
+    #[arg(long, value_name = "DIR")]
+    pub plugin_dir: Option<String>,
+    /// Address the remote server listens on in headless mode
+    #[arg(long, value_name = "ADDR")]
+    pub remote_addr: Option<String>,
+    /// Serve Prometheus metrics on this address, e.g. 127.0.0.1:9184
+    #[arg(long, value_name = "ADDR")]
+    pub prometheus_addr: Option<String>,
+    /// Directory for stored metrics history
+    #[arg(long, value_name = "DIR")]
+    pub data_dir: Option<PathBuf>,
+}
+
+impl ConfigOverrides {
+    pub fn apply(&self, config: &mut Config) {
+        if let Some(v) = self.update_interval {
+            config.update_interval = v;
+        }
+        if let Some(v) = self.proxy_port {
+            config.proxy_port = v;
+        }
+        if let Some(v) = self.reverse_proxy_port {
+            config.reverse_proxy_port = v;
+        }
+        if let Some(v) = &self.reverse_proxy_target {
+            config.reverse_proxy_target = v.clone();
+        }
+        if let Some(v) = self.dns_port {
+            config.dns_port = v;
+        }
+        if let Some(v) = &self.packet_filter {
+            config.packet_filter = v.clone();
+        }
+        if let Some(v) = &self.geoip {
+            config.geoip_path = v.clone();
+        }
+        if let Some(v) = &self.plugin_dir {
+            config.plugin_dir = v.clone();
+        }
+        if let Some(v) = &self.remote_addr {
+            config.remote_addr = v.clone();
+        }
+        if let Some(v) = &self.prometheus_addr {
+            config.prometheus_addr = Some(v.clone());
+        }
+        if let Some(v) = &self.data_dir {
+            config.storage.dir = v.clone();
+        }
+    }
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Print the current metrics
+    Snapshot {
+        #[arg(long, value_enum, default_value_t = OutputFormat::Json)]
+        format: OutputFormat,
+    },
+    /// Record metrics for a while and export them
+    Record {
+        /// How long to record, e.g. 30s, 10m, 1h30m
+        #[arg(long, value_parser = parse_duration)]
+        duration: Duration,
+        /// Output file; the format follows the extension unless --format is given
+        #[arg(long)]
+        out: PathBuf,
+        #[arg(long, value_enum)]
+        format: Option<OutputFormat>,
+    },
+    /// Capture packets to a pcap file
+    Capture {
+        /// Interface to capture on (default device if omitted)
+        #[arg(long)]
+        iface: Option<String>,
+        /// BPF filter expression, e.g. "tcp port 443"
+        #[arg(long)]
+        filter: Option<String>,
+        #[arg(long)]
+        out: PathBuf,
+        /// Stop after this many packets
+        #[arg(long)]
+        count: Option<usize>,
+        /// Stop after this long, e.g. 30s
+        #[arg(long, value_parser = parse_duration)]
+        duration: Option<Duration>,
+    },
+    /// Print a summary of a saved capture (JSON or CSV)
+    Import {
+        file: PathBuf,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Json,
+    Csv,
+}
+
+impl From<OutputFormat> for ExportFormat {
+    fn from(f: OutputFormat) -> Self {
+        match f {
+            OutputFormat::Json => ExportFormat::Json,
+            OutputFormat::Csv => ExportFormat::Csv,
+        }
+    }
+}
+
+pub fn run(command: Command, config: &Config) -> Result<(), String> {
+    match command {
+        Command::Snapshot { format } => snapshot(format, config),
+        Command::Record { duration, out, format } => record(duration, out, format, config),
+        Command::Capture { iface, filter, out, count, duration } => capture_packets(iface, filter, out, count, duration),
+        Command::Import { file } => import(file),
+    }
+}
+
+// The alert rules, silences and maintenance windows from the config. Notification
+// channels and the alert history are left out, so a one-off command never pages
+// anyone or adds to the history of a running instance.
+fn alert_manager(config: &Config) -> AlertManager {
+    let am = AlertManager::with_rules(config.alert_rules.clone()).with_silences(config.silences.clone(), config.maintenance_windows.clone());
+    for (index, error) in am.rule_errors() {
+        eprintln!("Alert rule {} ignored: {}", index + 1, error);
+    }
+    am
+}
+
+fn snapshot(format: OutputFormat, config: &Config) -> Result<(), String> {
+    let mut collector = Collector::spawn(None, alert_manager(config));
+    let samples = collector.subscribe();
+    // Rates and CPU usage need two samples to be meaningful
+    let _ = samples.recv();
+    let metrics = samples.recv().map_err(|e| e.to_string())?;
+    collector.stop();
+    let stdout = std::io::stdout();
+    match format {
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(stdout.lock(), &metrics).map_err(|e| e.to_string())?;
+            println!();
+            Ok(())
+        }
+        OutputFormat::Csv => export::write_metrics(&[metrics], ExportFormat::Csv, stdout.lock()).map_err(|e| e.to_string()),
+    }
+}
+
+fn record(duration: Duration, out: PathBuf, format: Option<OutputFormat>, config: &Config) -> Result<(), String> {
+    let format = format.map(ExportFormat::from).unwrap_or_else(|| export::format_for_path(&out));
+    let mut collector = Collector::spawn(None, alert_manager(config));
+    let samples = collector.subscribe();
+    let started = chrono::Utc::now();
+    let deadline = Instant::now() + duration;
+    let mut history: Vec<Metrics> = Vec::new();
+    while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+        match samples.recv_timeout(remaining) {
+            Ok(mut m) => {
+                m.processes.clear();
+                history.push(m);
+            }
+            Err(std::sync::mpsc::RecvTimeoutError::Timeout) => break,
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+    collector.stop();
+    let mut capture = export::Capture::new(history);
+    capture.host = System::new().host_name().unwrap_or_default();
+    capture.alerts = {
+        let am = collector.alert_manager.lock().unwrap();
+        am.history.iter().chain(&am.active_alerts).filter(|a| a.started_at >= started).cloned().collect()
+    };
+    export::export_capture(&capture, format, &out.to_string_lossy()).map_err(|e| e.to_string())?;
+    eprintln!("Recorded {} samples to {}", capture.samples.len(), out.display());
+    Ok(())
+}
+
+fn capture_packets(iface: Option<String>, filter: Option<String>, out: PathBuf, count: Option<usize>, duration: Option<Duration>) -> Result<(), String> {
+    let device = match iface {
+        Some(name) => name,
+        None => pcap::Device::lookup()
+            .map_err(|e| e.to_string())?
+            .ok_or("no capture device found")?
+            .name,
+    };
+    let mut cap = pcap::Capture::from_device(device.as_str())
+        .and_then(|c| c.promisc(true).timeout(500).open())
+        .map_err(|e| format!("cannot capture on {}: {}", device, e))?;
+    if let Some(filter) = &filter {
+        cap.filter(filter, true).map_err(|e| format!("invalid filter: {}", e))?;
+    }
+    let mut savefile = cap.savefile(&out).map_err(|e| e.to_string())?;
+    let linktype = cap.get_datalink();
+    let deadline = duration.map(|d| Instant::now() + d);
+    // Ctrl-C ends the capture like a stop condition, so the savefile is still flushed
+    let interrupted = ctrl_c_flag();
+    if count.is_none() && duration.is_none() {
+        eprintln!("Capturing on {} until Ctrl-C", device);
+    }
+    let mut protocols: BTreeMap<String, usize> = BTreeMap::new();
+    let mut captured = 0;
+    while count.is_none_or(|c| captured < c) && deadline.is_none_or(|d| Instant::now() < d) && !interrupted.load(Ordering::SeqCst) {
+        match cap.next_packet() {
+            Ok(packet) => {
+                savefile.write(&packet);
+                let info = capture::parse_packet(packet.data.to_vec(), linktype, None);
+                *protocols.entry(info.protocol).or_insert(0) += 1;
+                captured += 1;
+            }
+            Err(pcap::Error::TimeoutExpired) => continue,
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+    savefile.flush().map_err(|e| e.to_string())?;
+    eprintln!("Captured {} packets on {} to {}", captured, device, out.display());
+    for (proto, n) in protocols {
+        eprintln!("  {}: {}", proto, n);
+    }
+    Ok(())
+}
+
+// Set once Ctrl-C is pressed, which then no longer kills the process
+fn ctrl_c_flag() -> Arc<AtomicBool> {
+    let flag = Arc::new(AtomicBool::new(false));
+    let set = flag.clone();
+    std::thread::spawn(move || {
+        let Ok(rt) = tokio::runtime::Builder::new_current_thread().enable_all().build() else { return };
+        if rt.block_on(tokio::signal::ctrl_c()).is_ok() {
+            set.store(true, Ordering::SeqCst);
+        }
+    });
+    flag
+}
+
+fn import(file: PathBuf) -> Result<(), String> {
+    let capture = export::import_file(&file.to_string_lossy(), export::format_for_path(&file)).map_err(|e| e.to_string())?;
+    let history = &capture.samples;
+    let (first, last) = match (history.first(), history.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => {
+            println!("{}: no samples", file.display());
+            return Ok(());
+        }
+    };
+    let n = history.len() as f64;
+    let mem_pct = |m: &Metrics| m.mem_used as f64 / m.mem_total.max(1) as f64 * 100.0;
+    let max = |f: &dyn Fn(&Metrics) -> f64| history.iter().map(f).fold(f64::MIN, f64::max);
+    let avg = |f: &dyn Fn(&Metrics) -> f64| history.iter().map(f).sum::<f64>() / n;
+    println!("File:     {}", file.display());
+    println!("Samples:  {}", history.len());
+    println!("From:     {}", first.timestamp.to_rfc3339());
+    println!("To:       {}", last.timestamp.to_rfc3339());
+    println!("Span:     {}s", (last.timestamp - first.timestamp).num_seconds());
+    println!("CPU:      avg {:.1}%  max {:.1}%", avg(&|m| m.cpu_total as f64), max(&|m| m.cpu_total as f64));
+    println!("Memory:   avg {:.1}%  max {:.1}%", avg(&mem_pct), max(&mem_pct));
+    println!("Net RX:   avg {:.2} KB/s  max {:.2} KB/s", avg(&|m| m.net_rx as f64) / 1024.0, max(&|m| m.net_rx as f64) / 1024.0);
+    println!("Net TX:   avg {:.2} KB/s  max {:.2} KB/s", avg(&|m| m.net_tx as f64) / 1024.0, max(&|m| m.net_tx as f64) / 1024.0);
+    if !capture.alerts.is_empty() {
+        println!("Alerts:   {}", capture.alerts.len());
+        for alert in &capture.alerts {
+            println!("  {} {:?} {}", alert.started_at.to_rfc3339(), alert.level, alert.message);
+        }
+    }
+    if let Some(p) = &capture.packets {
+        println!("Packets:  TCP {}  UDP {}  ICMP {}  ARP {}", p.tcp_count, p.udp_count, p.icmp_count, p.arp_count);
+    }
+    Ok(())
+}
+
+// Parses durations such as "90", "30s", "10m", "2h" or "1h30m"; bare numbers are seconds
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let total = match s.parse::<u64>() {
+        Ok(secs) => secs,
+        Err(_) => {
+            let mut total = 0u64;
+            let mut digits = String::new();
+            for c in s.chars() {
+                if c.is_ascii_digit() {
+                    digits.push(c);
+                    continue;
+                }
+                let value: u64 = digits.parse().map_err(|_| format!("invalid duration: {}", s))?;
+                digits.clear();
+                let unit = match c {
+                    's' => 1,
+                    'm' => 60,
+                    'h' => 3600,
+                    'd' => 86400,
+                    _ => return Err(format!("invalid duration unit '{}' in {}", c, s)),
+                };
+                total = value.checked_mul(unit).and_then(|v| total.checked_add(v)).ok_or_else(|| format!("duration too long: {}", s))?;
+            }
+            if !digits.is_empty() {
+                return Err(format!("missing unit after {} in {}", digits, s));
+            }
+            total
+        }
+    };
+    if total == 0 {
+        return Err(format!("duration must be longer than zero: {:?}", s));
+    }
+    Ok(Duration::from_secs(total))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration(" 30s "), Ok(Duration::from_secs(30)));
+        assert_eq!(parse_duration("10m"), Ok(Duration::from_secs(600)));
+        assert_eq!(parse_duration("1h30m"), Ok(Duration::from_secs(5400)));
+        assert_eq!(parse_duration("2d1s"), Ok(Duration::from_secs(2 * 86400 + 1)));
+        assert_eq!(parse_duration("0h5m"), Ok(Duration::from_secs(300)));
+    }
+
+    #[test]
+    fn rejects_bad_durations() {
+        assert_eq!(parse_duration(""), Err("duration must be longer than zero: \"\"".to_string()));
+        assert_eq!(parse_duration("0"), Err("duration must be longer than zero: \"0\"".to_string()));
+        assert_eq!(parse_duration("0m0s"), Err("duration must be longer than zero: \"0m0s\"".to_string()));
+        assert_eq!(parse_duration("10x"), Err("invalid duration unit 'x' in 10x".to_string()));
+        assert_eq!(parse_duration("1h30"), Err("missing unit after 30 in 1h30".to_string()));
+        assert_eq!(parse_duration("m"), Err("invalid duration: m".to_string()));
+        assert_eq!(parse_duration("-5s"), Err("invalid duration: -5s".to_string()));
+    }
+
+    #[test]
+    fn rejects_overflowing_durations() {
+        let too_long = Err("duration too long: 99999999999999999d".to_string());
+        assert_eq!(parse_duration("99999999999999999d"), too_long);
+        assert!(parse_duration("18446744073709551615s1s").is_err());
+        assert!(parse_duration("99999999999999999999").is_err());
+        assert_eq!(parse_duration("18446744073709551615"), Ok(Duration::from_secs(u64::MAX)));
+    }
+}
@@ -0,0 +1,266 @@
+use crate::alert::AlertManager;
+use crate::history::MetricsHistory;
+use crate::linux_stats::{self, DiskCounters};
+use crate::metrics::{DiskMetrics, Metrics, NetInterfaceStats, ProcessMetrics};
+use crate::report;
+use crate::storage::{self, MetricsStore, StorageConfig};
+
+use sysinfo::{System, SystemExt, DiskExt, NetworkExt, NetworksExt, ProcessorExt, ProcessExt, PidExt};
+#[cfg(target_os = "linux")]
+use sysinfo::UserExt;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+// Background sampler shared by every frontend. Each sample updates the latest
+// Metrics, the history store and the on-disk segments, then runs the alert rules.
+pub struct Collector {
+    pub metrics: Arc<Mutex<Metrics>>,
+    pub history: Arc<Mutex<MetricsHistory>>,
+    pub interface_selection: Arc<Mutex<Option<String>>>,
+    pub alert_manager: Arc<Mutex<AlertManager>>,
+    subscribers: Arc<Mutex<Vec<Sender<Metrics>>>>,
+    running: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Collector {
+    // Without a storage config nothing is loaded from or written to disk
+    pub fn spawn(storage_config: Option<StorageConfig>, alert_manager: AlertManager) -> Self {
+        let metrics = Arc::new(Mutex::new(Metrics::default()));
+        let history = Arc::new(Mutex::new(MetricsHistory::new()));
+        let interface_selection = Arc::new(Mutex::new(None));
+        let alert_manager = Arc::new(Mutex::new(alert_manager));
+        let subscribers: Arc<Mutex<Vec<Sender<Metrics>>>> = Arc::new(Mutex::new(Vec::new()));
+        let running = Arc::new(AtomicBool::new(true));
+
+        let metrics_clone = metrics.clone();
+        let history_clone = history.clone();
+        let interface_selection_clone = interface_selection.clone();
+        let alert_manager_clone = alert_manager.clone();
+        let subscribers_clone = subscribers.clone();
+        let running_clone = running.clone();
+        let handle = thread::spawn(move || {
+            let mut store = storage_config.and_then(|storage_config| {
+                // Reload previous sessions before sampling so charts start with them
+                match storage::load_since(&storage_config.dir, chrono::Duration::seconds(storage_config.retention_secs as i64)) {
+                    Ok(samples) => {
+                        alert_manager_clone.lock().unwrap().learn_from(&samples);
+                        let mut hist = history_clone.lock().unwrap();
+                        for sample in samples {
+                            hist.push(sample);
+                        }
+                    }
+                    Err(e) => report::error(format!("Failed to load stored history: {}", e)),
+                }
+                match MetricsStore::open(storage_config) {
+                    Ok(store) => Some(store),
+                    Err(e) => {
+                        report::error(format!("Metrics storage disabled: {}", e));
+                        None
+                    }
+                }
+            });
+            let mut sys = System::new_all();
+            let mut last_iface_totals: HashMap<String, (u64, u64)> = HashMap::new();
+            let mut last_disk_counters: HashMap<String, DiskCounters> = HashMap::new();
+            let mut last_sample = Instant::now();
+            let mut tick: u64 = 0;
+            while running_clone.load(Ordering::Relaxed) {
+                thread::sleep(Duration::from_millis(200));
+                let elapsed = last_sample.elapsed().as_secs_f64().max(0.001);
+                last_sample = Instant::now();
+                sys.refresh_cpu();
+                sys.refresh_memory();
+                sys.refresh_disks_list();
+                sys.refresh_disks();
+                if tick.is_multiple_of(25) {
+                    sys.refresh_networks_list();
+                } else {
+                    sys.refresh_networks();
+                }
+                tick += 1;
+                sys.refresh_processes();
+                let cpus = sys.processors();
+                let cpu_usages: Vec<f32> = cpus.iter().map(|c| c.cpu_usage()).collect();
+                let cpu_total = cpu_usages.iter().sum::<f32>() / cpu_usages.len().max(1) as f32;
+                let mem_total = sys.total_memory();
+                let mem_used = sys.used_memory();
+                let disks = collect_disks(&sys, &mut last_disk_counters, elapsed);
+                let net_per_interface = collect_interfaces(&sys, &mut last_iface_totals, elapsed);
+                let interfaces: Vec<String> = net_per_interface.iter().map(|i| i.name.clone()).collect();
+                let selected_interface = interface_selection_clone.lock().unwrap().clone()
+                    .filter(|name| interfaces.contains(name));
+                let (net_rx, net_tx) = match &selected_interface {
+                    Some(name) => net_per_interface.iter()
+                        .find(|i| &i.name == name)
+                        .map(|i| (i.rx, i.tx))
+                        .unwrap_or((0, 0)),
+                    None => (
+                        net_per_interface.iter().map(|i| i.rx).sum(),
+                        net_per_interface.iter().map(|i| i.tx).sum(),
+                    ),
+                };
+                let processes = collect_processes(&sys, elapsed);
+                let m = Metrics {
+                    timestamp: chrono::Utc::now(),
+                    cpu_usage: cpu_usages,
+                    cpu_total,
+                    mem_total,
+                    mem_used,
+                    disks,
+                    net_rx,
+                    net_tx,
+                    selected_interface,
+                    interfaces,
+                    net_per_interface,
+                    processes,
+                };
+                // Store latest metrics
+                if let Ok(mut lock) = metrics_clone.lock() {
+                    *lock = m.clone();
+                }
+                if let Some(store) = &mut store {
+                    if let Err(e) = store.append(&m) {
+                        report::error(format!("Failed to write metrics segment: {}", e));
+                    }
+                }
+                if let Ok(mut am) = alert_manager_clone.lock() {
+                    am.check(&m);
+                }
+                if let Ok(mut subs) = subscribers_clone.lock() {
+                    subs.retain(|tx| tx.send(m.clone()).is_ok());
+                }
+                // Store history for plotting
+                if let Ok(mut hist) = history_clone.lock() {
+                    hist.push(m);
+                }
+            }
+        });
+
+        Self {
+            metrics,
+            history,
+            interface_selection,
+            alert_manager,
+            subscribers,
+            running,
+            handle: Some(handle),
+        }
+    }
+
+    // Every sample taken after this call is also sent to the returned receiver
+    pub fn subscribe(&self) -> Receiver<Metrics> {
+        let (tx, rx) = mpsc::channel();
+        self.subscribers.lock().unwrap().push(tx);
+        rx
+    }
+
+    // Stops sampling and waits for the current sample to be written out
+    pub fn stop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn collect_disks(sys: &System, last_counters: &mut HashMap<String, DiskCounters>, elapsed: f64) -> Vec<DiskMetrics> {
+    let counters = linux_stats::read_diskstats();
+    let disks = sys.disks().iter().map(|d| {
+        let name = d.name().to_string_lossy().to_string();
+        let (inodes_total, inodes_free) = linux_stats::inode_usage(d.mount_point()).unwrap_or((0, 0));
+        let mut disk = DiskMetrics {
+            name: name.clone(),
+            mount_point: d.mount_point().to_string_lossy().to_string(),
+            fs_type: String::from_utf8_lossy(d.file_system()).to_string(),
+            total: d.total_space(),
+            available: d.available_space(),
+            inodes_total,
+            inodes_free,
+            ..Default::default()
+        };
+        let device = linux_stats::block_device_name(&name);
+        if let (Some(cur), Some(prev)) = (counters.get(&device), last_counters.get(&device)) {
+            // Sectors in /proc/diskstats are always 512 bytes
+            let reads = cur.reads.saturating_sub(prev.reads);
+            let writes = cur.writes.saturating_sub(prev.writes);
+            disk.read_bytes = (cur.sectors_read.saturating_sub(prev.sectors_read) as f64 * 512.0 / elapsed) as u64;
+            disk.write_bytes = (cur.sectors_written.saturating_sub(prev.sectors_written) as f64 * 512.0 / elapsed) as u64;
+            disk.read_iops = reads as f64 / elapsed;
+            disk.write_iops = writes as f64 / elapsed;
+            disk.queue_depth = cur.ms_weighted.saturating_sub(prev.ms_weighted) as f64 / (elapsed * 1000.0);
+            // Busy time per request, excluding time spent queued (that would be await)
+            let busy_ms = cur.ms_io.saturating_sub(prev.ms_io);
+            if reads + writes > 0 {
+                disk.service_time_ms = busy_ms as f64 / (reads + writes) as f64;
+            }
+        } else if let Some(cur) = counters.get(&device) {
+            disk.queue_depth = cur.in_flight as f64;
+        }
+        disk
+    }).collect();
+    *last_counters = counters;
+    disks
+}
+
+fn collect_interfaces(sys: &System, last_totals: &mut HashMap<String, (u64, u64)>, elapsed: f64) -> Vec<NetInterfaceStats> {
+    let addrs = if_addrs::get_if_addrs().unwrap_or_default();
+    let mut stats: Vec<NetInterfaceStats> = sys.networks().iter().map(|(name, data)| {
+        let rx_total = data.total_received();
+        let tx_total = data.total_transmitted();
+        // The first sample of an interface has no baseline, so it reports zero
+        let (rx, tx) = match last_totals.insert(name.clone(), (rx_total, tx_total)) {
+            Some((last_rx, last_tx)) => (
+                (rx_total.saturating_sub(last_rx) as f64 / elapsed) as u64,
+                (tx_total.saturating_sub(last_tx) as f64 / elapsed) as u64,
+            ),
+            None => (0, 0),
+        };
+        let sysfs = linux_stats::read_iface_sysfs(name);
+        NetInterfaceStats {
+            name: name.clone(),
+            rx,
+            tx,
+            rx_packets: data.total_packets_received(),
+            tx_packets: data.total_packets_transmitted(),
+            rx_errors: data.total_errors_on_received(),
+            tx_errors: data.total_errors_on_transmitted(),
+            rx_dropped: sysfs.rx_dropped,
+            tx_dropped: sysfs.tx_dropped,
+            mtu: sysfs.mtu,
+            addresses: addrs.iter().filter(|a| &a.name == name).map(|a| a.ip().to_string()).collect(),
+            is_up: sysfs.is_up,
+        }
+    }).collect();
+    last_totals.retain(|name, _| stats.iter().any(|i| &i.name == name));
+    stats.sort_by(|a, b| a.name.cmp(&b.name));
+    stats
+}
+
+fn collect_processes(sys: &System, elapsed: f64) -> Vec<ProcessMetrics> {
+    sys.processes().values().map(|p| {
+        #[cfg(target_os = "linux")]
+        let user = sys.users().iter().find(|u| *u.uid() == p.uid).map(|u| u.name().to_string());
+        #[cfg(not(target_os = "linux"))]
+        let user = None;
+        let disk = p.disk_usage();
+        ProcessMetrics {
+            pid: p.pid().as_u32(),
+            ppid: p.parent().map(|pid| pid.as_u32()),
+            name: p.name().to_string(),
+            cmdline: p.cmd().join(" "),
+            user,
+            cpu_usage: p.cpu_usage(),
+            rss: p.memory(),
+            virtual_memory: p.virtual_memory(),
+            disk_read: (disk.read_bytes as f64 / elapsed) as u64,
+            disk_write: (disk.written_bytes as f64 / elapsed) as u64,
+            start_time: p.start_time(),
+            state: format!("{:?}", p.status()),
+        }
+    }).collect()
+}
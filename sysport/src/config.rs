@@ -0,0 +1,271 @@
+use crate::alert::escalation::EscalationPolicy;
+use crate::alert::silence::{MaintenanceWindow, Silence};
+use crate::alert::{AlertManager, AlertRule};
+use crate::notifications::{ChannelConfig, NotificationManager};
+use crate::push::PushConfig;
+use crate::storage::StorageConfig;
+use crate::theme::CustomTheme;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+// Bump when a field changes meaning; older files are migrated in `migrate`
+pub const CONFIG_VERSION: u32 = 2;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub version: u32,
+    pub update_interval: f32, // seconds
+    pub proxy_port: u16,
+    pub reverse_proxy_port: u16,
+    pub reverse_proxy_target: String,
+    pub dns_port: u16,
+    pub packet_filter: String,
+    pub geoip_path: String,
+    pub plugin_dir: String,
+    pub remote_addr: String,
+    // Where to serve Prometheus metrics, e.g. "127.0.0.1:9184"; None disables it
+    pub prometheus_addr: Option<String>,
+    // Time-series databases that samples are pushed to
+    pub push: Vec<PushConfig>,
+    pub alert_rules: Vec<AlertRule>,
+    pub notifications: Vec<ChannelConfig>,
+    pub silences: Vec<Silence>,
+    pub maintenance_windows: Vec<MaintenanceWindow>,
+    pub escalation_policies: Vec<EscalationPolicy>,
+    pub use_custom_theme: bool,
+    pub theme: CustomTheme,
+    pub storage: StorageConfig,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            version: CONFIG_VERSION,
+            update_interval: 1.0,
+            proxy_port: 8888,
+            reverse_proxy_port: 8889,
+            reverse_proxy_target: "127.0.0.1:80".to_string(),
+            dns_port: 5353,
+            packet_filter: String::new(),
+            geoip_path: "GeoLite2-Country.mmdb".to_string(),
+            plugin_dir: "../plugins/sample_plugin/target/release".to_string(),
+            remote_addr: "0.0.0.0:8080".to_string(),
+            prometheus_addr: None,
+            push: Vec::new(),
+            alert_rules: AlertManager::new().rules,
+            notifications: Vec::new(),
+            silences: Vec::new(),
+            maintenance_windows: Vec::new(),
+            escalation_policies: Vec::new(),
+            use_custom_theme: true,
+            theme: CustomTheme::default(),
+            storage: StorageConfig::default(),
+        }
+    }
+}
+
+// Per-user config; a site-wide file is used until the user saves their own
+pub fn default_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("sysport")
+        .join("config.json")
+}
+
+#[cfg(unix)]
+fn system_path() -> Option<PathBuf> {
+    Some(PathBuf::from("/etc/sysport/config.json"))
+}
+
+#[cfg(not(unix))]
+fn system_path() -> Option<PathBuf> {
+    std::env::var_os("ProgramData").map(|dir| PathBuf::from(dir).join("sysport").join("config.json"))
+}
+
+impl Config {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let data = fs::read_to_string(path)?;
+        let invalid = |e: serde_json::Error| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), e));
+        let mut value: Value = serde_json::from_str(&data).map_err(invalid)?;
+        migrate(&mut value)?;
+        serde_json::from_value(value).map_err(invalid)
+    }
+
+    // Loads `path`, falling back to the site-wide config and then to the defaults.
+    // A broken file is reported and ignored rather than stopping startup.
+    pub fn load_or_default(path: &Path) -> Self {
+        let fallback = system_path().filter(|p| p != path);
+        for candidate in std::iter::once(path.to_path_buf()).chain(fallback) {
+            if !candidate.exists() {
+                continue;
+            }
+            match Config::load(&candidate) {
+                Ok(config) => return config,
+                Err(e) => eprintln!("Ignoring config {}: {}", candidate.display(), e),
+            }
+        }
+        Config::default()
+    }
+
+    // Writes to a temporary file first so a crash never leaves a half-written config
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let tmp = path.with_extension("json.tmp");
+        {
+            let mut file = fs::File::create(&tmp)?;
+            file.write_all(serde_json::to_string_pretty(self)?.as_bytes())?;
+            file.write_all(b"\n")?;
+            file.sync_all()?;
+        }
+        fs::rename(&tmp, path)
+    }
+
+    // Alert history lives next to the metrics segments
+    pub fn alert_manager(&self) -> AlertManager {
+        let manager = AlertManager::with_rules(self.alert_rules.clone())
+            .with_history(self.storage.dir.join("alerts.jsonl"))
+            .with_silences(self.silences.clone(), self.maintenance_windows.clone())
+            .with_escalation_policies(self.escalation_policies.clone());
+        match self.notifier() {
+            Some(notifier) => manager.with_notifier(notifier),
+            None => manager,
+        }
+    }
+
+    // None when no channels are configured
+    pub fn notifier(&self) -> Option<NotificationManager> {
+        (!self.notifications.is_empty()).then(|| NotificationManager::start(self.notifications.clone()))
+    }
+
+    // This config with the settings that changed from `before` to `after`, leaving the
+    // rest alone, so command-line overrides in `before` are never written back to the file
+    pub fn with_changes(&self, before: &Config, after: &Config) -> Config {
+        let (Ok(mut merged), Ok(old), Ok(new)) = (serde_json::to_value(self), serde_json::to_value(before), serde_json::to_value(after)) else {
+            return after.clone();
+        };
+        merge_changes(&mut merged, &old, &new);
+        serde_json::from_value(merged).unwrap_or_else(|_| after.clone())
+    }
+}
+
+// Copies what differs between `before` and `after` into `base`, field by field within objects
+fn merge_changes(base: &mut Value, before: &Value, after: &Value) {
+    match (base, before, after) {
+        (Value::Object(base), Value::Object(before), Value::Object(after)) => {
+            for (key, value) in after {
+                match (base.get_mut(key), before.get(key)) {
+                    (Some(base), Some(before)) => merge_changes(base, before, value),
+                    _ => {
+                        base.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (base, before, after) => {
+            if before != after {
+                *base = after.clone();
+            }
+        }
+    }
+}
+
+// Upgrades an older config in place so it deserializes as the current version
+fn migrate(value: &mut Value) -> io::Result<()> {
+    if !value.is_object() {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "config must be a JSON object"));
+    }
+    let version = value.get("version").and_then(Value::as_u64).unwrap_or(CONFIG_VERSION as u64);
+    if version > CONFIG_VERSION as u64 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("config version {} is newer than this build supports ({})", version, CONFIG_VERSION),
+        ));
+    }
+    // v1 rules were `{"CpuUsage": {"threshold": 90.0, "level": "Warning"}}`;
+    // v2 moves the threshold into a condition and adds duration, hysteresis and cooldown
+    if version < 2 {
+        if let Some(Value::Array(rules)) = value.get_mut("alert_rules") {
+            for rule in rules.iter_mut() {
+                let Some((kind, body)) = rule.as_object().and_then(|o| o.iter().next()) else { continue };
+                let level = body.get("level").cloned().unwrap_or(json!("Warning"));
+                let threshold = body.get("threshold").cloned().unwrap_or(Value::Null);
+                *rule = json!({
+                    "condition": { kind.as_str(): { "threshold": threshold } },
+                    "level": level,
+                });
+            }
+        }
+    }
+    value["version"] = json!(CONFIG_VERSION);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alert::{AlertCondition, AlertLevel};
+    use crate::cli::ConfigOverrides;
+    use crate::testutil::temp_dir;
+
+    #[test]
+    fn version_1_files_are_migrated() {
+        let path = temp_dir("config-v1").join("config.json");
+        fs::write(
+            &path,
+            r#"{
+                "version": 1,
+                "update_interval": 2.0,
+                "alert_rules": [
+                    {"CpuUsage": {"threshold": 90.0, "level": "Critical"}},
+                    {"NetRx": {"threshold": 1000}}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let config = Config::load(&path).unwrap();
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.update_interval, 2.0);
+        assert_eq!(config.dns_port, Config::default().dns_port);
+        assert_eq!(
+            config.alert_rules,
+            [
+                AlertRule::new(AlertCondition::CpuUsage { threshold: 90.0 }, AlertLevel::Critical),
+                AlertRule::new(AlertCondition::NetRx { threshold: 1000 }, AlertLevel::Warning),
+            ]
+        );
+    }
+
+    #[test]
+    fn newer_versions_are_refused() {
+        let path = temp_dir("config-newer").join("config.json");
+        fs::write(&path, format!(r#"{{"version": {}}}"#, CONFIG_VERSION + 1)).unwrap();
+
+        let err = Config::load(&path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn saving_keeps_command_line_overrides_out_of_the_file() {
+        let path = temp_dir("config-overrides").join("config.json");
+        let file_config = Config { proxy_port: 9000, ..Config::default() };
+        file_config.save(&path).unwrap();
+
+        // Started with --update-interval 5 --dns-port 5454, then the user changed
+        // the packet filter in the settings panel
+        let mut running = Config::load(&path).unwrap();
+        ConfigOverrides { update_interval: Some(5.0), dns_port: Some(5454), ..Default::default() }.apply(&mut running);
+        let edited = Config { packet_filter: "proto:tcp".into(), ..running.clone() };
+        file_config.with_changes(&running, &edited).save(&path).unwrap();
+
+        let saved = Config::load(&path).unwrap();
+        assert_eq!(saved, Config { proxy_port: 9000, packet_filter: "proto:tcp".into(), ..Config::default() });
+    }
+}
@@ -1,71 +1,384 @@
-use crate::metrics::Metrics;
+use crate::alert::Alert;
+use crate::metrics::{DiskMetrics, Metrics, NetInterfaceStats};
+use crate::packet_stats::PacketStats;
+use chrono::{DateTime, SecondsFormat, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::fs::File;
-use std::io::{Write, Read};
+use std::io::{self, Read, Write};
+use std::str::FromStr;
 
+// Version of the JSON capture document. Version 1 was a bare array of samples
+// with only the totals. Bump when a field changes meaning.
+pub const EXPORT_VERSION: u32 = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ExportFormat {
     Json,
     Csv,
 }
 
-pub fn export_metrics(history: &[Metrics], format: ExportFormat, path: &str) -> std::io::Result<()> {
+// Everything an export holds: every sample in full, plus the alerts and packet
+// counts seen while recording
+#[derive(Serialize, Deserialize)]
+pub struct Capture {
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    #[serde(default)]
+    pub host: String,
+    pub samples: Vec<Metrics>,
+    // Active alerts and resolved ones from the history
+    #[serde(default)]
+    pub alerts: Vec<Alert>,
+    // None when packets were not being captured
+    #[serde(default)]
+    pub packets: Option<PacketStats>,
+}
+
+impl Capture {
+    pub fn new(samples: Vec<Metrics>) -> Self {
+        Self { version: EXPORT_VERSION, exported_at: Utc::now(), host: String::new(), samples, alerts: Vec::new(), packets: None }
+    }
+}
+
+pub fn export_metrics(history: &[Metrics], format: ExportFormat, path: &str) -> io::Result<()> {
+    export_capture(&Capture::new(history.to_vec()), format, path)
+}
+
+pub fn export_capture(capture: &Capture, format: ExportFormat, path: &str) -> io::Result<()> {
+    let file = File::create(path)?;
+    write_capture(capture, format, file)
+}
+
+pub fn write_metrics<W: Write>(history: &[Metrics], format: ExportFormat, writer: W) -> io::Result<()> {
+    write_capture(&Capture::new(history.to_vec()), format, writer)
+}
+
+// CSV has one row per sample and no room for alerts or packet counts
+pub fn write_capture<W: Write>(capture: &Capture, format: ExportFormat, mut writer: W) -> io::Result<()> {
     match format {
         ExportFormat::Json => {
-            let json = serde_json::to_string_pretty(&history_as_serializable(history))?;
-            let mut file = File::create(path)?;
-            file.write_all(json.as_bytes())?;
-        }
-        ExportFormat::Csv => {
-            let mut wtr = csv::Writer::from_path(path)?;
-            wtr.write_record(&["timestamp","cpu_total","mem_used","mem_total","net_rx","net_tx"])?;
-            for m in history {
-                wtr.write_record(&[
-                    format!("{:?}", m.timestamp),
-                    format!("{:.2}", m.cpu_total),
-                    m.mem_used.to_string(),
-                    m.mem_total.to_string(),
-                    m.net_rx.to_string(),
-                    m.net_tx.to_string(),
-                ])?;
-            }
-            wtr.flush()?;
+            let json = serde_json::to_string_pretty(capture)?;
+            writer.write_all(json.as_bytes())?;
         }
+        ExportFormat::Csv => write_csv(&capture.samples, writer)?,
     }
     Ok(())
 }
 
-pub fn import_capture(path: &str) -> std::io::Result<Vec<Metrics>> {
+pub fn import_capture(path: &str) -> io::Result<Capture> {
     let mut file = File::open(path)?;
     let mut buf = String::new();
     file.read_to_string(&mut buf)?;
-    let data: Vec<SerializableMetrics> = serde_json::from_str(&buf)?;
-    Ok(data.into_iter().map(|s| s.into()).collect())
+    let invalid = |e: String| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path, e));
+    let value: Value = serde_json::from_str(&buf).map_err(|e| invalid(e.to_string()))?;
+    if value.is_array() {
+        let data: Vec<SerializableMetrics> = serde_json::from_value(value).map_err(|e| invalid(e.to_string()))?;
+        let (mut samples, elapsed): (Vec<Metrics>, Vec<Option<u64>>) = data.into_iter().map(SerializableMetrics::into_sample).unzip();
+        place_legacy_samples(&mut samples, &elapsed, written_at(path));
+        let mut capture = Capture::new(samples);
+        capture.version = 1;
+        return Ok(capture);
+    }
+    let version = value.get("version").and_then(Value::as_u64).ok_or_else(|| invalid("not a sysport capture (no version)".to_string()))?;
+    if version > EXPORT_VERSION as u64 {
+        return Err(invalid(format!(
+            "written with export schema version {}, but this sysport reads up to version {}; upgrade sysport to import it",
+            version, EXPORT_VERSION
+        )));
+    }
+    serde_json::from_value(value).map_err(|e| invalid(e.to_string()))
 }
 
-pub fn export_capture(history: &[Metrics], path: &str) -> std::io::Result<()> {
-    let json = serde_json::to_string_pretty(&history_as_serializable(history))?;
-    let mut file = File::create(path)?;
-    file.write_all(json.as_bytes())?;
-    Ok(())
+pub fn import_csv(path: &str) -> io::Result<Vec<Metrics>> {
+    let mut rdr = csv::Reader::from_path(path)?;
+    let headers = rdr.headers()?.clone();
+    let mut history = Vec::new();
+    let mut elapsed = Vec::new();
+    for (row, record) in rdr.records().enumerate() {
+        let record = record?;
+        let (sample, elapsed_ms) = metrics_from_row(&headers, &record)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: row {}: {}", path, row + 2, e)))?;
+        history.push(sample);
+        elapsed.push(elapsed_ms);
+    }
+    place_legacy_samples(&mut history, &elapsed, written_at(path));
+    Ok(history)
+}
+
+// Old exports stamped each sample with the milliseconds elapsed between sampling
+// and writing the file. Count those back from when the file was written, keeping
+// the samples in file order at least one default update interval apart.
+fn place_legacy_samples(samples: &mut [Metrics], elapsed_ms: &[Option<u64>], written: DateTime<Utc>) {
+    let interval = TimeDelta::seconds(1);
+    let mut previous: Option<DateTime<Utc>> = None;
+    for (sample, elapsed) in samples.iter_mut().zip(elapsed_ms) {
+        if let Some(ms) = elapsed {
+            let sampled = i64::try_from(*ms).ok().and_then(TimeDelta::try_milliseconds).and_then(|ago| written.checked_sub_signed(ago));
+            sample.timestamp = match (sampled, previous) {
+                (Some(ts), Some(prev)) if ts > prev => ts,
+                (Some(ts), None) => ts,
+                (_, Some(prev)) => prev + interval,
+                (None, None) => written,
+            };
+        }
+        previous = Some(sample.timestamp);
+    }
+}
+
+fn written_at(path: &str) -> DateTime<Utc> {
+    std::fs::metadata(path).and_then(|m| m.modified()).map(DateTime::from).unwrap_or_else(|_| Utc::now())
+}
+
+// Picks CSV for `.csv` files and JSON for everything else
+pub fn format_for_path(path: &std::path::Path) -> ExportFormat {
+    if path.extension().is_some_and(|e| e.eq_ignore_ascii_case("csv")) {
+        ExportFormat::Csv
+    } else {
+        ExportFormat::Json
+    }
 }
 
-pub fn import_log(path: &str) -> std::io::Result<String> {
+// CSV files carry samples only
+pub fn import_file(path: &str, format: ExportFormat) -> io::Result<Capture> {
+    match format {
+        ExportFormat::Json => import_capture(path),
+        ExportFormat::Csv => import_csv(path).map(Capture::new),
+    }
+}
+
+pub fn import_log(path: &str) -> io::Result<String> {
     let mut file = File::open(path)?;
     let mut buf = String::new();
     file.read_to_string(&mut buf)?;
     Ok(buf)
 }
 
-pub fn export_log(log: &str, path: &str) -> std::io::Result<()> {
+pub fn export_log(log: &str, path: &str) -> io::Result<()> {
     let mut file = File::create(path)?;
     file.write_all(log.as_bytes())?;
     Ok(())
 }
 
-// Helper for serializing Metrics (since Instant is not serializable)
-use serde::Serialize;
-#[derive(Serialize, serde::Deserialize)]
+// CSV layout: the totals, then `cpu<N>` per core, `disk[<mount>].<field>` per
+// disk and `net[<interface>].<field>` per interface, and the process list as a
+// JSON array. Disks and interfaces missing from a sample leave their cells empty.
+const BASE_COLUMNS: [&str; 8] = ["timestamp", "cpu_total", "mem_used", "mem_total", "net_rx", "net_tx", "selected_interface", "interfaces"];
+const DISK_FIELDS: [&str; 12] = [
+    "name", "fs_type", "total", "available", "inodes_total", "inodes_free", "read_bytes", "write_bytes", "read_iops", "write_iops", "queue_depth", "service_time_ms",
+];
+const NET_FIELDS: [&str; 11] = ["rx", "tx", "rx_packets", "tx_packets", "rx_errors", "tx_errors", "rx_dropped", "tx_dropped", "mtu", "is_up", "addresses"];
+// Separates names and addresses within a cell; interface names may contain spaces
+const LIST_SEPARATOR: &str = ";";
+
+fn write_csv<W: Write>(history: &[Metrics], writer: W) -> io::Result<()> {
+    let cores = history.iter().map(|m| m.cpu_usage.len()).max().unwrap_or(0);
+    let mut mounts: Vec<&str> = Vec::new();
+    let mut nics: Vec<&str> = Vec::new();
+    for m in history {
+        for disk in &m.disks {
+            if !mounts.contains(&disk.mount_point.as_str()) {
+                mounts.push(&disk.mount_point);
+            }
+        }
+        for nic in &m.net_per_interface {
+            if !nics.contains(&nic.name.as_str()) {
+                nics.push(&nic.name);
+            }
+        }
+    }
+    let mut header: Vec<String> = BASE_COLUMNS.iter().map(|c| c.to_string()).collect();
+    header.extend((0..cores).map(|i| format!("cpu{}", i)));
+    for mount in &mounts {
+        header.extend(DISK_FIELDS.iter().map(|f| format!("disk[{}].{}", mount, f)));
+    }
+    for nic in &nics {
+        header.extend(NET_FIELDS.iter().map(|f| format!("net[{}].{}", nic, f)));
+    }
+    header.push("processes".to_string());
+
+    let mut wtr = csv::Writer::from_writer(writer);
+    wtr.write_record(&header)?;
+    for m in history {
+        let mut row = vec![
+            m.timestamp.to_rfc3339_opts(SecondsFormat::Millis, true),
+            format!("{:.2}", m.cpu_total),
+            m.mem_used.to_string(),
+            m.mem_total.to_string(),
+            m.net_rx.to_string(),
+            m.net_tx.to_string(),
+            m.selected_interface.clone().unwrap_or_default(),
+            m.interfaces.join(LIST_SEPARATOR),
+        ];
+        row.extend((0..cores).map(|i| m.cpu_usage.get(i).map(|u| format!("{:.2}", u)).unwrap_or_default()));
+        for mount in &mounts {
+            let disk = m.disks.iter().find(|d| d.mount_point == *mount);
+            row.extend(DISK_FIELDS.iter().map(|f| disk.map(|d| disk_field(d, f)).unwrap_or_default()));
+        }
+        for nic in &nics {
+            let stats = m.net_per_interface.iter().find(|n| n.name == *nic);
+            row.extend(NET_FIELDS.iter().map(|f| stats.map(|n| net_field(n, f)).unwrap_or_default()));
+        }
+        row.push(if m.processes.is_empty() { String::new() } else { serde_json::to_string(&m.processes)? });
+        wtr.write_record(&row)?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+fn disk_field(d: &DiskMetrics, field: &str) -> String {
+    match field {
+        "name" => d.name.clone(),
+        "fs_type" => d.fs_type.clone(),
+        "total" => d.total.to_string(),
+        "available" => d.available.to_string(),
+        "inodes_total" => d.inodes_total.to_string(),
+        "inodes_free" => d.inodes_free.to_string(),
+        "read_bytes" => d.read_bytes.to_string(),
+        "write_bytes" => d.write_bytes.to_string(),
+        "read_iops" => format!("{:.2}", d.read_iops),
+        "write_iops" => format!("{:.2}", d.write_iops),
+        "queue_depth" => format!("{:.2}", d.queue_depth),
+        "service_time_ms" => format!("{:.2}", d.service_time_ms),
+        _ => String::new(),
+    }
+}
+
+fn set_disk_field(d: &mut DiskMetrics, field: &str, value: &str) -> Result<(), String> {
+    match field {
+        "name" => d.name = value.to_string(),
+        "fs_type" => d.fs_type = value.to_string(),
+        "total" => d.total = parse(value)?,
+        "available" => d.available = parse(value)?,
+        "inodes_total" => d.inodes_total = parse(value)?,
+        "inodes_free" => d.inodes_free = parse(value)?,
+        "read_bytes" => d.read_bytes = parse(value)?,
+        "write_bytes" => d.write_bytes = parse(value)?,
+        "read_iops" => d.read_iops = parse(value)?,
+        "write_iops" => d.write_iops = parse(value)?,
+        "queue_depth" => d.queue_depth = parse(value)?,
+        "service_time_ms" => d.service_time_ms = parse(value)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+fn net_field(n: &NetInterfaceStats, field: &str) -> String {
+    match field {
+        "rx" => n.rx.to_string(),
+        "tx" => n.tx.to_string(),
+        "rx_packets" => n.rx_packets.to_string(),
+        "tx_packets" => n.tx_packets.to_string(),
+        "rx_errors" => n.rx_errors.to_string(),
+        "tx_errors" => n.tx_errors.to_string(),
+        "rx_dropped" => n.rx_dropped.to_string(),
+        "tx_dropped" => n.tx_dropped.to_string(),
+        "mtu" => n.mtu.map(|v| v.to_string()).unwrap_or_default(),
+        "is_up" => n.is_up.map(|v| v.to_string()).unwrap_or_default(),
+        "addresses" => n.addresses.join(LIST_SEPARATOR),
+        _ => String::new(),
+    }
+}
+
+fn set_net_field(n: &mut NetInterfaceStats, field: &str, value: &str) -> Result<(), String> {
+    match field {
+        "rx" => n.rx = parse(value)?,
+        "tx" => n.tx = parse(value)?,
+        "rx_packets" => n.rx_packets = parse(value)?,
+        "tx_packets" => n.tx_packets = parse(value)?,
+        "rx_errors" => n.rx_errors = parse(value)?,
+        "tx_errors" => n.tx_errors = parse(value)?,
+        "rx_dropped" => n.rx_dropped = parse(value)?,
+        "tx_dropped" => n.tx_dropped = parse(value)?,
+        "mtu" => n.mtu = Some(parse(value)?),
+        "is_up" => n.is_up = Some(parse(value)?),
+        "addresses" => n.addresses = split_list(value),
+        _ => {}
+    }
+    Ok(())
+}
+
+// Reads a CSV row by column name, so older files with fewer columns still import
+// and unknown columns are skipped. Also returns the legacy elapsed-milliseconds
+// timestamp, if the row has one.
+fn metrics_from_row(headers: &csv::StringRecord, record: &csv::StringRecord) -> Result<(Metrics, Option<u64>), String> {
+    let mut m = Metrics::default();
+    let mut elapsed_ms = None;
+    for (column, value) in headers.iter().zip(record.iter()) {
+        if value.is_empty() {
+            continue;
+        }
+        match column {
+            "timestamp" => {
+                m.timestamp = match DateTime::parse_from_rfc3339(value) {
+                    Ok(ts) => ts.with_timezone(&Utc),
+                    // Old exports wrote milliseconds since sampling; see `place_legacy_samples`
+                    Err(_) if value.parse::<u64>().is_ok() => {
+                        elapsed_ms = value.parse().ok();
+                        m.timestamp
+                    }
+                    Err(e) => return Err(format!("timestamp {:?}: {}", value, e)),
+                }
+            }
+            "cpu_total" => m.cpu_total = parse(value)?,
+            "mem_used" => m.mem_used = parse(value)?,
+            "mem_total" => m.mem_total = parse(value)?,
+            "net_rx" => m.net_rx = parse(value)?,
+            "net_tx" => m.net_tx = parse(value)?,
+            "selected_interface" => m.selected_interface = Some(value.to_string()),
+            "interfaces" => m.interfaces = split_list(value),
+            "processes" => m.processes = serde_json::from_str(value).map_err(|e| format!("processes: {}", e))?,
+            column => {
+                if let Some(core) = column.strip_prefix("cpu").and_then(|i| i.parse::<usize>().ok()) {
+                    if m.cpu_usage.len() <= core {
+                        m.cpu_usage.resize(core + 1, 0.0);
+                    }
+                    m.cpu_usage[core] = parse(value)?;
+                } else if let Some((mount, field)) = keyed_column(column, "disk") {
+                    let index = match m.disks.iter().position(|d| d.mount_point == mount) {
+                        Some(index) => index,
+                        None => {
+                            m.disks.push(DiskMetrics { mount_point: mount.to_string(), ..Default::default() });
+                            m.disks.len() - 1
+                        }
+                    };
+                    set_disk_field(&mut m.disks[index], field, value).map_err(|e| format!("{}: {}", column, e))?;
+                } else if let Some((name, field)) = keyed_column(column, "net") {
+                    let index = match m.net_per_interface.iter().position(|n| n.name == name) {
+                        Some(index) => index,
+                        None => {
+                            m.net_per_interface.push(NetInterfaceStats { name: name.to_string(), ..Default::default() });
+                            m.net_per_interface.len() - 1
+                        }
+                    };
+                    set_net_field(&mut m.net_per_interface[index], field, value).map_err(|e| format!("{}: {}", column, e))?;
+                }
+            }
+        }
+    }
+    Ok((m, elapsed_ms))
+}
+
+// "disk[/var].total" -> ("/var", "total")
+fn keyed_column<'a>(column: &'a str, prefix: &str) -> Option<(&'a str, &'a str)> {
+    column.strip_prefix(prefix)?.strip_prefix('[')?.rsplit_once("].")
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value.split(LIST_SEPARATOR).filter(|s| !s.is_empty()).map(|s| s.to_string()).collect()
+}
+
+fn parse<T: FromStr>(value: &str) -> Result<T, String>
+where
+    T::Err: std::fmt::Display,
+{
+    value.trim().parse().map_err(|e| format!("{:?}: {}", value, e))
+}
+
+// Version 1 captures: a bare array with only the totals
+#[derive(Serialize, Deserialize)]
 struct SerializableMetrics {
-    timestamp: u128,
+    timestamp: ExportTimestamp,
     cpu_total: f32,
     mem_total: u64,
     mem_used: u64,
@@ -73,27 +386,164 @@ struct SerializableMetrics {
     net_tx: u64,
 }
 
-impl From<SerializableMetrics> for Metrics {
-    fn from(s: SerializableMetrics) -> Self {
-        Metrics {
-            timestamp: std::time::Instant::now(),
-            cpu_total: s.cpu_total,
-            mem_total: s.mem_total,
-            mem_used: s.mem_used,
-            net_rx: s.net_rx,
-            net_tx: s.net_tx,
+// Captures written before timestamps were wall-clock carry the milliseconds
+// elapsed since sampling; see `place_legacy_samples`.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum ExportTimestamp {
+    Utc(DateTime<Utc>),
+    LegacyElapsedMs(u64),
+}
+
+impl SerializableMetrics {
+    // The sample, and its legacy elapsed-milliseconds timestamp if it has one
+    fn into_sample(self) -> (Metrics, Option<u64>) {
+        let (timestamp, elapsed_ms) = match self.timestamp {
+            ExportTimestamp::Utc(ts) => (ts, None),
+            ExportTimestamp::LegacyElapsedMs(ms) => (Utc::now(), Some(ms)),
+        };
+        let sample = Metrics {
+            timestamp,
+            cpu_total: self.cpu_total,
+            mem_total: self.mem_total,
+            mem_used: self.mem_used,
+            net_rx: self.net_rx,
+            net_tx: self.net_tx,
             ..Default::default()
-        }
+        };
+        (sample, elapsed_ms)
     }
 }
 
-fn history_as_serializable(history: &[Metrics]) -> Vec<SerializableMetrics> {
-    history.iter().map(|m| SerializableMetrics {
-        timestamp: m.timestamp.elapsed().as_millis(),
-        cpu_total: m.cpu_total,
-        mem_total: m.mem_total,
-        mem_used: m.mem_used,
-        net_rx: m.net_rx,
-        net_tx: m.net_tx,
-    }).collect()
-} 
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alert::AlertLevel;
+    use crate::metrics::ProcessMetrics;
+    use crate::testutil::temp_dir;
+    use chrono::TimeZone;
+
+    fn sample(second: u32) -> Metrics {
+        Metrics {
+            timestamp: Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, second).unwrap() + TimeDelta::milliseconds(250),
+            cpu_usage: vec![12.5, 0.25],
+            cpu_total: 6.5,
+            mem_total: 16 << 30,
+            mem_used: 5 << 30,
+            disks: vec![DiskMetrics {
+                name: "sda1".into(),
+                mount_point: "/var".into(),
+                fs_type: "ext4".into(),
+                total: 100 << 30,
+                available: 40 << 30,
+                read_iops: 1.5,
+                service_time_ms: 0.75,
+                ..Default::default()
+            }],
+            net_rx: 2048,
+            net_tx: 512,
+            selected_interface: Some("eth 0".into()),
+            interfaces: vec!["eth 0".into(), "lo".into()],
+            net_per_interface: vec![NetInterfaceStats {
+                name: "eth 0".into(),
+                rx: 2048,
+                tx: 512,
+                mtu: Some(1500),
+                is_up: Some(true),
+                addresses: vec!["10.0.0.2".into(), "fe80::1".into()],
+                ..Default::default()
+            }],
+            processes: vec![ProcessMetrics { pid: 42, name: "postgres".into(), cpu_usage: 3.5, ..Default::default() }],
+        }
+    }
+
+    fn write(path: &std::path::Path, contents: &str) -> String {
+        std::fs::write(path, contents).unwrap();
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn version_1_captures_are_spread_out_by_sample_order() {
+        let dir = temp_dir("export-v1");
+        let path = write(
+            &dir.join("old.json"),
+            r#"[
+                {"timestamp": 3000, "cpu_total": 10.0, "mem_total": 100, "mem_used": 10, "net_rx": 1, "net_tx": 2},
+                {"timestamp": 2000, "cpu_total": 20.0, "mem_total": 100, "mem_used": 20, "net_rx": 3, "net_tx": 4},
+                {"timestamp": 2000, "cpu_total": 30.0, "mem_total": 100, "mem_used": 30, "net_rx": 5, "net_tx": 6}
+            ]"#,
+        );
+        let written = written_at(&path);
+
+        let capture = import_capture(&path).unwrap();
+        assert_eq!(capture.version, 1);
+        let totals: Vec<_> = capture.samples.iter().map(|m| (m.cpu_total, m.mem_used, m.net_rx, m.net_tx)).collect();
+        assert_eq!(totals, [(10.0, 10, 1, 2), (20.0, 20, 3, 4), (30.0, 30, 5, 6)]);
+        let stamps: Vec<_> = capture.samples.iter().map(|m| m.timestamp).collect();
+        // Counted back from when the file was written; the tied last sample
+        // goes one interval after the one before it
+        assert_eq!(stamps, [written - TimeDelta::seconds(3), written - TimeDelta::seconds(2), written - TimeDelta::seconds(1)]);
+    }
+
+    #[test]
+    fn legacy_csv_rows_are_spread_out_by_sample_order() {
+        let dir = temp_dir("export-v1-csv");
+        let path = write(&dir.join("old.csv"), "timestamp,cpu_total,mem_used,mem_total,net_rx,net_tx\n1500,1.00,1,2,3,4\n500,2.00,1,2,3,4\n");
+        let written = written_at(&path);
+
+        let samples = import_csv(&path).unwrap();
+        let stamps: Vec<_> = samples.iter().map(|m| m.timestamp).collect();
+        assert_eq!(stamps, [written - TimeDelta::milliseconds(1500), written - TimeDelta::milliseconds(500)]);
+    }
+
+    #[test]
+    fn captures_from_a_newer_schema_are_rejected() {
+        let dir = temp_dir("export-newer");
+        let path = write(&dir.join("new.json"), &format!(r#"{{"version": {}, "exported_at": "2030-01-01T00:00:00Z", "samples": []}}"#, EXPORT_VERSION + 1));
+
+        let err = import_capture(&path).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains(&format!("schema version {}", EXPORT_VERSION + 1)), "{}", err);
+    }
+
+    #[test]
+    fn json_captures_round_trip() {
+        let dir = temp_dir("export-json");
+        let path = dir.join("capture.json");
+        let mut capture = Capture::new(vec![sample(0), sample(1)]);
+        capture.host = "db1".into();
+        capture.alerts = vec![Alert::test("CpuUsage", AlertLevel::Warning)];
+        capture.packets = Some(PacketStats { tcp_count: 7, tcp_bytes: 4096, ..Default::default() });
+        export_capture(&capture, format_for_path(&path), path.to_str().unwrap()).unwrap();
+
+        let imported = import_file(path.to_str().unwrap(), format_for_path(&path)).unwrap();
+        assert_eq!(serde_json::to_value(&imported).unwrap(), serde_json::to_value(&capture).unwrap());
+    }
+
+    #[test]
+    fn csv_samples_round_trip() {
+        let dir = temp_dir("export-csv");
+        let path = dir.join("capture.csv");
+        // The second sample has no disks, which leaves its disk cells empty
+        let samples = vec![sample(0), Metrics { disks: vec![], ..sample(1) }];
+        export_metrics(&samples, format_for_path(&path), path.to_str().unwrap()).unwrap();
+
+        let imported = import_file(path.to_str().unwrap(), format_for_path(&path)).unwrap();
+        assert_eq!(serde_json::to_value(&imported.samples).unwrap(), serde_json::to_value(&samples).unwrap());
+    }
+
+    #[test]
+    fn exported_timestamps_import_unchanged() {
+        let dir = temp_dir("export-timestamps");
+        // JSON keeps full precision; CSV keeps milliseconds
+        let precise = Utc.with_ymd_and_hms(2024, 3, 1, 23, 59, 59).unwrap() + TimeDelta::nanoseconds(123_456_789);
+        let json = dir.join("capture.json");
+        export_metrics(&[Metrics { timestamp: precise, ..Default::default() }], ExportFormat::Json, json.to_str().unwrap()).unwrap();
+        assert_eq!(import_file(json.to_str().unwrap(), ExportFormat::Json).unwrap().samples[0].timestamp, precise);
+
+        let millis = Utc.with_ymd_and_hms(2024, 3, 1, 23, 59, 59).unwrap() + TimeDelta::milliseconds(123);
+        let csv = dir.join("capture.csv");
+        export_metrics(&[Metrics { timestamp: millis, ..Default::default() }], ExportFormat::Csv, csv.to_str().unwrap()).unwrap();
+        assert_eq!(import_file(csv.to_str().unwrap(), ExportFormat::Csv).unwrap().samples[0].timestamp, millis);
+    }
+}
@@ -0,0 +1,152 @@
+use crate::alert::AlertManager;
+use crate::capture;
+use crate::collector::Collector;
+use crate::config::Config;
+use crate::packet_stats::PacketStats;
+#[cfg(feature = "prometheus")]
+use crate::prometheus;
+use crate::push;
+use crate::remote::RemoteServer;
+
+use std::collections::{HashSet, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+enum Event {
+    Tick,
+    Reload,
+    Shutdown,
+}
+
+// Runs the collector, alerting, packet capture, remote server, Prometheus endpoint and metric push without a window
+// until SIGTERM/SIGINT. SIGHUP re-reads the config file and applies its alert rules and notification channels.
+pub fn run(config: Config, config_path: PathBuf) -> std::io::Result<()> {
+    let alert_manager = config.alert_manager();
+    report_rule_errors(&alert_manager);
+    let mut collector = Collector::spawn(Some(config.storage.clone()), alert_manager);
+    let geoip_reader = fs::read(&config.geoip_path)
+        .ok()
+        .and_then(|data| maxminddb::Reader::from_source(data).ok());
+    let raw_packets = Arc::new(Mutex::new(VecDeque::with_capacity(capture::MAX_RAW_PACKETS)));
+    let packet_stats = Arc::new(Mutex::new(PacketStats::default()));
+    capture::spawn_capture(None, raw_packets, packet_stats.clone(), geoip_reader);
+    #[cfg(feature = "prometheus")]
+    if let Some(addr) = &config.prometheus_addr {
+        prometheus::spawn(addr, prometheus::Sources { metrics: collector.metrics.clone(), packets: packet_stats, alert_manager: collector.alert_manager.clone() });
+    }
+    push::start(&config.push, &collector);
+
+    let rt = tokio::runtime::Runtime::new()?;
+    rt.block_on(async {
+        let server = RemoteServer::start(&config.remote_addr).await;
+        let mut signals = Signals::new()?;
+        let mut ticker = tokio::time::interval(Duration::from_secs(1));
+        let mut firing: HashSet<u64> = HashSet::new();
+        println!("SysPort running headless (pid {})", std::process::id());
+        loop {
+            let event = tokio::select! {
+                _ = ticker.tick() => Event::Tick,
+                event = signals.recv() => event,
+            };
+            match event {
+                Event::Tick => {
+                    let metrics = collector.metrics.lock().unwrap().clone();
+                    server.broadcast_metrics(&metrics).await;
+                    log_alert_changes(&collector, &mut firing);
+                }
+                Event::Reload => {
+                    reload(&collector, &config_path);
+                }
+                Event::Shutdown => break,
+            }
+        }
+        Ok::<(), std::io::Error>(())
+    })?;
+
+    println!("Shutting down");
+    collector.stop();
+    Ok(())
+}
+
+// Only alert rules, notification channels, silences and escalation policies are applied live; other settings take effect on restart
+fn reload(collector: &Collector, config_path: &Path) {
+    match Config::load(config_path) {
+        Ok(config) => {
+            let count = config.alert_rules.len();
+            let mut am = collector.alert_manager.lock().unwrap();
+            am.set_notifier(config.notifier());
+            am.set_silences(config.silences, config.maintenance_windows);
+            am.set_escalation_policies(config.escalation_policies);
+            am.set_rules(config.alert_rules);
+            println!("Reloaded {} alert rules from {}", count, config_path.display());
+            report_rule_errors(&am);
+        }
+        Err(e) => eprintln!("Config reload failed, keeping current settings: {}", e),
+    }
+}
+
+// Rules that fail to parse are kept but never fire, so say so up front
+fn report_rule_errors(am: &AlertManager) {
+    for (index, error) in am.rule_errors() {
+        eprintln!("Alert rule {} ignored: {}", index + 1, error);
+    }
+}
+
+// Prints alerts as they start firing and when they resolve
+fn log_alert_changes(collector: &Collector, firing: &mut HashSet<u64>) {
+    let am = collector.alert_manager.lock().unwrap();
+    let current: HashSet<u64> = am.active_alerts.iter().filter(|a| a.is_firing()).map(|a| a.id).collect();
+    for alert in am.active_alerts.iter().filter(|a| a.is_firing() && !firing.contains(&a.id)) {
+        println!("[{:?}] #{} {}", alert.level, alert.id, alert.message);
+    }
+    for id in firing.difference(&current) {
+        if let Some(alert) = am.history.iter().rev().find(|a| a.id == *id) {
+            println!("[Resolved] #{} {}", alert.id, alert.message);
+        }
+    }
+    *firing = current;
+}
+
+#[cfg(unix)]
+struct Signals {
+    term: tokio::signal::unix::Signal,
+    int: tokio::signal::unix::Signal,
+    hup: tokio::signal::unix::Signal,
+}
+
+#[cfg(unix)]
+impl Signals {
+    fn new() -> std::io::Result<Self> {
+        use tokio::signal::unix::{signal, SignalKind};
+        Ok(Self {
+            term: signal(SignalKind::terminate())?,
+            int: signal(SignalKind::interrupt())?,
+            hup: signal(SignalKind::hangup())?,
+        })
+    }
+
+    async fn recv(&mut self) -> Event {
+        tokio::select! {
+            _ = self.term.recv() => Event::Shutdown,
+            _ = self.int.recv() => Event::Shutdown,
+            _ = self.hup.recv() => Event::Reload,
+        }
+    }
+}
+
+#[cfg(not(unix))]
+struct Signals;
+
+#[cfg(not(unix))]
+impl Signals {
+    fn new() -> std::io::Result<Self> {
+        Ok(Self)
+    }
+
+    async fn recv(&mut self) -> Event {
+        let _ = tokio::signal::ctrl_c().await;
+        Event::Shutdown
+    }
+}
@@ -0,0 +1,314 @@
+use crate::metrics::Metrics;
+use chrono::{DateTime, TimeZone, Utc};
+use std::collections::VecDeque;
+
+// 5 minutes of raw samples at the 200 ms poll rate
+pub const RAW_CAPACITY: usize = 1500;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Resolution {
+    Raw,
+    TenSeconds,
+    OneMinute,
+    OneHour,
+}
+
+impl Resolution {
+    pub const ALL: [Resolution; 4] = [Resolution::Raw, Resolution::TenSeconds, Resolution::OneMinute, Resolution::OneHour];
+
+    pub fn bucket_secs(&self) -> i64 {
+        match self {
+            Resolution::Raw => 0,
+            Resolution::TenSeconds => 10,
+            Resolution::OneMinute => 60,
+            Resolution::OneHour => 3600,
+        }
+    }
+
+    // Number of points kept: 5 min raw, 1 h of 10 s, 24 h of 1 min, 7 days of 1 h
+    pub fn capacity(&self) -> usize {
+        match self {
+            Resolution::Raw => RAW_CAPACITY,
+            Resolution::TenSeconds => 360,
+            Resolution::OneMinute => 1440,
+            Resolution::OneHour => 168,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Resolution::Raw => "Raw (last 5 min)",
+            Resolution::TenSeconds => "10 s (last hour)",
+            Resolution::OneMinute => "1 min (last day)",
+            Resolution::OneHour => "1 h (last week)",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Stat {
+    pub min: f64,
+    pub avg: f64,
+    pub max: f64,
+}
+
+impl Stat {
+    pub fn single(value: f64) -> Self {
+        Self { min: value, avg: value, max: value }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RollupPoint {
+    pub start: DateTime<Utc>,
+    pub cpu_total: Stat,
+    pub mem_used: Stat,
+    pub mem_total: u64,
+    pub net_rx: Stat,
+    pub net_tx: Stat,
+}
+
+impl RollupPoint {
+    pub fn from_sample(m: &Metrics) -> Self {
+        Self {
+            start: m.timestamp,
+            cpu_total: Stat::single(m.cpu_total as f64),
+            mem_used: Stat::single(m.mem_used as f64),
+            mem_total: m.mem_total,
+            net_rx: Stat::single(m.net_rx as f64),
+            net_tx: Stat::single(m.net_tx as f64),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+struct StatAccumulator {
+    min: f64,
+    max: f64,
+    sum: f64,
+    count: usize,
+}
+
+impl StatAccumulator {
+    fn add(&mut self, value: f64) {
+        if self.count == 0 {
+            self.min = value;
+            self.max = value;
+        } else {
+            self.min = self.min.min(value);
+            self.max = self.max.max(value);
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+
+    fn stat(&self) -> Stat {
+        Stat {
+            min: self.min,
+            avg: if self.count == 0 { 0.0 } else { self.sum / self.count as f64 },
+            max: self.max,
+        }
+    }
+}
+
+// Open bucket of a rollup tier, folded into a RollupPoint when the next bucket starts
+struct Bucket {
+    start: DateTime<Utc>,
+    cpu_total: StatAccumulator,
+    mem_used: StatAccumulator,
+    mem_total: u64,
+    net_rx: StatAccumulator,
+    net_tx: StatAccumulator,
+}
+
+impl Bucket {
+    fn new(start: DateTime<Utc>) -> Self {
+        Self {
+            start,
+            cpu_total: StatAccumulator::default(),
+            mem_used: StatAccumulator::default(),
+            mem_total: 0,
+            net_rx: StatAccumulator::default(),
+            net_tx: StatAccumulator::default(),
+        }
+    }
+
+    fn add(&mut self, m: &Metrics) {
+        self.cpu_total.add(m.cpu_total as f64);
+        self.mem_used.add(m.mem_used as f64);
+        self.mem_total = m.mem_total;
+        self.net_rx.add(m.net_rx as f64);
+        self.net_tx.add(m.net_tx as f64);
+    }
+
+    fn finish(&self) -> RollupPoint {
+        RollupPoint {
+            start: self.start,
+            cpu_total: self.cpu_total.stat(),
+            mem_used: self.mem_used.stat(),
+            mem_total: self.mem_total,
+            net_rx: self.net_rx.stat(),
+            net_tx: self.net_tx.stat(),
+        }
+    }
+}
+
+struct Tier {
+    resolution: Resolution,
+    points: VecDeque<RollupPoint>,
+    open: Option<Bucket>,
+}
+
+impl Tier {
+    fn new(resolution: Resolution) -> Self {
+        Self { resolution, points: VecDeque::with_capacity(resolution.capacity()), open: None }
+    }
+
+    fn bucket_start(&self, ts: DateTime<Utc>) -> DateTime<Utc> {
+        let secs = self.resolution.bucket_secs();
+        let start = ts.timestamp() - ts.timestamp().rem_euclid(secs);
+        Utc.timestamp_opt(start, 0).single().unwrap_or(ts)
+    }
+
+    fn add(&mut self, m: &Metrics) {
+        let start = self.bucket_start(m.timestamp);
+        if let Some(open) = &self.open {
+            if open.start != start {
+                let point = open.finish();
+                push_bounded(&mut self.points, point, self.resolution.capacity());
+                self.open = None;
+            }
+        }
+        self.open.get_or_insert_with(|| Bucket::new(start)).add(m);
+    }
+}
+
+fn push_bounded<T>(ring: &mut VecDeque<T>, item: T, capacity: usize) {
+    if ring.len() >= capacity {
+        ring.pop_front();
+    }
+    ring.push_back(item);
+}
+
+// Ring-buffer history: raw samples for the last few minutes plus min/avg/max
+// rollups at 10 s, 1 min and 1 h, so memory stays bounded over long sessions.
+pub struct MetricsHistory {
+    raw: VecDeque<Metrics>,
+    tiers: Vec<Tier>,
+}
+
+impl Default for MetricsHistory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MetricsHistory {
+    pub fn new() -> Self {
+        Self {
+            raw: VecDeque::with_capacity(RAW_CAPACITY),
+            tiers: Resolution::ALL[1..].iter().map(|r| Tier::new(*r)).collect(),
+        }
+    }
+
+    // Per-process data is dropped from stored samples; only the latest Metrics keeps it
+    pub fn push(&mut self, mut m: Metrics) {
+        m.processes.clear();
+        for tier in &mut self.tiers {
+            tier.add(&m);
+        }
+        push_bounded(&mut self.raw, m, RAW_CAPACITY);
+    }
+
+    pub fn raw(&self) -> &VecDeque<Metrics> {
+        &self.raw
+    }
+
+    pub fn raw_samples(&self) -> Vec<Metrics> {
+        self.raw.iter().cloned().collect()
+    }
+
+    // Completed points of a resolution, oldest first, including the bucket still being filled
+    pub fn points(&self, resolution: Resolution) -> Vec<RollupPoint> {
+        match self.tiers.iter().find(|t| t.resolution == resolution) {
+            Some(tier) => {
+                let mut points: Vec<RollupPoint> = tier.points.iter().cloned().collect();
+                if let Some(open) = &tier.open {
+                    points.push(open.finish());
+                }
+                points
+            }
+            None => self.raw.iter().map(RollupPoint::from_sample).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::ProcessMetrics;
+    use chrono::Duration;
+
+    // On an hour boundary, so every tier's buckets line up with it
+    fn t0() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap()
+    }
+
+    fn sample(secs: i64, cpu: f32) -> Metrics {
+        Metrics { timestamp: t0() + Duration::seconds(secs), cpu_total: cpu, mem_total: 100, mem_used: cpu as u64, ..Default::default() }
+    }
+
+    fn cpu(point: &RollupPoint) -> (f64, f64, f64) {
+        (point.cpu_total.min, point.cpu_total.avg, point.cpu_total.max)
+    }
+
+    #[test]
+    fn rollups_keep_min_avg_and_max_per_bucket() {
+        let mut history = MetricsHistory::new();
+        for (secs, value) in [(0, 10.0), (4, 30.0), (8, 20.0), (10, 50.0), (15, 70.0), (61, 5.0)] {
+            history.push(sample(secs, value));
+        }
+
+        let ten = history.points(Resolution::TenSeconds);
+        let starts: Vec<i64> = ten.iter().map(|p| (p.start - t0()).num_seconds()).collect();
+        assert_eq!(starts, [0, 10, 60]);
+        assert_eq!(cpu(&ten[0]), (10.0, 20.0, 30.0));
+        assert_eq!(cpu(&ten[1]), (50.0, 60.0, 70.0));
+        // The bucket still being filled is included
+        assert_eq!(cpu(&ten[2]), (5.0, 5.0, 5.0));
+
+        let minutes = history.points(Resolution::OneMinute);
+        assert_eq!(minutes.len(), 2);
+        assert_eq!(cpu(&minutes[0]), (10.0, 36.0, 70.0));
+        assert_eq!(history.points(Resolution::OneHour).len(), 1);
+        assert_eq!(history.points(Resolution::Raw).len(), 6);
+    }
+
+    #[test]
+    fn rings_drop_their_oldest_points_when_full() {
+        let mut history = MetricsHistory::new();
+        for i in 0..RAW_CAPACITY as i64 + 5 {
+            history.push(sample(i, 1.0));
+        }
+        assert_eq!(history.raw().len(), RAW_CAPACITY);
+        assert_eq!(history.raw()[0].timestamp, t0() + Duration::seconds(5));
+
+        let mut history = MetricsHistory::new();
+        let buckets = Resolution::TenSeconds.capacity() as i64 + 3;
+        for i in 0..buckets {
+            history.push(sample(i * 10, 1.0));
+        }
+        // Full ring of completed points plus the open bucket
+        let ten = history.points(Resolution::TenSeconds);
+        assert_eq!(ten.len(), Resolution::TenSeconds.capacity() + 1);
+        assert_eq!(ten[0].start, t0() + Duration::seconds(20));
+        assert_eq!(ten.last().unwrap().start, t0() + Duration::seconds((buckets - 1) * 10));
+    }
+
+    #[test]
+    fn stored_samples_drop_per_process_data() {
+        let mut history = MetricsHistory::new();
+        history.push(Metrics { processes: vec![ProcessMetrics::default()], ..sample(0, 1.0) });
+        assert!(history.raw()[0].processes.is_empty());
+    }
+}
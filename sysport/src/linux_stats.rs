@@ -0,0 +1,103 @@
+// Readers for kernel statistics that sysinfo does not expose.
+// On other platforms every reader returns the empty/default value.
+use std::collections::HashMap;
+
+#[derive(Clone, Default)]
+pub struct IfaceSysfs {
+    pub mtu: Option<u32>,
+    pub is_up: Option<bool>,
+    pub rx_dropped: u64,
+    pub tx_dropped: u64,
+}
+
+#[cfg(target_os = "linux")]
+pub fn read_iface_sysfs(name: &str) -> IfaceSysfs {
+    use std::fs;
+    let base = format!("/sys/class/net/{}", name);
+    let read_u64 = |file: &str| -> Option<u64> {
+        fs::read_to_string(format!("{}/{}", base, file)).ok()?.trim().parse().ok()
+    };
+    IfaceSysfs {
+        mtu: read_u64("mtu").map(|v| v as u32),
+        is_up: fs::read_to_string(format!("{}/operstate", base)).ok().map(|s| {
+            let state = s.trim();
+            // Loopback and some virtual devices report "unknown" while carrying traffic
+            state == "up" || state == "unknown"
+        }),
+        rx_dropped: read_u64("statistics/rx_dropped").unwrap_or(0),
+        tx_dropped: read_u64("statistics/tx_dropped").unwrap_or(0),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn read_iface_sysfs(_name: &str) -> IfaceSysfs {
+    IfaceSysfs::default()
+}
+
+// Cumulative counters for one block device, as found in /proc/diskstats
+#[derive(Clone, Copy, Default)]
+pub struct DiskCounters {
+    pub reads: u64,
+    pub writes: u64,
+    pub sectors_read: u64,
+    pub sectors_written: u64,
+    pub in_flight: u64,
+    // Time the device was busy with at least one request
+    pub ms_io: u64,
+    pub ms_weighted: u64,
+}
+
+#[cfg(target_os = "linux")]
+pub fn read_diskstats() -> HashMap<String, DiskCounters> {
+    let mut stats = HashMap::new();
+    if let Ok(content) = std::fs::read_to_string("/proc/diskstats") {
+        for line in content.lines() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 14 {
+                continue;
+            }
+            let num = |i: usize| fields[i].parse::<u64>().unwrap_or(0);
+            stats.insert(fields[2].to_string(), DiskCounters {
+                reads: num(3),
+                sectors_read: num(5),
+                writes: num(7),
+                sectors_written: num(9),
+                in_flight: num(11),
+                ms_io: num(12),
+                ms_weighted: num(13),
+            });
+        }
+    }
+    stats
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn read_diskstats() -> HashMap<String, DiskCounters> {
+    HashMap::new()
+}
+
+// Maps a device path such as /dev/sda1 or /dev/mapper/root to its /proc/diskstats name
+pub fn block_device_name(device: &str) -> String {
+    let resolved = std::fs::canonicalize(device)
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_else(|_| device.to_string());
+    resolved.strip_prefix("/dev/").unwrap_or(&resolved).to_string()
+}
+
+// Returns (total, free) inodes of the filesystem mounted at `path`
+#[cfg(unix)]
+pub fn inode_usage(path: &std::path::Path) -> Option<(u64, u64)> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    Some((stat.f_files as u64, stat.f_ffree as u64))
+}
+
+#[cfg(not(unix))]
+pub fn inode_usage(_path: &std::path::Path) -> Option<(u64, u64)> {
+    None
+}
@@ -1,11 +1,27 @@
 mod metrics;
+mod history;
+mod storage;
 mod alert;
 mod export;
 mod theme;
 mod packet_stats;
+#[cfg(feature = "prometheus")]
+mod prometheus;
+mod push;
 mod remote;
 mod plugins;
 mod app;
+mod linux_stats;
+mod collector;
+mod capture;
+mod headless;
+mod cli;
+mod config;
+mod tui;
+mod notifications;
+mod report;
+#[cfg(test)]
+mod testutil;
 use eframe::{egui, epi};
 use egui::plot::{Plot, Line, Values, Value};
 use sysinfo::{System, SystemExt, DiskExt, NetworkExt, NetworksExt};
@@ -29,13 +45,43 @@ fn load_icon() -> Option<IconData> {
 }
 
 fn main() {
+    let args = <cli::Cli as clap::Parser>::parse();
+    let config_path = args.config.clone().unwrap_or_else(config::default_path);
+    let file_config = config::Config::load_or_default(&config_path);
+    let mut config = file_config.clone();
+    args.overrides.apply(&mut config);
+    if let Some(command) = args.command {
+        if let Err(e) = cli::run(command, &config) {
+            eprintln!("error: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    #[cfg(not(feature = "prometheus"))]
+    if let Some(addr) = config.prometheus_addr.as_ref().filter(|_| !args.tui) {
+        eprintln!("Prometheus endpoint {} not started: sysport was built without the `prometheus` feature", addr);
+    }
+    if args.headless {
+        if let Err(e) = headless::run(config, config_path) {
+            eprintln!("Headless mode failed: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if args.tui {
+        if let Err(e) = tui::run(&config) {
+            eprintln!("Terminal UI failed: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
     let mut options = eframe::NativeOptions::default();
     if let Some(icon) = load_icon() {
         options.icon_data = Some(icon);
     }
     let mut plugin_system = plugins::PluginSystem::new();
-    plugin_system.load_plugins("../plugins/sample_plugin/target/release"); // Adjust path as needed
-    eframe::run_native(Box::new(SysPortApp::default()), options);
+    plugin_system.load_plugins(&config.plugin_dir);
+    eframe::run_native(Box::new(SysPortApp::new(config, file_config, config_path)), options);
 }
 
 // In plotting, use:
@@ -1,8 +1,10 @@
-use std::time::Instant;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Metrics {
-    pub timestamp: Instant,
+    pub timestamp: DateTime<Utc>,
     pub cpu_usage: Vec<f32>, // per core
     pub cpu_total: f32,
     pub mem_total: u64,
@@ -13,12 +15,13 @@ pub struct Metrics {
     pub selected_interface: Option<String>,
     pub interfaces: Vec<String>,
     pub net_per_interface: Vec<NetInterfaceStats>,
+    pub processes: Vec<ProcessMetrics>,
 }
 
 impl Default for Metrics {
     fn default() -> Self {
         Self {
-            timestamp: Instant::now(),
+            timestamp: Utc::now(),
             cpu_usage: vec![],
             cpu_total: 0.0,
             mem_total: 0,
@@ -29,20 +32,59 @@ impl Default for Metrics {
             selected_interface: None,
             interfaces: vec![],
             net_per_interface: vec![],
+            processes: vec![],
         }
     }
 }
 
-#[derive(Clone, Default)]
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct DiskMetrics {
     pub name: String,
+    pub mount_point: String,
+    pub fs_type: String,
     pub total: u64,
     pub available: u64,
+    pub inodes_total: u64,
+    pub inodes_free: u64,
+    pub read_bytes: u64, // bytes/s
+    pub write_bytes: u64, // bytes/s
+    pub read_iops: f64,
+    pub write_iops: f64,
+    pub queue_depth: f64, // average requests in flight
+    pub service_time_ms: f64, // average busy time per completed request, excluding queueing
 }
 
-#[derive(Clone, Default)]
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct NetInterfaceStats {
     pub name: String,
-    pub rx: u64,
-    pub tx: u64,
-} 
\ No newline at end of file
+    pub rx: u64, // bytes/s
+    pub tx: u64, // bytes/s
+    pub rx_packets: u64,
+    pub tx_packets: u64,
+    pub rx_errors: u64,
+    pub tx_errors: u64,
+    pub rx_dropped: u64,
+    pub tx_dropped: u64,
+    pub mtu: Option<u32>,
+    pub addresses: Vec<String>,
+    pub is_up: Option<bool>,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProcessMetrics {
+    pub pid: u32,
+    pub ppid: Option<u32>,
+    pub name: String,
+    pub cmdline: String,
+    pub user: Option<String>,
+    pub cpu_usage: f32, // percent of one core, may exceed 100
+    pub rss: u64, // KB
+    pub virtual_memory: u64, // KB
+    pub disk_read: u64, // bytes/s
+    pub disk_write: u64, // bytes/s
+    pub start_time: u64, // seconds since epoch
+    pub state: String,
+}
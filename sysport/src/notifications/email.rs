@@ -0,0 +1,194 @@
+use super::{template, NotificationChannel};
+use crate::alert::Alert;
+
+use lettre::message::header::ContentType;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+const DEFAULT_SUBJECT: &str = "[sysport] {{level}} {{state}} on {{host}}: {{source}}";
+const DEFAULT_BODY: &str = "{{message}}\n\nHost: {{host}}\nLevel: {{level}}\nState: {{state}}\nSource: {{source}}\nStarted: {{started_at}}\nEnded: {{ended_at}}\nAlert: #{{id}}\n";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub enum SmtpSecurity {
+    #[default]
+    StartTls,
+    Tls,
+    // Unencrypted, e.g. for a local relay or a test server
+    Plain,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EmailConfig {
+    pub host: String,
+    // Defaults to 587 for STARTTLS, 465 for TLS and 25 for plain
+    #[serde(default)]
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub security: SmtpSecurity,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    pub from: String,
+    pub to: Vec<String>,
+    // Templates, see `template`
+    #[serde(default)]
+    pub subject: Option<String>,
+    #[serde(default)]
+    pub body: Option<String>,
+}
+
+impl EmailConfig {
+    // Bad addresses would fail every send, so they are caught before the channel starts
+    pub fn validate(&self) -> Result<(), String> {
+        if self.to.is_empty() {
+            return Err("no recipients".to_string());
+        }
+        for address in std::iter::once(&self.from).chain(&self.to) {
+            address.parse::<Mailbox>().map_err(|e| format!("{}: {}", address, e))?;
+        }
+        Ok(())
+    }
+}
+
+pub struct EmailChannel {
+    config: EmailConfig,
+    transport: Result<SmtpTransport, String>,
+}
+
+impl EmailChannel {
+    pub fn new(config: EmailConfig) -> Self {
+        let transport = transport(&config).map_err(|e| e.to_string());
+        Self { config, transport }
+    }
+
+    fn message(&self, alert: &Alert, host: &str) -> Result<Message, String> {
+        let mailbox = |address: &str| address.parse::<Mailbox>().map_err(|e| format!("{}: {}", address, e));
+        let mut builder = Message::builder().from(mailbox(&self.config.from)?);
+        for to in &self.config.to {
+            builder = builder.to(mailbox(to)?);
+        }
+        // Headers cannot span lines, so digests only list their alerts in the body
+        let subject = template::render(self.config.subject.as_deref().unwrap_or(DEFAULT_SUBJECT), alert, host, template::plain);
+        let subject = subject.lines().next().unwrap_or_default().to_string();
+        let body = template::render(self.config.body.as_deref().unwrap_or(DEFAULT_BODY), alert, host, template::plain);
+        builder.subject(subject).header(ContentType::TEXT_PLAIN).body(body).map_err(|e| e.to_string())
+    }
+}
+
+fn transport(config: &EmailConfig) -> Result<SmtpTransport, lettre::transport::smtp::Error> {
+    let mut builder = match config.security {
+        SmtpSecurity::StartTls => SmtpTransport::starttls_relay(&config.host)?,
+        SmtpSecurity::Tls => SmtpTransport::relay(&config.host)?,
+        SmtpSecurity::Plain => SmtpTransport::builder_dangerous(&config.host),
+    };
+    if let Some(port) = config.port {
+        builder = builder.port(port);
+    }
+    if let Some(username) = &config.username {
+        builder = builder.credentials(Credentials::new(username.clone(), config.password.clone().unwrap_or_default()));
+    }
+    Ok(builder.timeout(Some(Duration::from_secs(10))).build())
+}
+
+impl NotificationChannel for EmailChannel {
+    fn send(&mut self, alert: &Alert, host: &str) -> Result<(), String> {
+        let message = self.message(alert, host)?;
+        let transport = self.transport.as_ref().map_err(|e| e.clone())?;
+        transport.send(&message).map(|_| ()).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alert::AlertLevel;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    // Accepts one connection, answers every command and returns what the client sent
+    fn smtp_stand_in(listener: TcpListener) -> thread::JoinHandle<Vec<String>> {
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut writer = stream.try_clone().unwrap();
+            let mut lines = Vec::new();
+            let mut in_data = false;
+            writer.write_all(b"220 localhost ESMTP test\r\n").unwrap();
+            for line in BufReader::new(stream).lines() {
+                let line = line.unwrap();
+                lines.push(line.clone());
+                let reply: &[u8] = if in_data {
+                    if line != "." {
+                        continue;
+                    }
+                    in_data = false;
+                    b"250 queued\r\n"
+                } else if line.starts_with("EHLO") {
+                    b"250-localhost\r\n250 8BITMIME\r\n"
+                } else if line == "DATA" {
+                    in_data = true;
+                    b"354 go ahead\r\n"
+                } else if line == "QUIT" {
+                    writer.write_all(b"221 bye\r\n").unwrap();
+                    break;
+                } else {
+                    b"250 OK\r\n"
+                };
+                writer.write_all(reply).unwrap();
+            }
+            lines
+        })
+    }
+
+    fn config(port: u16) -> EmailConfig {
+        EmailConfig {
+            host: "127.0.0.1".to_string(),
+            port: Some(port),
+            security: SmtpSecurity::Plain,
+            username: None,
+            password: None,
+            from: "sysport <sysport@example.com>".to_string(),
+            to: vec!["oncall@example.com".to_string(), "ops@example.com".to_string()],
+            subject: Some("[{{level}}] {{host}}: {{source}}\nsecond line".to_string()),
+            body: Some("{{message}} (alert #{{id}}, {{state}})".to_string()),
+        }
+    }
+
+    fn alert() -> Alert {
+        Alert { id: 42, message: "Disk /var usage high: 97.0%".to_string(), ..Alert::test("DiskUsage /var", AlertLevel::Critical) }
+    }
+
+    #[test]
+    fn sends_through_a_local_smtp_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = smtp_stand_in(listener);
+
+        EmailChannel::new(config(port)).send(&alert(), "web1").unwrap();
+        let lines = server.join().unwrap();
+
+        assert!(lines.contains(&"MAIL FROM:<sysport@example.com>".to_string()), "{:?}", lines);
+        assert!(lines.contains(&"RCPT TO:<oncall@example.com>".to_string()), "{:?}", lines);
+        assert!(lines.contains(&"RCPT TO:<ops@example.com>".to_string()), "{:?}", lines);
+        // Only the first line of the subject template is used
+        assert!(lines.contains(&"Subject: [Critical] web1: DiskUsage /var".to_string()), "{:?}", lines);
+        assert!(lines.contains(&"Disk /var usage high: 97.0% (alert #42, Firing)".to_string()), "{:?}", lines);
+    }
+
+    #[test]
+    fn rejects_bad_addresses_up_front() {
+        assert_eq!(config(25).validate(), Ok(()));
+        let mut bad = config(25);
+        bad.to.push("not an address".to_string());
+        assert!(bad.validate().unwrap_err().starts_with("not an address"));
+        bad.to.clear();
+        assert_eq!(bad.validate(), Err("no recipients".to_string()));
+        let mut bad = config(25);
+        bad.from = "sysport@".to_string();
+        assert!(bad.validate().is_err());
+    }
+}
@@ -0,0 +1,33 @@
+use super::NotificationChannel;
+use crate::alert::Alert;
+
+use std::process::Command;
+
+pub struct ExecChannel {
+    pub command: String,
+    pub args: Vec<String>,
+}
+
+impl NotificationChannel for ExecChannel {
+    fn send(&mut self, alert: &Alert, host: &str) -> Result<(), String> {
+        let time = |t: Option<chrono::DateTime<chrono::Utc>>| t.map(|t| t.to_rfc3339()).unwrap_or_default();
+        let output = Command::new(&self.command)
+            .args(&self.args)
+            .env("SYSPORT_ALERT_ID", alert.id.to_string())
+            .env("SYSPORT_ALERT_LEVEL", format!("{:?}", alert.level))
+            .env("SYSPORT_ALERT_STATE", format!("{:?}", alert.state))
+            .env("SYSPORT_ALERT_SOURCE", &alert.source)
+            .env("SYSPORT_ALERT_MESSAGE", &alert.message)
+            .env("SYSPORT_ALERT_STARTED_AT", alert.started_at.to_rfc3339())
+            .env("SYSPORT_ALERT_FIRED_AT", time(alert.fired_at))
+            .env("SYSPORT_ALERT_ENDED_AT", time(alert.ended_at))
+            .env("SYSPORT_HOST", host)
+            .output()
+            .map_err(|e| format!("cannot run {}: {}", self.command, e))?;
+        if output.status.success() {
+            return Ok(());
+        }
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Err(format!("{} exited with {}: {}", self.command, output.status, stderr.trim()))
+    }
+}
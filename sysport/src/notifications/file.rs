@@ -0,0 +1,25 @@
+use super::{template, NotificationChannel};
+use crate::alert::Alert;
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+pub struct FileChannel {
+    pub path: PathBuf,
+    pub line: Option<String>,
+}
+
+impl NotificationChannel for FileChannel {
+    fn send(&mut self, alert: &Alert, host: &str) -> Result<(), String> {
+        let line = match &self.line {
+            Some(line) => template::render(line, alert, host, template::plain),
+            None => serde_json::to_string(alert).map_err(|e| e.to_string())?,
+        };
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path).map_err(|e| format!("{}: {}", self.path.display(), e))?;
+        writeln!(file, "{}", line).map_err(|e| e.to_string())
+    }
+}
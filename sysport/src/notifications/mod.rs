@@ -1,14 +1,272 @@
+mod email;
+mod exec;
+mod file;
+pub mod queue;
+mod syslog;
+pub mod template;
+mod webhook;
+
+use crate::alert::{Alert, AlertLevel, AlertState};
+use crate::report;
+pub use email::EmailConfig;
+use queue::{ChannelQueue, DeliveryLog, DeliveryPolicy};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use sysinfo::{System, SystemExt};
+
 #[derive(Debug, Clone)]
 pub struct Notification {
     pub title: String,
     pub message: String,
 }
 
-pub struct NotificationManager;
+// Somewhere alerts can be delivered. Sending may block; channels run on the
+// notification thread, never on the collector.
+pub trait NotificationChannel: Send {
+    fn send(&mut self, alert: &Alert, host: &str) -> Result<(), String>;
+
+    // Several alerts at once, when a channel is over its rate limit. By default
+    // they are folded into one alert at the highest level, one line each.
+    fn send_digest(&mut self, alerts: &[Alert], host: &str) -> Result<(), String> {
+        let Some(first) = alerts.first() else {
+            return Ok(());
+        };
+        let mut digest = first.clone();
+        if let Some(level) = alerts.iter().map(|a| &a.level).max() {
+            digest.level = level.clone();
+        }
+        digest.state = if alerts.iter().all(|a| a.state == AlertState::Resolved) { AlertState::Resolved } else { AlertState::Firing };
+        digest.source = "digest".to_string();
+        digest.message = format!("{} alerts:", alerts.len());
+        for alert in alerts {
+            digest.message.push_str(&format!("\n[{:?}] {:?}: {}", alert.level, alert.state, alert.message));
+        }
+        self.send(&digest, host)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ChannelKind {
+    // POSTs `body` (a template, see `template`) or a default JSON document
+    Webhook {
+        url: String,
+        #[serde(default)]
+        body: Option<String>,
+        #[serde(default)]
+        headers: Vec<(String, String)>,
+    },
+    // Runs `command` with the alert in SYSPORT_ALERT_* environment variables
+    Exec {
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+    },
+    // The local syslog socket, which systemd-journald also reads
+    Syslog {
+        #[serde(default = "default_ident")]
+        ident: String,
+    },
+    // Appends one line per alert: `line` as a template, or the alert as JSON
+    File {
+        path: PathBuf,
+        #[serde(default)]
+        line: Option<String>,
+    },
+    Desktop,
+    // Sends a mail over SMTP to every address in `to`
+    Email(EmailConfig),
+}
+
+fn default_ident() -> String {
+    "sysport".to_string()
+}
+
+// Restricts a channel to matching alerts. Both patterns are regular expressions;
+// a missing pattern matches everything.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Route {
+    // Matched against the alert source, e.g. "DiskUsage /var" or "ProcessMissing"
+    pub source: Option<String>,
+    pub message: Option<String>,
+    // Alerts matching an exclude route are dropped even if another route matches
+    pub exclude: bool,
+}
+
+impl Route {
+    pub fn compile(&self) -> Result<CompiledRoute, regex::Error> {
+        let compile = |pattern: &Option<String>| pattern.as_deref().map(Regex::new).transpose();
+        Ok(CompiledRoute { source: compile(&self.source)?, message: compile(&self.message)? })
+    }
+}
+
+// A compiled Route; a missing pattern matches everything
+pub struct CompiledRoute {
+    source: Option<Regex>,
+    message: Option<Regex>,
+}
+
+impl CompiledRoute {
+    fn matches(&self, alert: &Alert) -> bool {
+        let is_match = |re: &Option<Regex>, text: &str| re.as_ref().is_none_or(|re| re.is_match(text));
+        is_match(&self.source, &alert.source) && is_match(&self.message, &alert.message)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChannelConfig {
+    pub name: String,
+    pub kind: ChannelKind,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    // Alerts below this level are not sent
+    #[serde(default = "default_min_level")]
+    pub min_level: AlertLevel,
+    #[serde(default = "default_true")]
+    pub send_resolved: bool,
+    // Empty sends everything; otherwise an alert must match an include route
+    #[serde(default)]
+    pub routes: Vec<Route>,
+    #[serde(default)]
+    pub delivery: DeliveryPolicy,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_min_level() -> AlertLevel {
+    AlertLevel::Info
+}
+
+impl ChannelConfig {
+    // Compiles the routes once, one entry per route, so patterns are not recompiled
+    // per alert. Invalid patterns are reported here and never match. A channel with
+    // settings that could never deliver (e.g. a bad email address) is not started.
+    pub fn validate(&self) -> Result<Vec<Option<CompiledRoute>>, String> {
+        if let ChannelKind::Email(email) = &self.kind {
+            email.validate().map_err(|e| format!("channel {}: {}", self.name, e))?;
+        }
+        Ok(self
+            .routes
+            .iter()
+            .map(|route| route.compile().map_err(|e| report::error(format!("Notification route ignored: channel {}: {}", self.name, e))).ok())
+            .collect())
+    }
+
+    // `compiled` holds the routes from `validate`
+    pub fn accepts(&self, alert: &Alert, compiled: &[Option<CompiledRoute>]) -> bool {
+        if !self.enabled || alert.level < self.min_level {
+            return false;
+        }
+        if alert.state == AlertState::Resolved && !self.send_resolved {
+            return false;
+        }
+        let matches = |(_, route): &(&Route, &Option<CompiledRoute>)| route.as_ref().is_some_and(|r| r.matches(alert));
+        let routes = self.routes.iter().zip(compiled);
+        if routes.clone().filter(|(r, _)| r.exclude).any(|route| matches(&route)) {
+            return false;
+        }
+        let mut includes = routes.filter(|(r, _)| !r.exclude).peekable();
+        includes.peek().is_none() || includes.any(|route| matches(&route))
+    }
+
+    // Escalated alerts go only to the channels their policy named, when it named
+    // any; recoveries also reach every named channel that saw the alert
+    pub fn wants(&self, alert: &Alert, compiled: &[Option<CompiledRoute>]) -> bool {
+        let resolved = alert.state == AlertState::Resolved;
+        let named = self.enabled && alert.routed_to.contains(&self.name) && (!resolved || self.send_resolved);
+        if !resolved && alert.escalation > 0 && !alert.routed_to.is_empty() {
+            return named;
+        }
+        named || self.accepts(alert, compiled)
+    }
+
+    fn build(&self) -> Box<dyn NotificationChannel> {
+        match &self.kind {
+            ChannelKind::Webhook { url, body, headers } => Box::new(webhook::WebhookChannel::new(url, body.clone(), headers.clone())),
+            ChannelKind::Exec { command, args } => Box::new(exec::ExecChannel { command: command.clone(), args: args.clone() }),
+            ChannelKind::Syslog { ident } => Box::new(syslog::SyslogChannel { ident: ident.clone() }),
+            ChannelKind::File { path, line } => Box::new(file::FileChannel { path: path.clone(), line: line.clone() }),
+            ChannelKind::Desktop => Box::new(DesktopChannel),
+            ChannelKind::Email(config) => Box::new(email::EmailChannel::new(config.clone())),
+        }
+    }
+}
+
+// Delivers alerts to the configured channels on a background thread, each
+// through its own queue (see `queue`). Dropping the manager stops the thread
+// once queued alerts are sent or have run out of retries.
+pub struct NotificationManager {
+    tx: Sender<Alert>,
+    log: DeliveryLog,
+}
 
 impl NotificationManager {
+    pub fn start(configs: Vec<ChannelConfig>) -> Self {
+        let mut queues: Vec<ChannelQueue> = configs
+            .into_iter()
+            .filter_map(|config| match config.validate() {
+                Ok(routes) => Some(ChannelQueue::new(config, routes)),
+                Err(e) => {
+                    report::error(format!("Notification channel not started: {}", e));
+                    None
+                }
+            })
+            .collect();
+        let (tx, rx) = mpsc::channel::<Alert>();
+        let log = DeliveryLog::default();
+        let worker_log = log.clone();
+        thread::spawn(move || {
+            let host = System::new().host_name().unwrap_or_else(|| "localhost".to_string());
+            let mut open = true;
+            while open || queues.iter().any(|q| !q.is_idle()) {
+                match rx.recv_timeout(Duration::from_secs(1)) {
+                    Ok(alert) => {
+                        for queue in queues.iter_mut().filter(|q| q.wants(&alert)) {
+                            queue.enqueue(alert.clone(), &worker_log);
+                        }
+                    }
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => {
+                        if !open {
+                            thread::sleep(Duration::from_secs(1));
+                        }
+                        open = false;
+                    }
+                }
+                for queue in &mut queues {
+                    queue.flush(&host, &worker_log);
+                }
+            }
+        });
+        Self { tx, log }
+    }
+
+    // A manager whose alerts arrive on the returned receiver instead of channels
+    #[cfg(test)]
+    pub fn capture() -> (Self, mpsc::Receiver<Alert>) {
+        let (tx, rx) = mpsc::channel();
+        (Self { tx, log: DeliveryLog::default() }, rx)
+    }
+
+    // Recent deliveries, newest last, for the notification center
+    pub fn log(&self) -> DeliveryLog {
+        Arc::clone(&self.log)
+    }
+
+    pub fn dispatch(&self, alert: &Alert) {
+        let _ = self.tx.send(alert.clone());
+    }
+
     pub fn send_notification(notification: &Notification) {
-        #[cfg(target_os = "linux")]
+        #[cfg(all(target_os = "linux", feature = "notify-rust"))]
         {
             use notify_rust::Notification as NRNotification;
             let _ = NRNotification::new()
@@ -16,6 +274,10 @@ impl NotificationManager {
                 .body(&notification.message)
                 .show();
         }
+        #[cfg(all(target_os = "linux", not(feature = "notify-rust")))]
+        {
+            println!("Notification: {} - {}", notification.title, notification.message);
+        }
         #[cfg(target_os = "macos")]
         {
             // TODO: Use mac-notification-sys or AppleScript
@@ -27,4 +289,80 @@ impl NotificationManager {
             println!("Windows notification: {} - {}", notification.title, notification.message);
         }
     }
-} 
\ No newline at end of file
+}
+
+struct DesktopChannel;
+
+impl NotificationChannel for DesktopChannel {
+    fn send(&mut self, alert: &Alert, _host: &str) -> Result<(), String> {
+        let title = match alert.state {
+            AlertState::Resolved => format!("Resolved: {:?}", alert.level),
+            _ => format!("{:?} alert", alert.level),
+        };
+        NotificationManager::send_notification(&Notification { title, message: alert.message.clone() });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(source: &str, exclude: bool) -> Route {
+        Route { source: Some(source.to_string()), message: None, exclude }
+    }
+
+    #[test]
+    fn routes_are_compiled_once_and_invalid_ones_never_match() {
+        let config = ChannelConfig {
+            name: "ops".to_string(),
+            kind: ChannelKind::Desktop,
+            enabled: true,
+            min_level: AlertLevel::Info,
+            send_resolved: true,
+            routes: vec![route("^DiskUsage", false), route("(unclosed", false), route("/boot$", true)],
+            delivery: DeliveryPolicy::default(),
+        };
+        let routes = config.validate().unwrap();
+        assert_eq!(routes.iter().map(Option::is_some).collect::<Vec<_>>(), vec![true, false, true]);
+        let accepts = |source: &str| config.accepts(&Alert::test(source, AlertLevel::Warning), &routes);
+        assert!(accepts("DiskUsage /var"));
+        assert!(!accepts("DiskUsage /boot"));
+        assert!(!accepts("CpuUsage"));
+        assert!(!accepts("(unclosed"));
+
+        // A channel whose only include route is invalid sends nothing
+        let config = ChannelConfig { routes: vec![route("(unclosed", false)], ..config };
+        let routes = config.validate().unwrap();
+        assert!(!config.accepts(&Alert::test("CpuUsage", AlertLevel::Warning), &routes));
+    }
+
+    #[test]
+    fn escalated_alerts_go_only_to_the_channels_their_policy_named() {
+        let channel = |name: &str, routes| ChannelConfig {
+            name: name.to_string(),
+            kind: ChannelKind::Desktop,
+            enabled: true,
+            min_level: AlertLevel::Info,
+            send_resolved: true,
+            routes,
+            delivery: DeliveryPolicy::default(),
+        };
+        let email = channel("email", Vec::new());
+        // The pager takes nothing unless an escalation routes it there
+        let pager = channel("pager", vec![route("^$", false)]);
+        let (email_routes, pager_routes) = (email.validate().unwrap(), pager.validate().unwrap());
+        let wants = |alert: &Alert| (email.wants(alert, &email_routes), pager.wants(alert, &pager_routes));
+
+        let alert = Alert::test("CpuUsage", AlertLevel::Warning);
+        assert_eq!(wants(&alert), (true, false));
+        let escalated = Alert { level: AlertLevel::Critical, escalation: 1, routed_to: vec!["pager".to_string()], ..alert.clone() };
+        assert_eq!(wants(&escalated), (false, true));
+        // Recoveries reach the channels that saw the alert either way
+        let resolved = Alert { state: AlertState::Resolved, ..escalated.clone() };
+        assert_eq!(wants(&resolved), (true, true));
+        // A step that names no channels leaves routing to the usual rules
+        let raised = Alert { level: AlertLevel::Critical, escalation: 1, ..alert };
+        assert_eq!(wants(&raised), (true, false));
+    }
+}
@@ -0,0 +1,375 @@
+// Per-channel delivery: dedupe, rate limiting, retry with backoff and digests
+use super::{ChannelConfig, CompiledRoute, NotificationChannel};
+use crate::alert::{Alert, AlertLevel, AlertState};
+use crate::report;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// Entries kept for the notification center
+pub const MAX_LOG: usize = 200;
+const MAX_BACKOFF_SECS: u64 = 600;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeliveryPolicy {
+    // The same rule and subject firing again (or resolving again) within this
+    // window is not sent again, so a flapping rule notifies once
+    pub dedupe_secs: u64,
+    // Sends per minute; anything over the limit waits and goes out as a digest.
+    // 0 means no limit.
+    pub max_per_minute: u32,
+    // Failed sends are retried after 5s, 10s, 20s, ... up to this many attempts
+    pub max_attempts: u32,
+    // This many alerts waiting at once are sent as one digest
+    pub digest_after: usize,
+}
+
+impl Default for DeliveryPolicy {
+    fn default() -> Self {
+        Self { dedupe_secs: 300, max_per_minute: 10, max_attempts: 5, digest_after: 5 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeliveryStatus {
+    Delivered,
+    Retrying,
+    Failed,
+    Suppressed,
+}
+
+#[derive(Debug, Clone)]
+pub struct DeliveryRecord {
+    pub at: DateTime<Utc>,
+    pub channel: String,
+    pub summary: String,
+    pub status: DeliveryStatus,
+    pub detail: String,
+}
+
+pub type DeliveryLog = Arc<Mutex<VecDeque<DeliveryRecord>>>;
+
+struct Delivery {
+    alerts: Vec<Alert>,
+    attempts: u32,
+    next_attempt: Instant,
+}
+
+impl Delivery {
+    fn summary(&self) -> String {
+        match self.alerts.as_slice() {
+            [alert] => format!("#{} {:?}: {}", alert.id, alert.state, alert.message),
+            alerts => format!("Digest of {} alerts", alerts.len()),
+        }
+    }
+}
+
+pub struct ChannelQueue {
+    pub config: ChannelConfig,
+    // Compiled by `ChannelConfig::validate`; invalid routes are None and never match
+    routes: Vec<Option<CompiledRoute>>,
+    channel: Box<dyn NotificationChannel>,
+    waiting: VecDeque<Alert>,
+    retries: Vec<Delivery>,
+    sent_at: VecDeque<Instant>,
+    // Last time each (source, level, resolved) was accepted, for dedupe. The level
+    // is part of it so escalations are never taken for repeats.
+    last_seen: HashMap<(String, AlertLevel, bool), Instant>,
+    // Alerts whose firing was deduped; their recovery is dropped too
+    suppressed: HashSet<u64>,
+}
+
+impl ChannelQueue {
+    pub fn new(config: ChannelConfig, routes: Vec<Option<CompiledRoute>>) -> Self {
+        let channel = config.build();
+        Self::with_channel(config, routes, channel)
+    }
+
+    fn with_channel(config: ChannelConfig, routes: Vec<Option<CompiledRoute>>, channel: Box<dyn NotificationChannel>) -> Self {
+        Self {
+            config,
+            routes,
+            channel,
+            waiting: VecDeque::new(),
+            retries: Vec::new(),
+            sent_at: VecDeque::new(),
+            last_seen: HashMap::new(),
+            suppressed: HashSet::new(),
+        }
+    }
+
+    pub fn wants(&self, alert: &Alert) -> bool {
+        self.config.wants(alert, &self.routes)
+    }
+
+    pub fn enqueue(&mut self, alert: Alert, log: &DeliveryLog) {
+        self.enqueue_at(alert, log, Instant::now());
+    }
+
+    fn enqueue_at(&mut self, alert: Alert, log: &DeliveryLog, now: Instant) {
+        let resolved = alert.state == AlertState::Resolved;
+        let window = Duration::from_secs(self.config.delivery.dedupe_secs);
+        let key = (alert.source.clone(), alert.level.clone(), resolved);
+        let duplicate = if resolved {
+            self.suppressed.remove(&alert.id)
+        } else {
+            self.last_seen.get(&key).is_some_and(|t| now.duration_since(*t) < window)
+        };
+        self.last_seen.retain(|_, t| now.duration_since(*t) < window);
+        if duplicate {
+            if !resolved {
+                self.suppressed.insert(alert.id);
+            }
+            let summary = format!("#{} {:?}: {}", alert.id, alert.state, alert.message);
+            record(log, &self.config.name, summary, DeliveryStatus::Suppressed, "duplicate within the dedupe window".to_string());
+            return;
+        }
+        self.last_seen.insert(key, now);
+        self.waiting.push_back(alert);
+    }
+
+    // Sends whatever is due and allowed by the rate limit
+    pub fn flush(&mut self, host: &str, log: &DeliveryLog) {
+        self.flush_at(host, log, Instant::now());
+    }
+
+    fn flush_at(&mut self, host: &str, log: &DeliveryLog, now: Instant) {
+        while self.sent_at.front().is_some_and(|t| now.duration_since(*t) >= Duration::from_secs(60)) {
+            self.sent_at.pop_front();
+        }
+        let limit = match self.config.delivery.max_per_minute {
+            0 => usize::MAX,
+            n => n as usize,
+        };
+        let mut budget = limit.saturating_sub(self.sent_at.len());
+        let mut due = Vec::new();
+        let (ready, later): (Vec<Delivery>, Vec<Delivery>) = std::mem::take(&mut self.retries).into_iter().partition(|d| d.next_attempt <= now);
+        self.retries = later;
+        for delivery in ready {
+            if budget == 0 {
+                self.retries.push(delivery);
+            } else {
+                budget -= 1;
+                due.push(delivery);
+            }
+        }
+        // Alerts over the limit keep waiting and go out together as a digest
+        if !self.waiting.is_empty() && budget > 0 {
+            if self.waiting.len() >= self.config.delivery.digest_after.max(1) || self.waiting.len() > budget {
+                due.push(Delivery { alerts: self.waiting.drain(..).collect(), attempts: 0, next_attempt: now });
+            } else {
+                due.extend(self.waiting.drain(..).map(|alert| Delivery { alerts: vec![alert], attempts: 0, next_attempt: now }));
+            }
+        }
+        for mut delivery in due {
+            self.sent_at.push_back(now);
+            delivery.attempts += 1;
+            let result = match delivery.alerts.as_slice() {
+                [alert] => self.channel.send(alert, host),
+                alerts => self.channel.send_digest(alerts, host),
+            };
+            match result {
+                Ok(()) => record(log, &self.config.name, delivery.summary(), DeliveryStatus::Delivered, String::new()),
+                Err(e) if delivery.attempts < self.config.delivery.max_attempts => {
+                    let backoff = (5u64 << (delivery.attempts - 1).min(10)).min(MAX_BACKOFF_SECS);
+                    delivery.next_attempt = now + Duration::from_secs(backoff);
+                    let detail = format!("attempt {} failed, retrying in {}s: {}", delivery.attempts, backoff, e);
+                    record(log, &self.config.name, delivery.summary(), DeliveryStatus::Retrying, detail);
+                    self.retries.push(delivery);
+                }
+                Err(e) => {
+                    report::error(format!("Notification via {} failed: {}", self.config.name, e));
+                    let detail = format!("gave up after {} attempts: {}", delivery.attempts, e);
+                    record(log, &self.config.name, delivery.summary(), DeliveryStatus::Failed, detail);
+                }
+            }
+        }
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.waiting.is_empty() && self.retries.is_empty()
+    }
+}
+
+fn record(log: &DeliveryLog, channel: &str, summary: String, status: DeliveryStatus, detail: String) {
+    let mut log = log.lock().unwrap();
+    if log.len() >= MAX_LOG {
+        log.pop_front();
+    }
+    log.push_back(DeliveryRecord { at: Utc::now(), channel: channel.to_string(), summary, status, detail });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notifications::ChannelKind;
+
+    // Records what it was asked to send; the next `failures` sends fail
+    #[derive(Clone, Default)]
+    struct FakeChannel {
+        sent: Arc<Mutex<Vec<Vec<u64>>>>,
+        attempts: Arc<Mutex<u32>>,
+        failures: Arc<Mutex<u32>>,
+    }
+
+    impl FakeChannel {
+        fn deliver(&mut self, ids: Vec<u64>) -> Result<(), String> {
+            *self.attempts.lock().unwrap() += 1;
+            let mut failures = self.failures.lock().unwrap();
+            if *failures > 0 {
+                *failures -= 1;
+                return Err("connection refused".to_string());
+            }
+            self.sent.lock().unwrap().push(ids);
+            Ok(())
+        }
+
+        fn take_sent(&self) -> Vec<Vec<u64>> {
+            std::mem::take(&mut self.sent.lock().unwrap())
+        }
+    }
+
+    impl NotificationChannel for FakeChannel {
+        fn send(&mut self, alert: &Alert, _host: &str) -> Result<(), String> {
+            self.deliver(vec![alert.id])
+        }
+
+        fn send_digest(&mut self, alerts: &[Alert], _host: &str) -> Result<(), String> {
+            self.deliver(alerts.iter().map(|a| a.id).collect())
+        }
+    }
+
+    struct Harness {
+        queue: ChannelQueue,
+        channel: FakeChannel,
+        log: DeliveryLog,
+        t0: Instant,
+    }
+
+    impl Harness {
+        fn new(delivery: DeliveryPolicy) -> Self {
+            let config = ChannelConfig {
+                name: "fake".to_string(),
+                kind: ChannelKind::Desktop,
+                enabled: true,
+                min_level: AlertLevel::Info,
+                send_resolved: true,
+                routes: Vec::new(),
+                delivery,
+            };
+            let channel = FakeChannel::default();
+            let queue = ChannelQueue::with_channel(config, Vec::new(), Box::new(channel.clone()));
+            Self { queue, channel, log: DeliveryLog::default(), t0: Instant::now() }
+        }
+
+        fn enqueue(&mut self, secs: u64, id: u64, source: &str, state: AlertState) {
+            let alert = Alert { id, state, ..Alert::test(source, AlertLevel::Warning) };
+            self.queue.enqueue_at(alert, &self.log, self.t0 + Duration::from_secs(secs));
+        }
+
+        fn flush(&mut self, secs: u64) -> Vec<Vec<u64>> {
+            self.queue.flush_at("web1", &self.log, self.t0 + Duration::from_secs(secs));
+            self.channel.take_sent()
+        }
+
+        fn statuses(&self) -> Vec<DeliveryStatus> {
+            self.log.lock().unwrap().iter().map(|r| r.status).collect()
+        }
+    }
+
+    fn policy(dedupe_secs: u64, max_per_minute: u32, max_attempts: u32, digest_after: usize) -> DeliveryPolicy {
+        DeliveryPolicy { dedupe_secs, max_per_minute, max_attempts, digest_after }
+    }
+
+    #[test]
+    fn repeats_within_the_dedupe_window_are_suppressed() {
+        let mut h = Harness::new(policy(300, 0, 1, 10));
+        h.enqueue(0, 1, "CpuUsage", AlertState::Firing);
+        h.enqueue(0, 1, "CpuUsage", AlertState::Resolved);
+        assert_eq!(h.flush(0), vec![vec![1], vec![1]]);
+        // The rule flaps: its second firing and that firing's recovery are dropped
+        h.enqueue(60, 2, "CpuUsage", AlertState::Firing);
+        h.enqueue(70, 2, "CpuUsage", AlertState::Resolved);
+        // Other subjects and other levels are not repeats
+        h.enqueue(80, 3, "DiskUsage /var", AlertState::Firing);
+        let escalated = Alert { id: 4, ..Alert::test("CpuUsage", AlertLevel::Critical) };
+        h.queue.enqueue_at(escalated, &h.log, h.t0 + Duration::from_secs(80));
+        assert_eq!(h.flush(80), vec![vec![3], vec![4]]);
+        // Once the window has passed it is sent again
+        h.enqueue(400, 5, "CpuUsage", AlertState::Firing);
+        assert_eq!(h.flush(400), vec![vec![5]]);
+        assert_eq!(h.statuses().iter().filter(|s| **s == DeliveryStatus::Suppressed).count(), 2);
+    }
+
+    #[test]
+    fn alerts_over_the_rate_limit_go_out_as_one_digest() {
+        let mut h = Harness::new(policy(0, 2, 1, 10));
+        h.enqueue(0, 1, "a", AlertState::Firing);
+        assert_eq!(h.flush(0), vec![vec![1]]);
+        for (id, source) in [(2, "b"), (3, "c"), (4, "d")] {
+            h.enqueue(1, id, source, AlertState::Firing);
+        }
+        // One send left this minute, so the three waiting alerts share it
+        assert_eq!(h.flush(1), vec![vec![2, 3, 4]]);
+        h.enqueue(2, 5, "e", AlertState::Firing);
+        assert!(h.flush(2).is_empty());
+        assert!(h.flush(59).is_empty());
+        assert_eq!(h.flush(61), vec![vec![5]]);
+    }
+
+    #[test]
+    fn many_waiting_alerts_are_digested() {
+        let mut h = Harness::new(policy(0, 0, 1, 3));
+        h.enqueue(0, 1, "a", AlertState::Firing);
+        h.enqueue(0, 2, "b", AlertState::Firing);
+        assert_eq!(h.flush(0), vec![vec![1], vec![2]]);
+        for id in 3..=5 {
+            h.enqueue(1, id, &id.to_string(), AlertState::Firing);
+        }
+        assert_eq!(h.flush(1), vec![vec![3, 4, 5]]);
+    }
+
+    #[test]
+    fn zero_per_minute_means_no_limit() {
+        let mut h = Harness::new(policy(0, 0, 1, 1000));
+        for id in 1..=100 {
+            h.enqueue(0, id, &id.to_string(), AlertState::Firing);
+            assert_eq!(h.flush(0), vec![vec![id]]);
+        }
+    }
+
+    #[test]
+    fn failed_sends_are_retried_with_backoff() {
+        let mut h = Harness::new(policy(0, 0, 5, 10));
+        *h.channel.failures.lock().unwrap() = 2;
+        h.enqueue(0, 1, "a", AlertState::Firing);
+        assert!(h.flush(0).is_empty());
+        assert!(h.log.lock().unwrap().back().unwrap().detail.contains("retrying in 5s"));
+        assert!(h.flush(4).is_empty());
+        assert!(h.flush(5).is_empty());
+        assert!(h.log.lock().unwrap().back().unwrap().detail.contains("retrying in 10s"));
+        assert!(h.flush(14).is_empty());
+        assert_eq!(h.flush(15), vec![vec![1]]);
+        assert_eq!(*h.channel.attempts.lock().unwrap(), 3);
+        assert_eq!(h.statuses(), vec![DeliveryStatus::Retrying, DeliveryStatus::Retrying, DeliveryStatus::Delivered]);
+        assert!(h.queue.is_idle());
+    }
+
+    #[test]
+    fn gives_up_after_max_attempts() {
+        let mut h = Harness::new(policy(0, 0, 2, 10));
+        *h.channel.failures.lock().unwrap() = u32::MAX;
+        h.enqueue(0, 1, "a", AlertState::Firing);
+        h.flush(0);
+        assert!(!h.queue.is_idle());
+        h.flush(5);
+        h.flush(3600);
+        assert_eq!(*h.channel.attempts.lock().unwrap(), 2);
+        assert_eq!(h.statuses(), vec![DeliveryStatus::Retrying, DeliveryStatus::Failed]);
+        assert!(h.queue.is_idle());
+    }
+}
@@ -0,0 +1,38 @@
+use super::NotificationChannel;
+use crate::alert::{Alert, AlertLevel, AlertState};
+
+pub struct SyslogChannel {
+    pub ident: String,
+}
+
+// RFC 3164 priority for facility "user": crit, warning, info, and notice for recoveries
+fn priority(alert: &Alert) -> u8 {
+    let severity = match (&alert.state, &alert.level) {
+        (AlertState::Resolved, _) => 5,
+        (_, AlertLevel::Critical) => 2,
+        (_, AlertLevel::Warning) => 4,
+        (_, AlertLevel::Info) => 6,
+    };
+    8 + severity
+}
+
+impl NotificationChannel for SyslogChannel {
+    #[cfg(unix)]
+    fn send(&mut self, alert: &Alert, _host: &str) -> Result<(), String> {
+        use std::os::unix::net::UnixDatagram;
+        // The local daemon adds the timestamp and host name
+        let line = format!("<{}>{}[{}]: [{:?}] #{} {}", priority(alert), self.ident, std::process::id(), alert.state, alert.id, alert.message);
+        let socket = UnixDatagram::unbound().map_err(|e| e.to_string())?;
+        ["/dev/log", "/var/run/syslog"]
+            .iter()
+            .find_map(|path| socket.send_to(line.as_bytes(), path).ok())
+            .map(|_| ())
+            .ok_or_else(|| "no syslog socket at /dev/log or /var/run/syslog".to_string())
+    }
+
+    #[cfg(not(unix))]
+    fn send(&mut self, alert: &Alert, _host: &str) -> Result<(), String> {
+        let _ = (priority(alert), &self.ident);
+        Err("syslog is only available on Unix".to_string())
+    }
+}
@@ -0,0 +1,47 @@
+// Notification templates: `{{field}}` is replaced by the alert's field.
+// Fields: id, level, state, message, source, started_at, fired_at, ended_at, host.
+// Unknown fields are left as they are so typos show up in the output.
+use crate::alert::Alert;
+
+pub fn render(template: &str, alert: &Alert, host: &str, escape: fn(&str) -> String) -> String {
+    let time = |t: Option<chrono::DateTime<chrono::Utc>>| t.map(|t| t.to_rfc3339()).unwrap_or_default();
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let Some(len) = rest[start + 2..].find("}}") else {
+            rest = &rest[start..];
+            break;
+        };
+        let name = rest[start + 2..start + 2 + len].trim();
+        let value = match name {
+            "id" => Some(alert.id.to_string()),
+            "level" => Some(format!("{:?}", alert.level)),
+            "state" => Some(format!("{:?}", alert.state)),
+            "message" => Some(alert.message.clone()),
+            "source" => Some(alert.source.clone()),
+            "started_at" => Some(alert.started_at.to_rfc3339()),
+            "fired_at" => Some(time(alert.fired_at)),
+            "ended_at" => Some(time(alert.ended_at)),
+            "host" => Some(host.to_string()),
+            _ => None,
+        };
+        match value {
+            Some(value) => out.push_str(&escape(&value)),
+            None => out.push_str(&rest[start..start + 4 + len]),
+        }
+        rest = &rest[start + 4 + len..];
+    }
+    out.push_str(rest);
+    out
+}
+
+pub fn plain(s: &str) -> String {
+    s.to_string()
+}
+
+// Escapes for use inside a JSON string literal
+pub fn json(s: &str) -> String {
+    let quoted = serde_json::to_string(s).unwrap_or_default();
+    quoted[1..quoted.len() - 1].to_string()
+}
@@ -0,0 +1,32 @@
+use super::{template, NotificationChannel};
+use crate::alert::Alert;
+
+use std::time::Duration;
+
+const DEFAULT_BODY: &str = r#"{"id": {{id}}, "level": "{{level}}", "state": "{{state}}", "source": "{{source}}", "message": "{{message}}", "started_at": "{{started_at}}", "host": "{{host}}"}"#;
+
+pub struct WebhookChannel {
+    url: String,
+    body: Option<String>,
+    headers: Vec<(String, String)>,
+    agent: ureq::Agent,
+}
+
+impl WebhookChannel {
+    pub fn new(url: &str, body: Option<String>, headers: Vec<(String, String)>) -> Self {
+        let agent = ureq::AgentBuilder::new().timeout(Duration::from_secs(10)).build();
+        Self { url: url.to_string(), body, headers, agent }
+    }
+}
+
+impl NotificationChannel for WebhookChannel {
+    fn send(&mut self, alert: &Alert, host: &str) -> Result<(), String> {
+        // Values are JSON-escaped since the body is usually a JSON document
+        let body = template::render(self.body.as_deref().unwrap_or(DEFAULT_BODY), alert, host, template::json);
+        let mut request = self.agent.post(&self.url).set("Content-Type", "application/json");
+        for (name, value) in &self.headers {
+            request = request.set(name, value);
+        }
+        request.send_string(&body).map(|_| ()).map_err(|e| e.to_string())
+    }
+}
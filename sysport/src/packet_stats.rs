@@ -1,6 +1,9 @@
 use egui::Color32;
+use pcap::Linktype;
+use serde::{Deserialize, Serialize};
 
-#[derive(Default)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct PacketStats {
     pub tcp_count: usize,
     pub udp_count: usize,
@@ -13,8 +16,12 @@ pub struct PacketStats {
 }
 
 impl PacketStats {
-    pub fn update(&mut self, pkt: &[u8]) {
-        let (proto, _color) = decode_protocol(pkt);
+    // `linktype` is the capture's link-layer header type
+    pub fn update(&mut self, pkt: &[u8], linktype: Linktype) {
+        let (proto, _color) = match linktype {
+            Linktype::ETHERNET => decode_frame(pkt),
+            _ => decode_protocol(pkt),
+        };
         match proto.as_str() {
             "TCP" => {
                 self.tcp_count += 1;
@@ -40,6 +47,15 @@ impl PacketStats {
     }
 }
 
+// Decodes the IPv4 payload of an Ethernet frame
+fn decode_frame(pkt: &[u8]) -> (String, Color32) {
+    match pkt.get(12..14) {
+        Some([0x08, 0x00]) => decode_protocol(&pkt[14..]),
+        Some([0x08, 0x06]) => ("ARP".to_string(), Color32::RED),
+        _ => ("Other".to_string(), Color32::GRAY),
+    }
+}
+
 pub fn decode_protocol(pkt: &[u8]) -> (String, Color32) {
     if pkt.len() > 20 && (pkt[0] >> 4) == 4 {
         // IPv4
@@ -58,4 +74,32 @@ pub fn decode_protocol(pkt: &[u8]) -> (String, Color32) {
     } else {
         ("Other".to_string(), Color32::GRAY)
     }
-} 
\ No newline at end of file
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // IPv4 header from 8.0.1.2, so bytes 12-13 read like an IPv4 EtherType
+    fn ipv4(protocol: u8) -> Vec<u8> {
+        let mut pkt = vec![0x45, 0, 0, 28, 0, 0, 0, 0, 64, protocol, 0, 0, 8, 0, 1, 2, 10, 0, 0, 1];
+        pkt.extend([0; 8]);
+        pkt
+    }
+
+    fn ethernet(ethertype: [u8; 2], payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0xff; 12];
+        frame.extend(ethertype);
+        frame.extend(payload);
+        frame
+    }
+
+    #[test]
+    fn decodes_by_link_type() {
+        let mut stats = PacketStats::default();
+        stats.update(&ethernet([0x08, 0x00], &ipv4(6)), Linktype::ETHERNET);
+        stats.update(&ethernet([0x08, 0x06], &[0; 28]), Linktype::ETHERNET);
+        stats.update(&ipv4(17), Linktype::RAW);
+        assert_eq!((stats.tcp_count, stats.arp_count, stats.udp_count), (1, 1, 1));
+        assert_eq!(stats.udp_bytes, 28);
+    }
+}
@@ -0,0 +1,201 @@
+// Current metrics, packet counters and active alerts in the Prometheus text
+// exposition format, served at /metrics. Only built with the `prometheus` feature.
+use crate::alert::{AlertManager, AlertState};
+use crate::metrics::Metrics;
+use crate::packet_stats::PacketStats;
+use crate::report;
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+
+// What the endpoint reads on every scrape
+#[derive(Clone)]
+pub struct Sources {
+    pub metrics: Arc<Mutex<Metrics>>,
+    pub packets: Arc<Mutex<PacketStats>>,
+    pub alert_manager: Arc<Mutex<AlertManager>>,
+}
+
+impl Sources {
+    pub fn render(&self) -> String {
+        let metrics = self.metrics.lock().unwrap().clone();
+        let packets = self.packets.lock().unwrap().clone();
+        let am = self.alert_manager.lock().unwrap();
+        render(&metrics, &packets, &am)
+    }
+}
+
+struct Exposition {
+    out: String,
+}
+
+impl Exposition {
+    fn family(&mut self, name: &str, kind: &str, help: &str) {
+        let _ = writeln!(self.out, "# HELP {} {}", name, help);
+        let _ = writeln!(self.out, "# TYPE {} {}", name, kind);
+    }
+
+    fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: f64) {
+        self.out.push_str(name);
+        if !labels.is_empty() {
+            let labels: Vec<String> = labels.iter().map(|(k, v)| format!("{}=\"{}\"", k, escape(v))).collect();
+            let _ = write!(self.out, "{{{}}}", labels.join(","));
+        }
+        let _ = writeln!(self.out, " {}", value);
+    }
+
+    // A family with one sample per item, e.g. per disk
+    fn each<T>(&mut self, name: &str, kind: &str, help: &str, items: &[T], labels: impl Fn(&T) -> Vec<(&'static str, String)>, value: impl Fn(&T) -> f64) {
+        if items.is_empty() {
+            return;
+        }
+        self.family(name, kind, help);
+        for item in items {
+            let labels = labels(item);
+            let labels: Vec<(&str, &str)> = labels.iter().map(|(k, v)| (*k, v.as_str())).collect();
+            self.sample(name, &labels, value(item));
+        }
+    }
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+pub fn render(m: &Metrics, packets: &PacketStats, am: &AlertManager) -> String {
+    let mut e = Exposition { out: String::new() };
+
+    e.family("sysport_cpu_usage_overall_percent", "gauge", "CPU usage across all cores.");
+    e.sample("sysport_cpu_usage_overall_percent", &[], m.cpu_total as f64);
+    let cores: Vec<(usize, f32)> = m.cpu_usage.iter().copied().enumerate().collect();
+    e.each("sysport_cpu_usage_percent", "gauge", "CPU usage per core.", &cores, |(i, _)| vec![("core", i.to_string())], |(_, u)| *u as f64);
+
+    // Metrics reports memory in KB
+    e.family("sysport_memory_total_bytes", "gauge", "Total memory.");
+    e.sample("sysport_memory_total_bytes", &[], m.mem_total as f64 * 1024.0);
+    e.family("sysport_memory_used_bytes", "gauge", "Used memory.");
+    e.sample("sysport_memory_used_bytes", &[], m.mem_used as f64 * 1024.0);
+
+    let disk = |d: &crate::metrics::DiskMetrics| vec![("mountpoint", d.mount_point.clone()), ("device", d.name.clone()), ("fstype", d.fs_type.clone())];
+    e.each("sysport_disk_size_bytes", "gauge", "Filesystem size.", &m.disks, disk, |d| d.total as f64);
+    e.each("sysport_disk_available_bytes", "gauge", "Filesystem space available.", &m.disks, disk, |d| d.available as f64);
+    e.each("sysport_disk_inodes", "gauge", "Filesystem inodes.", &m.disks, disk, |d| d.inodes_total as f64);
+    e.each("sysport_disk_inodes_free", "gauge", "Filesystem inodes free.", &m.disks, disk, |d| d.inodes_free as f64);
+    e.each("sysport_disk_read_bytes_per_second", "gauge", "Disk read throughput.", &m.disks, disk, |d| d.read_bytes as f64);
+    e.each("sysport_disk_written_bytes_per_second", "gauge", "Disk write throughput.", &m.disks, disk, |d| d.write_bytes as f64);
+    e.each("sysport_disk_reads_per_second", "gauge", "Disk read operations per second.", &m.disks, disk, |d| d.read_iops);
+    e.each("sysport_disk_writes_per_second", "gauge", "Disk write operations per second.", &m.disks, disk, |d| d.write_iops);
+    e.each("sysport_disk_queue_depth", "gauge", "Average disk requests in flight.", &m.disks, disk, |d| d.queue_depth);
+    e.each("sysport_disk_service_time_seconds", "gauge", "Average time the disk was busy per completed request, excluding queueing.", &m.disks, disk, |d| d.service_time_ms / 1000.0);
+
+    let nic = |n: &crate::metrics::NetInterfaceStats| vec![("interface", n.name.clone())];
+    let nics = &m.net_per_interface;
+    e.each("sysport_network_receive_bytes_per_second", "gauge", "Network receive throughput.", nics, nic, |n| n.rx as f64);
+    e.each("sysport_network_transmit_bytes_per_second", "gauge", "Network transmit throughput.", nics, nic, |n| n.tx as f64);
+    e.each("sysport_network_receive_packets_total", "counter", "Packets received.", nics, nic, |n| n.rx_packets as f64);
+    e.each("sysport_network_transmit_packets_total", "counter", "Packets transmitted.", nics, nic, |n| n.tx_packets as f64);
+    e.each("sysport_network_receive_errors_total", "counter", "Receive errors.", nics, nic, |n| n.rx_errors as f64);
+    e.each("sysport_network_transmit_errors_total", "counter", "Transmit errors.", nics, nic, |n| n.tx_errors as f64);
+    e.each("sysport_network_receive_drop_total", "counter", "Received packets dropped.", nics, nic, |n| n.rx_dropped as f64);
+    e.each("sysport_network_transmit_drop_total", "counter", "Transmitted packets dropped.", nics, nic, |n| n.tx_dropped as f64);
+    let with_mtu: Vec<_> = nics.iter().filter(|n| n.mtu.is_some()).cloned().collect();
+    e.each("sysport_network_mtu_bytes", "gauge", "Interface MTU.", &with_mtu, nic, |n| n.mtu.unwrap_or_default() as f64);
+    let with_state: Vec<_> = nics.iter().filter(|n| n.is_up.is_some()).cloned().collect();
+    e.each("sysport_network_up", "gauge", "1 if the interface is up.", &with_state, nic, |n| if n.is_up == Some(true) { 1.0 } else { 0.0 });
+
+    e.family("sysport_processes", "gauge", "Processes running.");
+    e.sample("sysport_processes", &[], m.processes.len() as f64);
+
+    let protocols = [
+        ("tcp", packets.tcp_count, packets.tcp_bytes),
+        ("udp", packets.udp_count, packets.udp_bytes),
+        ("icmp", packets.icmp_count, packets.icmp_bytes),
+        ("arp", packets.arp_count, packets.arp_bytes),
+    ];
+    e.each("sysport_captured_packets_total", "counter", "Packets seen by packet capture.", &protocols, |(p, _, _)| vec![("protocol", p.to_string())], |(_, n, _)| *n as f64);
+    e.each("sysport_captured_bytes_total", "counter", "Bytes seen by packet capture.", &protocols, |(p, _, _)| vec![("protocol", p.to_string())], |(_, _, b)| *b as f64);
+
+    // Counts for every level and state, so absent alerts read as 0 rather than missing
+    let mut counts: BTreeMap<(String, String), usize> = BTreeMap::new();
+    for level in ["Info", "Warning", "Critical"] {
+        for state in ["Pending", "Firing", "Acknowledged"] {
+            counts.insert((level.to_string(), state.to_string()), 0);
+        }
+    }
+    for alert in &am.active_alerts {
+        *counts.entry((format!("{:?}", alert.level), format!("{:?}", alert.state))).or_default() += 1;
+    }
+    let counts: Vec<_> = counts.into_iter().collect();
+    e.each("sysport_alerts", "gauge", "Active alerts by level and state.", &counts, |((level, state), _)| vec![("level", level.clone()), ("state", state.clone())], |(_, n)| *n as f64);
+    let firing: Vec<_> = am.active_alerts.iter().filter(|a| a.state != AlertState::Pending).collect();
+    e.each(
+        "sysport_alert_firing",
+        "gauge",
+        "1 for each firing or acknowledged alert.",
+        &firing,
+        |a| vec![("source", a.source.clone()), ("level", format!("{:?}", a.level)), ("state", format!("{:?}", a.state))],
+        |_| 1.0,
+    );
+    e.out
+}
+
+pub fn spawn(addr: &str, sources: Sources) {
+    use warp::Filter;
+
+    let addr: std::net::SocketAddr = match addr.parse() {
+        Ok(addr) => addr,
+        Err(e) => {
+            report::error(format!("Prometheus endpoint disabled: invalid address {:?}: {}", addr, e));
+            return;
+        }
+    };
+    // Own runtime, since the GUI has none
+    std::thread::spawn(move || {
+        let rt = match tokio::runtime::Runtime::new() {
+            Ok(rt) => rt,
+            Err(e) => {
+                report::error(format!("Prometheus endpoint disabled: {}", e));
+                return;
+            }
+        };
+        rt.block_on(async move {
+            let route = warp::path("metrics").and(warp::path::end()).and(warp::get()).map(move || {
+                warp::reply::with_header(sources.render(), "Content-Type", "text/plain; version=0.0.4; charset=utf-8")
+            });
+            match warp::serve(route).try_bind_ephemeral(addr) {
+                Ok((addr, server)) => {
+                    println!("Prometheus metrics at http://{}/metrics", addr);
+                    server.await;
+                }
+                Err(e) => report::error(format!("Prometheus endpoint on {} failed: {}", addr, e)),
+            }
+        });
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::DiskMetrics;
+
+    #[test]
+    fn renders_families_and_escapes_labels() {
+        let metrics = Metrics {
+            cpu_total: 12.5,
+            disks: vec![DiskMetrics { name: "sda1".to_string(), mount_point: "/mnt/\"odd\"\\dir\n".to_string(), total: 1000, ..Default::default() }],
+            ..Default::default()
+        };
+        let out = render(&metrics, &PacketStats::default(), &AlertManager::new());
+        let lines: Vec<&str> = out.lines().collect();
+
+        let help = lines.iter().position(|l| *l == "# HELP sysport_cpu_usage_overall_percent CPU usage across all cores.").unwrap();
+        assert_eq!(lines[help + 1], "# TYPE sysport_cpu_usage_overall_percent gauge");
+        assert_eq!(lines[help + 2], "sysport_cpu_usage_overall_percent 12.5");
+        assert!(lines.contains(&"# TYPE sysport_captured_packets_total counter"));
+        assert!(lines.contains(&r#"sysport_disk_size_bytes{mountpoint="/mnt/\"odd\"\\dir\n",device="sda1",fstype=""} 1000"#), "{}", out);
+        assert!(lines.contains(&r#"sysport_alerts{level="Critical",state="Firing"} 0"#));
+        // Families without samples are left out
+        assert!(!out.contains("sysport_cpu_usage_percent"));
+    }
+}
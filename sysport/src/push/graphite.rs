@@ -0,0 +1,100 @@
+// Graphite plaintext protocol over TCP with tags, e.g.
+// sysport.disk.available_bytes;host=web1;mountpoint=/var 1.2e10 1760000000
+use super::{sanitize, Point, Sink};
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::Write;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+pub struct GraphiteSink {
+    addr: String,
+    prefix: String,
+    tags: BTreeMap<String, String>,
+    // Opened on the first send and again after a failed one
+    stream: Option<TcpStream>,
+}
+
+impl GraphiteSink {
+    pub fn new(addr: &str, prefix: String, tags: BTreeMap<String, String>) -> Self {
+        Self { addr: addr.to_string(), prefix, tags, stream: None }
+    }
+
+    fn connect(&self) -> Result<TcpStream, String> {
+        let addr = self.addr.to_socket_addrs().map_err(|e| e.to_string())?.next().ok_or_else(|| format!("{} did not resolve", self.addr))?;
+        let stream = TcpStream::connect_timeout(&addr, Duration::from_secs(10)).map_err(|e| e.to_string())?;
+        stream.set_write_timeout(Some(Duration::from_secs(10))).map_err(|e| e.to_string())?;
+        Ok(stream)
+    }
+}
+
+impl Sink for GraphiteSink {
+    fn send(&mut self, points: &[Point]) -> Result<(), String> {
+        let lines = encode(points, &self.prefix, &self.tags);
+        if lines.is_empty() {
+            return Ok(());
+        }
+        let mut stream = match self.stream.take() {
+            Some(stream) => stream,
+            None => self.connect()?,
+        };
+        stream.write_all(lines.as_bytes()).and_then(|_| stream.flush()).map_err(|e| e.to_string())?;
+        self.stream = Some(stream);
+        Ok(())
+    }
+}
+
+pub fn encode(points: &[Point], prefix: &str, tags: &BTreeMap<String, String>) -> String {
+    let mut out = String::new();
+    for p in points {
+        let mut suffix = String::new();
+        for (k, v) in p.all_tags(tags) {
+            // Tag names may not contain ;!^= and values may not contain ; or start with ~
+            let v = sanitize(v, &[';']);
+            let v = if v.starts_with('~') { v.replacen('~', "_", 1) } else { v };
+            let _ = write!(suffix, ";{}={}", sanitize(k, &[';', '!', '^', '=']), v);
+        }
+        for (field, value) in p.fields.iter().filter(|(_, v)| v.is_finite()) {
+            let path: Vec<String> = [prefix, p.measurement, field].iter().filter(|s| !s.is_empty()).map(|s| sanitize(s, &['.', ';'])).collect();
+            let _ = writeln!(out, "{}{} {} {}", path.join("."), suffix, value, p.timestamp.timestamp());
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use std::io::Read;
+    use std::net::TcpListener;
+
+    #[test]
+    fn sends_over_one_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let server = std::thread::spawn(move || {
+            let mut received = String::new();
+            listener.accept().unwrap().0.read_to_string(&mut received).unwrap();
+            received
+        });
+        let point = |core: &str, usage| Point {
+            measurement: "cpu",
+            tags: vec![("core", core.to_string())],
+            fields: vec![("usage_percent", usage)],
+            timestamp: Utc.timestamp_opt(1_760_000_000, 0).unwrap(),
+        };
+        let tags = BTreeMap::from([("host".to_string(), "web;1".to_string())]);
+
+        let mut sink = GraphiteSink::new(&addr, "sysport".to_string(), tags);
+        sink.send(&[point("0", 12.5)]).unwrap();
+        sink.send(&[point("~1", 50.0), point("2", f64::INFINITY)]).unwrap();
+        drop(sink);
+
+        assert_eq!(
+            server.join().unwrap(),
+            "sysport.cpu.usage_percent;host=web_1;core=0 12.5 1760000000\nsysport.cpu.usage_percent;host=web_1;core=_1 50 1760000000\n"
+        );
+    }
+}
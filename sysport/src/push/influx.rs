@@ -0,0 +1,161 @@
+// InfluxDB line protocol over HTTP, e.g.
+// sysport_disk,host=web1,mountpoint=/var available_bytes=1.2e10 1760000000000000000
+use super::{Point, Sink};
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::time::Duration;
+
+pub struct InfluxSink {
+    url: String,
+    token: Option<String>,
+    prefix: String,
+    tags: BTreeMap<String, String>,
+    agent: ureq::Agent,
+}
+
+impl InfluxSink {
+    pub fn new(url: &str, token: Option<String>, prefix: String, tags: BTreeMap<String, String>) -> Self {
+        let agent = ureq::AgentBuilder::new().timeout(Duration::from_secs(10)).build();
+        Self { url: url.to_string(), token, prefix, tags, agent }
+    }
+}
+
+impl Sink for InfluxSink {
+    fn send(&mut self, points: &[Point]) -> Result<(), String> {
+        let body = encode(points, &self.prefix, &self.tags);
+        if body.is_empty() {
+            return Ok(());
+        }
+        let mut request = self.agent.post(&self.url).set("Content-Type", "text/plain; charset=utf-8");
+        if let Some(token) = &self.token {
+            request = request.set("Authorization", &format!("Token {}", token));
+        }
+        request.send_string(&body).map(|_| ()).map_err(|e| e.to_string())
+    }
+}
+
+pub fn encode(points: &[Point], prefix: &str, tags: &BTreeMap<String, String>) -> String {
+    let mut out = String::new();
+    for p in points {
+        // Influx rejects NaN and infinite values
+        let fields: Vec<String> = p.fields.iter().filter(|(_, v)| v.is_finite()).map(|(k, v)| format!("{}={}", escape(k, false), v)).collect();
+        if fields.is_empty() {
+            continue;
+        }
+        let name = if prefix.is_empty() { p.measurement.to_string() } else { format!("{}_{}", prefix, p.measurement) };
+        out.push_str(&escape(&name, true));
+        // Sorted by key, which Influx recommends for write performance
+        let mut point_tags: Vec<(&str, &str)> = p.all_tags(tags).collect();
+        point_tags.sort();
+        for (k, v) in point_tags {
+            let _ = write!(out, ",{}={}", escape(k, false), escape(v, false));
+        }
+        let _ = writeln!(out, " {} {}", fields.join(","), p.timestamp.timestamp_nanos_opt().unwrap_or_default());
+    }
+    out
+}
+
+// Measurements escape commas and spaces; tag keys, values and field keys also `=`.
+// Line breaks would end the line, so they become spaces.
+fn escape(s: &str, measurement: bool) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\n' | '\r' => out.push_str("\\ "),
+            ',' | ' ' | '\\' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '=' if !measurement => out.push_str("\\="),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread::JoinHandle;
+
+    // Answers one HTTP request with `status` and returns the request line, headers and body
+    fn serve_once(status: &'static str) -> (String, JoinHandle<(String, Vec<String>, String)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let server = std::thread::spawn(move || {
+            let mut stream = listener.accept().unwrap().0;
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut headers = Vec::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim_end().is_empty() {
+                    break;
+                }
+                headers.push(line.trim_end().to_string());
+            }
+            let length = headers
+                .iter()
+                .find_map(|h| h.to_ascii_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse::<usize>().unwrap()))
+                .unwrap_or(0);
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            write!(stream, "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status).unwrap();
+            (request_line.trim_end().to_string(), headers, String::from_utf8(body).unwrap())
+        });
+        (addr, server)
+    }
+
+    fn cpu_point() -> Point {
+        Point {
+            measurement: "cpu",
+            tags: vec![("core", "0".to_string())],
+            fields: vec![("usage_percent", 12.5)],
+            timestamp: Utc.timestamp_opt(1_760_000_000, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn posts_line_protocol_to_the_write_url() {
+        let (addr, server) = serve_once("204 No Content");
+        let tags = BTreeMap::from([("host".to_string(), "web1".to_string())]);
+        let mut sink = InfluxSink::new(&format!("http://{}/api/v2/write?org=ops&bucket=metrics&precision=ns", addr), Some("secret".to_string()), "sysport".to_string(), tags);
+        sink.send(&[cpu_point()]).unwrap();
+
+        let (request_line, headers, body) = server.join().unwrap();
+        assert_eq!(request_line, "POST /api/v2/write?org=ops&bucket=metrics&precision=ns HTTP/1.1");
+        assert!(headers.iter().any(|h| h == "Authorization: Token secret"), "{:?}", headers);
+        assert_eq!(body, "sysport_cpu,core=0,host=web1 usage_percent=12.5 1760000000000000000\n");
+    }
+
+    #[test]
+    fn rejected_writes_are_errors() {
+        let (addr, server) = serve_once("401 Unauthorized");
+        let mut sink = InfluxSink::new(&format!("http://{}/write?db=sysport", addr), None, String::new(), BTreeMap::new());
+        assert!(sink.send(&[cpu_point()]).is_err());
+        let (request_line, headers, _) = server.join().unwrap();
+        assert_eq!(request_line, "POST /write?db=sysport HTTP/1.1");
+        assert!(!headers.iter().any(|h| h.starts_with("Authorization")));
+    }
+
+    #[test]
+    fn escapes_names_tags_and_fields() {
+        let point = Point {
+            measurement: "disk",
+            tags: vec![("device", "sda,1".to_string()), ("mountpoint", "/var\nlib".to_string())],
+            fields: vec![("free=bytes", 5.0), ("skipped", f64::NAN)],
+            timestamp: Utc.timestamp_opt(1_760_000_000, 0).unwrap(),
+        };
+        let tags = BTreeMap::from([("host".to_string(), "web 1".to_string()), ("rack=a".to_string(), "x=y".to_string())]);
+        assert_eq!(
+            encode(&[point], "my sys", &tags),
+            "my\\ sys_disk,device=sda\\,1,host=web\\ 1,mountpoint=/var\\ lib,rack\\=a=x\\=y free\\=bytes=5 1760000000000000000\n"
+        );
+    }
+}
@@ -0,0 +1,243 @@
+// Pushes collector samples to time-series databases: InfluxDB line protocol over
+// HTTP, Graphite plaintext over TCP and StatsD over UDP. Each target runs on its
+// own thread with its own queue, so a slow or unreachable one never holds up
+// the collector.
+mod graphite;
+mod influx;
+mod statsd;
+
+use crate::collector::Collector;
+use crate::metrics::Metrics;
+use crate::report;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Batches kept while a target is unreachable; older points are dropped first
+const MAX_QUEUED_BATCHES: usize = 10;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum PushKind {
+    // Full write URL, e.g. http://localhost:8086/api/v2/write?org=ops&bucket=sysport
+    Influx {
+        url: String,
+        // Sent as "Authorization: Token <token>"
+        #[serde(default)]
+        token: Option<String>,
+    },
+    // host:port of the plaintext listener, usually 2003
+    Graphite { addr: String },
+    // host:port, usually 8125; tags use the DogStatsD `|#tag:value` extension
+    Statsd { addr: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PushConfig {
+    pub name: String,
+    pub kind: PushKind,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    // Prepended to every metric name
+    #[serde(default = "default_prefix")]
+    pub prefix: String,
+    // Added to every point, e.g. "host": "web1"
+    #[serde(default)]
+    pub tags: BTreeMap<String, String>,
+    // At most one sample per interval is pushed; 0 pushes every sample
+    #[serde(default = "default_secs")]
+    pub interval_secs: u64,
+    // Queued points are sent this often, or sooner once a batch is full
+    #[serde(default = "default_secs")]
+    pub flush_secs: u64,
+    // Points per request or write
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_prefix() -> String {
+    "sysport".to_string()
+}
+
+fn default_secs() -> u64 {
+    10
+}
+
+fn default_batch_size() -> usize {
+    1000
+}
+
+// One measurement of one subject, e.g. the counters of one disk
+#[derive(Debug, Clone)]
+pub struct Point {
+    pub measurement: &'static str,
+    pub tags: Vec<(&'static str, String)>,
+    pub fields: Vec<(&'static str, f64)>,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl Point {
+    // The point's own tags after the configured ones, without empty values
+    fn all_tags<'a>(&'a self, config_tags: &'a BTreeMap<String, String>) -> impl Iterator<Item = (&'a str, &'a str)> {
+        config_tags
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .chain(self.tags.iter().map(|(k, v)| (*k, v.as_str())))
+            .filter(|(_, v)| !v.is_empty())
+    }
+}
+
+// Where points are written. Sending may block; sinks run on their own thread.
+trait Sink: Send {
+    fn send(&mut self, points: &[Point]) -> Result<(), String>;
+}
+
+impl PushConfig {
+    fn build(&self) -> Box<dyn Sink> {
+        let (prefix, tags) = (self.prefix.clone(), self.tags.clone());
+        match &self.kind {
+            PushKind::Influx { url, token } => Box::new(influx::InfluxSink::new(url, token.clone(), prefix, tags)),
+            PushKind::Graphite { addr } => Box::new(graphite::GraphiteSink::new(addr, prefix, tags)),
+            PushKind::Statsd { addr } => Box::new(statsd::StatsdSink::new(addr, prefix, tags)),
+        }
+    }
+}
+
+// Starts an exporter for every enabled target, fed by the collector's samples
+pub fn start(configs: &[PushConfig], collector: &Collector) {
+    for config in configs.iter().filter(|c| c.enabled) {
+        let samples = collector.subscribe();
+        let config = config.clone();
+        thread::spawn(move || run(config, samples));
+    }
+}
+
+// Runs until the collector stops, then sends what is left
+fn run(config: PushConfig, samples: Receiver<Metrics>) {
+    let mut sink = config.build();
+    let batch_size = config.batch_size.max(1);
+    let flush_every = Duration::from_secs(config.flush_secs.max(1));
+    let interval = sample_interval(config.interval_secs);
+    let mut queue: VecDeque<Point> = VecDeque::new();
+    let mut last_sample: Option<DateTime<Utc>> = None;
+    let mut next_flush = Instant::now() + flush_every;
+    loop {
+        let open = match samples.recv_timeout(next_flush.saturating_duration_since(Instant::now())) {
+            Ok(m) => {
+                if last_sample.is_none_or(|t| m.timestamp - t >= interval) {
+                    last_sample = Some(m.timestamp);
+                    queue.extend(points(&m));
+                    let excess = queue.len().saturating_sub(batch_size * MAX_QUEUED_BATCHES);
+                    queue.drain(..excess);
+                }
+                true
+            }
+            Err(RecvTimeoutError::Timeout) => true,
+            Err(RecvTimeoutError::Disconnected) => false,
+        };
+        if !open || queue.len() >= batch_size || Instant::now() >= next_flush {
+            flush(&config.name, sink.as_mut(), &mut queue, batch_size);
+            next_flush = Instant::now() + flush_every;
+        }
+        if !open {
+            break;
+        }
+    }
+}
+
+// Intervals too long for chrono are as good as never pushing again
+fn sample_interval(secs: u64) -> chrono::Duration {
+    i64::try_from(secs).ok().and_then(chrono::Duration::try_seconds).unwrap_or(chrono::Duration::MAX)
+}
+
+// Sends full batches in order and stops at the first failure; the rest is
+// retried at the next flush
+fn flush(name: &str, sink: &mut dyn Sink, queue: &mut VecDeque<Point>, batch_size: usize) {
+    while !queue.is_empty() {
+        let n = queue.len().min(batch_size);
+        let batch: Vec<Point> = queue.iter().take(n).cloned().collect();
+        match sink.send(&batch) {
+            Ok(()) => {
+                queue.drain(..n);
+            }
+            Err(e) => {
+                report::error(format!("Push to {} failed, {} points queued: {}", name, queue.len(), e));
+                return;
+            }
+        }
+    }
+}
+
+// Everything in a sample except the process list, which would add a series per process
+pub fn points(m: &Metrics) -> Vec<Point> {
+    let at = m.timestamp;
+    let point = |measurement, tags, fields| Point { measurement, tags, fields, timestamp: at };
+    let mut points = vec![
+        point("cpu", Vec::new(), vec![("usage_percent", m.cpu_total as f64)]),
+        // Metrics reports memory in KB
+        point("memory", Vec::new(), vec![("total_bytes", m.mem_total as f64 * 1024.0), ("used_bytes", m.mem_used as f64 * 1024.0)]),
+        point("system", Vec::new(), vec![("processes", m.processes.len() as f64)]),
+    ];
+    for (core, usage) in m.cpu_usage.iter().enumerate() {
+        points.push(point("cpu", vec![("core", core.to_string())], vec![("usage_percent", *usage as f64)]));
+    }
+    for d in &m.disks {
+        points.push(point(
+            "disk",
+            vec![("mountpoint", d.mount_point.clone()), ("device", d.name.clone()), ("fstype", d.fs_type.clone())],
+            vec![
+                ("total_bytes", d.total as f64),
+                ("available_bytes", d.available as f64),
+                ("inodes", d.inodes_total as f64),
+                ("inodes_free", d.inodes_free as f64),
+                ("read_bytes_per_sec", d.read_bytes as f64),
+                ("write_bytes_per_sec", d.write_bytes as f64),
+                ("reads_per_sec", d.read_iops),
+                ("writes_per_sec", d.write_iops),
+                ("queue_depth", d.queue_depth),
+                ("service_time_ms", d.service_time_ms),
+            ],
+        ));
+    }
+    for n in &m.net_per_interface {
+        points.push(point(
+            "net",
+            vec![("interface", n.name.clone())],
+            vec![
+                ("rx_bytes_per_sec", n.rx as f64),
+                ("tx_bytes_per_sec", n.tx as f64),
+                ("rx_packets", n.rx_packets as f64),
+                ("tx_packets", n.tx_packets as f64),
+                ("rx_errors", n.rx_errors as f64),
+                ("tx_errors", n.tx_errors as f64),
+                ("rx_dropped", n.rx_dropped as f64),
+                ("tx_dropped", n.tx_dropped as f64),
+            ],
+        ));
+    }
+    points
+}
+
+// Replaces characters a wire format reserves, so odd names cannot break a line
+fn sanitize(s: &str, reserved: &[char]) -> String {
+    s.chars().map(|c| if c.is_whitespace() || reserved.contains(&c) { '_' } else { c }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn huge_intervals_are_clamped() {
+        assert_eq!(sample_interval(10), chrono::Duration::seconds(10));
+        assert_eq!(sample_interval(u64::MAX), chrono::Duration::MAX);
+        assert_eq!(sample_interval(i64::MAX as u64), chrono::Duration::MAX);
+    }
+}
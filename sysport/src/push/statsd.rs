@@ -0,0 +1,115 @@
+// StatsD gauges over UDP with DogStatsD tags, e.g.
+// sysport.disk.available_bytes:12000000000|g|#host:web1,mountpoint:/var
+use super::{sanitize, Point, Sink};
+
+use std::collections::BTreeMap;
+use std::net::UdpSocket;
+
+// Keeps datagrams within a typical Ethernet MTU
+const MAX_DATAGRAM: usize = 1432;
+
+pub struct StatsdSink {
+    addr: String,
+    prefix: String,
+    tags: BTreeMap<String, String>,
+    socket: Option<UdpSocket>,
+}
+
+impl StatsdSink {
+    pub fn new(addr: &str, prefix: String, tags: BTreeMap<String, String>) -> Self {
+        Self { addr: addr.to_string(), prefix, tags, socket: None }
+    }
+
+    fn connect(&self) -> Result<UdpSocket, String> {
+        let socket = UdpSocket::bind("0.0.0.0:0").or_else(|_| UdpSocket::bind("[::]:0")).map_err(|e| e.to_string())?;
+        socket.connect(&self.addr).map_err(|e| e.to_string())?;
+        Ok(socket)
+    }
+}
+
+impl Sink for StatsdSink {
+    fn send(&mut self, points: &[Point]) -> Result<(), String> {
+        let socket = match self.socket.take() {
+            Some(socket) => socket,
+            None => self.connect()?,
+        };
+        for datagram in pack(&encode(points, &self.prefix, &self.tags)) {
+            socket.send(datagram.as_bytes()).map_err(|e| e.to_string())?;
+        }
+        self.socket = Some(socket);
+        Ok(())
+    }
+}
+
+pub fn encode(points: &[Point], prefix: &str, tags: &BTreeMap<String, String>) -> Vec<String> {
+    let mut lines = Vec::new();
+    for p in points {
+        let tags: Vec<String> = p.all_tags(tags).map(|(k, v)| format!("{}:{}", sanitize(k, &[':', '|', ',', '#']), sanitize(v, &['|', ',', '#']))).collect();
+        // Negative values would be read as a change to the gauge, not a new value
+        for (field, value) in p.fields.iter().filter(|(_, v)| v.is_finite() && *v >= 0.0) {
+            let name: Vec<String> = [prefix, p.measurement, field].iter().filter(|s| !s.is_empty()).map(|s| sanitize(s, &[':', '|', '@', '#', ','])).collect();
+            let mut line = format!("{}:{}|g", name.join("."), value);
+            if !tags.is_empty() {
+                line.push_str("|#");
+                line.push_str(&tags.join(","));
+            }
+            lines.push(line);
+        }
+    }
+    lines
+}
+
+// Joins lines with newlines into as few datagrams as fit
+fn pack(lines: &[String]) -> Vec<String> {
+    let mut datagrams: Vec<String> = Vec::new();
+    for line in lines {
+        match datagrams.last_mut() {
+            Some(last) if last.len() + 1 + line.len() <= MAX_DATAGRAM => {
+                last.push('\n');
+                last.push_str(line);
+            }
+            _ => datagrams.push(line.clone()),
+        }
+    }
+    datagrams
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use std::time::Duration;
+
+    #[test]
+    fn sends_gauges_in_one_datagram() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        server.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let point = Point {
+            measurement: "disk",
+            tags: vec![("mountpoint", "/var".to_string()), ("fstype", String::new())],
+            fields: vec![("available_bytes", 12e9), ("queue_depth", -1.0)],
+            timestamp: Utc.timestamp_opt(1_760_000_000, 0).unwrap(),
+        };
+        let cpu = Point { measurement: "cpu", tags: Vec::new(), fields: vec![("usage_percent", 12.5)], timestamp: point.timestamp };
+        let tags = BTreeMap::from([("host".to_string(), "web|1".to_string())]);
+
+        let mut sink = StatsdSink::new(&server.local_addr().unwrap().to_string(), "sysport".to_string(), tags);
+        sink.send(&[point, cpu]).unwrap();
+
+        let mut buf = [0; MAX_DATAGRAM];
+        let n = server.recv(&mut buf).unwrap();
+        assert_eq!(
+            std::str::from_utf8(&buf[..n]).unwrap(),
+            "sysport.disk.available_bytes:12000000000|g|#host:web_1,mountpoint:/var\nsysport.cpu.usage_percent:12.5|g|#host:web_1"
+        );
+    }
+
+    #[test]
+    fn packs_lines_into_small_datagrams() {
+        let lines: Vec<String> = (0..100).map(|i| format!("sysport.cpu.usage_percent:{}|g|#core:{}", i, i)).collect();
+        let datagrams = pack(&lines);
+        assert!(datagrams.len() > 1);
+        assert!(datagrams.iter().all(|d| d.len() <= MAX_DATAGRAM));
+        assert_eq!(datagrams.join("\n"), lines.join("\n"));
+    }
+}
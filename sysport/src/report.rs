@@ -0,0 +1,28 @@
+// Errors from background threads. They go to stderr unless a frontend that
+// owns the terminal has taken them over, as the TUI does for its status line.
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+
+static SINK: Mutex<Option<Sender<String>>> = Mutex::new(None);
+
+pub fn error(message: String) {
+    let message = match SINK.lock().unwrap().as_ref() {
+        Some(sink) => match sink.send(message) {
+            Ok(()) => return,
+            Err(unsent) => unsent.0,
+        },
+        None => message,
+    };
+    eprintln!("{}", message);
+}
+
+// Sends errors to the returned receiver until `release`
+pub fn take_over() -> Receiver<String> {
+    let (tx, rx) = mpsc::channel();
+    *SINK.lock().unwrap() = Some(tx);
+    rx
+}
+
+pub fn release() {
+    SINK.lock().unwrap().take();
+}
@@ -0,0 +1,341 @@
+use crate::metrics::Metrics;
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+// Segment files are append-only JSON lines behind a one-line header.
+// Raw segments are named `<start-ms>.seg`; once compacted they are rewritten
+// as `<start-ms>.compact.seg` with one averaged sample per compaction interval.
+const SEGMENT_HEADER: &str = "SYSPORT-SEGMENT v1";
+const RAW_SUFFIX: &str = ".seg";
+const COMPACT_SUFFIX: &str = ".compact.seg";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StorageConfig {
+    pub dir: PathBuf,
+    pub write_interval_secs: u64,
+    pub segment_duration_secs: u64,
+    pub max_segment_bytes: u64,
+    pub retention_secs: u64,
+    pub compact_after_secs: u64,
+    pub compact_interval_secs: u64,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            dir: default_data_dir().join("history"),
+            write_interval_secs: 5,
+            segment_duration_secs: 3600,
+            max_segment_bytes: 16 * 1024 * 1024,
+            retention_secs: 7 * 24 * 3600,
+            compact_after_secs: 24 * 3600,
+            compact_interval_secs: 60,
+        }
+    }
+}
+
+pub fn default_data_dir() -> PathBuf {
+    dirs::data_local_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("sysport")
+}
+
+struct ActiveSegment {
+    start: DateTime<Utc>,
+    writer: BufWriter<File>,
+    bytes: u64,
+}
+
+struct SegmentInfo {
+    path: PathBuf,
+    start: DateTime<Utc>,
+    compacted: bool,
+}
+
+pub struct MetricsStore {
+    pub config: StorageConfig,
+    active: Option<ActiveSegment>,
+    last_write: Option<DateTime<Utc>>,
+}
+
+impl MetricsStore {
+    pub fn open(config: StorageConfig) -> std::io::Result<Self> {
+        fs::create_dir_all(&config.dir)?;
+        let mut store = Self { config, active: None, last_write: None };
+        store.maintain()?;
+        Ok(store)
+    }
+
+    // Appends a sample, skipping it if it arrives sooner than the write interval
+    pub fn append(&mut self, m: &Metrics) -> std::io::Result<()> {
+        if let Some(last) = self.last_write {
+            if (m.timestamp - last).num_seconds() < self.config.write_interval_secs as i64 {
+                return Ok(());
+            }
+        }
+        let needs_roll = match &self.active {
+            Some(seg) => {
+                (m.timestamp - seg.start).num_seconds() >= self.config.segment_duration_secs as i64
+                    || seg.bytes >= self.config.max_segment_bytes
+            }
+            None => true,
+        };
+        if needs_roll {
+            self.roll(m.timestamp)?;
+        }
+        let mut record = m.clone();
+        record.processes.clear();
+        let mut line = serde_json::to_string(&record)?;
+        line.push('\n');
+        if let Some(seg) = &mut self.active {
+            seg.writer.write_all(line.as_bytes())?;
+            seg.writer.flush()?;
+            seg.bytes += line.len() as u64;
+        }
+        self.last_write = Some(m.timestamp);
+        Ok(())
+    }
+
+    fn roll(&mut self, start: DateTime<Utc>) -> std::io::Result<()> {
+        self.active = None;
+        let path = self.config.dir.join(format!("{}{}", start.timestamp_millis(), RAW_SUFFIX));
+        let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let mut bytes = file.metadata()?.len();
+        if bytes == 0 {
+            writeln!(file, "{}", SEGMENT_HEADER)?;
+            bytes = SEGMENT_HEADER.len() as u64 + 1;
+        }
+        self.active = Some(ActiveSegment { start, writer: BufWriter::new(file), bytes });
+        self.maintain()
+    }
+
+    // Applies retention and compaction to every closed segment
+    pub fn maintain(&mut self) -> std::io::Result<()> {
+        let now = Utc::now();
+        let active_start = self.active.as_ref().map(|a| a.start);
+        for seg in list_segments(&self.config.dir)? {
+            if Some(seg.start) == active_start {
+                continue;
+            }
+            let age = (now - seg.start).num_seconds() - self.config.segment_duration_secs as i64;
+            if age > self.config.retention_secs as i64 {
+                fs::remove_file(&seg.path)?;
+            } else if !seg.compacted && age > self.config.compact_after_secs as i64 {
+                self.compact(&seg)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn compact(&self, seg: &SegmentInfo) -> std::io::Result<()> {
+        let samples = read_segment(&seg.path)?;
+        let interval = self.config.compact_interval_secs.max(1) as i64;
+        let mut compacted: Vec<Metrics> = Vec::new();
+        let mut bucket: Vec<Metrics> = Vec::new();
+        for m in samples {
+            if let Some(first) = bucket.first() {
+                if m.timestamp.timestamp() / interval != first.timestamp.timestamp() / interval {
+                    compacted.push(average(&bucket));
+                    bucket.clear();
+                }
+            }
+            bucket.push(m);
+        }
+        if !bucket.is_empty() {
+            compacted.push(average(&bucket));
+        }
+        let target = self.config.dir.join(format!("{}{}", seg.start.timestamp_millis(), COMPACT_SUFFIX));
+        let tmp = target.with_extension("tmp");
+        {
+            let mut writer = BufWriter::new(File::create(&tmp)?);
+            writeln!(writer, "{}", SEGMENT_HEADER)?;
+            for m in &compacted {
+                writeln!(writer, "{}", serde_json::to_string(m)?)?;
+            }
+            writer.flush()?;
+        }
+        fs::rename(&tmp, &target)?;
+        fs::remove_file(&seg.path)
+    }
+}
+
+// Reads every retained sample newer than `window`, oldest first
+pub fn load_since(dir: &Path, window: Duration) -> std::io::Result<Vec<Metrics>> {
+    let cutoff = Utc::now() - window;
+    let mut samples = Vec::new();
+    for seg in list_segments(dir)? {
+        samples.extend(read_segment(&seg.path)?.into_iter().filter(|m| m.timestamp >= cutoff));
+    }
+    samples.sort_by_key(|m| m.timestamp);
+    Ok(samples)
+}
+
+fn list_segments(dir: &Path) -> std::io::Result<Vec<SegmentInfo>> {
+    let mut segments = Vec::new();
+    if !dir.exists() {
+        return Ok(segments);
+    }
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        let (stem, compacted) = if let Some(stem) = name.strip_suffix(COMPACT_SUFFIX) {
+            (stem.to_string(), true)
+        } else if let Some(stem) = name.strip_suffix(RAW_SUFFIX) {
+            (stem.to_string(), false)
+        } else {
+            continue;
+        };
+        if let Some(start) = stem.parse::<i64>().ok().and_then(|ms| Utc.timestamp_millis_opt(ms).single()) {
+            segments.push(SegmentInfo { path, start, compacted });
+        }
+    }
+    segments.sort_by_key(|s| s.start);
+    Ok(segments)
+}
+
+// Lines that fail to parse (e.g. a write torn by a crash) are skipped
+fn read_segment(path: &Path) -> std::io::Result<Vec<Metrics>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut lines = reader.lines();
+    match lines.next() {
+        Some(Ok(header)) if header == SEGMENT_HEADER => {}
+        _ => return Ok(Vec::new()),
+    }
+    Ok(lines
+        .map_while(Result::ok)
+        .filter_map(|line| serde_json::from_str::<Metrics>(&line).ok())
+        .collect())
+}
+
+// Averages the numeric fields of a bucket, keeping the last sample's structure
+fn average(bucket: &[Metrics]) -> Metrics {
+    let n = bucket.len() as f64;
+    let mut m = bucket[bucket.len() - 1].clone();
+    m.timestamp = bucket[0].timestamp;
+    m.cpu_total = (bucket.iter().map(|s| s.cpu_total as f64).sum::<f64>() / n) as f32;
+    for (i, core) in m.cpu_usage.iter_mut().enumerate() {
+        *core = (bucket.iter().map(|s| s.cpu_usage.get(i).copied().unwrap_or(0.0) as f64).sum::<f64>() / n) as f32;
+    }
+    m.mem_used = (bucket.iter().map(|s| s.mem_used as f64).sum::<f64>() / n) as u64;
+    m.net_rx = (bucket.iter().map(|s| s.net_rx as f64).sum::<f64>() / n) as u64;
+    m.net_tx = (bucket.iter().map(|s| s.net_tx as f64).sum::<f64>() / n) as u64;
+    m
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::{MetricsHistory, Resolution};
+    use crate::metrics::ProcessMetrics;
+    use crate::testutil::temp_dir;
+
+    fn config(name: &str) -> StorageConfig {
+        StorageConfig {
+            dir: temp_dir(name),
+            write_interval_secs: 5,
+            segment_duration_secs: 60,
+            max_segment_bytes: 1 << 20,
+            retention_secs: 24 * 3600,
+            compact_after_secs: 3600,
+            compact_interval_secs: 10,
+        }
+    }
+
+    // On a minute boundary, `ago` before now
+    fn minute_ago(ago: Duration) -> DateTime<Utc> {
+        let at = (Utc::now() - ago).timestamp();
+        Utc.timestamp_opt(at - at.rem_euclid(60), 0).unwrap()
+    }
+
+    fn sample(at: DateTime<Utc>, cpu: f32) -> Metrics {
+        Metrics { timestamp: at, cpu_total: cpu, processes: vec![ProcessMetrics::default()], ..Default::default() }
+    }
+
+    fn segment_names(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir).unwrap().map(|e| e.unwrap().file_name().to_string_lossy().to_string()).collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn samples_are_written_and_reloaded() {
+        let config = config("storage-reload");
+        let base = minute_ago(Duration::minutes(10));
+        let mut store = MetricsStore::open(config.clone()).unwrap();
+        // Samples closer together than the write interval are skipped
+        for i in 0..20 {
+            store.append(&sample(base + Duration::seconds(i), i as f32)).unwrap();
+        }
+        drop(store);
+        // A write torn by a crash
+        let segment = config.dir.join(&segment_names(&config.dir)[0]);
+        OpenOptions::new().append(true).open(&segment).unwrap().write_all(b"{\"cpu_total\": 9").unwrap();
+
+        let samples = load_since(&config.dir, Duration::hours(1)).unwrap();
+        let cpu: Vec<f32> = samples.iter().map(|m| m.cpu_total).collect();
+        assert_eq!(cpu, [0.0, 5.0, 10.0, 15.0]);
+        assert!(samples.iter().all(|m| m.processes.is_empty()));
+        assert!(load_since(&config.dir, Duration::minutes(5)).unwrap().is_empty());
+
+        // The history is rebuilt from the reloaded samples on startup
+        let mut history = MetricsHistory::new();
+        for m in samples {
+            history.push(m);
+        }
+        assert_eq!(history.points(Resolution::TenSeconds).len(), 2);
+    }
+
+    #[test]
+    fn segments_roll_over_by_duration_and_size() {
+        let config = config("storage-roll");
+        let base = minute_ago(Duration::minutes(10));
+        let mut store = MetricsStore::open(config.clone()).unwrap();
+        for i in 0..30 {
+            store.append(&sample(base + Duration::seconds(i * 5), 1.0)).unwrap();
+        }
+        let starts: Vec<String> = [0, 60, 120].iter().map(|s| format!("{}.seg", (base + Duration::seconds(*s)).timestamp_millis())).collect();
+        assert_eq!(segment_names(&config.dir), starts);
+
+        let config = StorageConfig { max_segment_bytes: 1, ..self::config("storage-roll-size") };
+        let mut store = MetricsStore::open(config.clone()).unwrap();
+        for i in 0..3 {
+            store.append(&sample(base + Duration::seconds(i * 5), 1.0)).unwrap();
+        }
+        assert_eq!(segment_names(&config.dir).len(), 3);
+        assert_eq!(load_since(&config.dir, Duration::hours(1)).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn old_segments_are_compacted_then_removed() {
+        let config = config("storage-compact");
+        let expired = minute_ago(Duration::days(2));
+        let old = minute_ago(Duration::hours(3));
+        let mut store = MetricsStore::open(config.clone()).unwrap();
+        store.append(&sample(expired, 1.0)).unwrap();
+        for i in 0..=12 {
+            store.append(&sample(old + Duration::seconds(i * 5), i as f32)).unwrap();
+        }
+        let now = Utc::now();
+        store.append(&sample(now, 1.0)).unwrap();
+
+        let names = segment_names(&config.dir);
+        assert_eq!(
+            names,
+            [
+                format!("{}.compact.seg", old.timestamp_millis()),
+                format!("{}.compact.seg", (old + Duration::seconds(60)).timestamp_millis()),
+                format!("{}.seg", now.timestamp_millis()),
+            ]
+        );
+        // One averaged sample per 10 s compaction interval
+        let compacted = read_segment(&config.dir.join(&names[0])).unwrap();
+        let cpu: Vec<f32> = compacted.iter().map(|m| m.cpu_total).collect();
+        assert_eq!(cpu, [0.5, 2.5, 4.5, 6.5, 8.5, 10.5]);
+        assert_eq!(compacted[1].timestamp, old + Duration::seconds(10));
+        assert_eq!(load_since(&config.dir, Duration::days(3)).unwrap().len(), 8);
+    }
+}
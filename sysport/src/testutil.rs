@@ -0,0 +1,12 @@
+// Helpers shared by unit tests
+use std::fs;
+use std::path::PathBuf;
+
+// An empty directory for one test under the system temp dir; `name` keeps
+// tests that run in parallel apart
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("sysport-test-{}-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
@@ -15,7 +15,7 @@ impl From<Color32Def> for Color32 {
     }
 }
 
-#[derive(Clone, Serialize, Deserialize, Debug)]
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
 pub struct CustomTheme {
     pub background: Color32Def,
     pub foreground: Color32Def,
@@ -0,0 +1,383 @@
+use crate::alert::{AlertLevel, AlertManager, AlertState};
+use crate::capture::{self, RawPacketInfo};
+use crate::collector::Collector;
+use crate::config::Config;
+use crate::metrics::Metrics;
+use crate::report;
+
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::execute;
+use ratatui::backend::{Backend, CrosstermBackend};
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Cell, Gauge, List, ListItem, Paragraph, Row, Sparkline, Table};
+use ratatui::{Frame, Terminal};
+use std::collections::VecDeque;
+use std::fs;
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const CORES_PER_LINE: usize = 4;
+const PACKET_LOG_LEN: usize = 100;
+
+struct TuiState {
+    raw_packets: Arc<Mutex<VecDeque<RawPacketInfo>>>,
+    paused: bool,
+    paused_packets: Vec<RawPacketInfo>,
+    // Errors from background threads, and the latest one with when it came
+    errors: Receiver<String>,
+    status: Option<(chrono::DateTime<chrono::Local>, String)>,
+}
+
+// Terminal frontend for machines without a display. It runs the same collector,
+// alert manager and packet capture as the GUI.
+pub fn run(config: &Config) -> std::io::Result<()> {
+    // Errors printed to stderr would scribble over the screen
+    let errors = report::take_over();
+    let mut collector = Collector::spawn(Some(config.storage.clone()), config.alert_manager());
+    let geoip_reader = fs::read(&config.geoip_path)
+        .ok()
+        .and_then(|data| maxminddb::Reader::from_source(data).ok());
+    let raw_packets = Arc::new(Mutex::new(VecDeque::with_capacity(capture::MAX_RAW_PACKETS)));
+    capture::spawn_capture(None, raw_packets.clone(), Default::default(), geoip_reader);
+    let mut state = TuiState { raw_packets, paused: false, paused_packets: Vec::new(), errors, status: None };
+
+    restore_terminal_on_panic();
+    let result = show(&collector, &mut state);
+    collector.stop();
+    report::release();
+    // Errors that came in after the last redraw
+    for message in state.errors.try_iter() {
+        eprintln!("{}", message);
+    }
+    result
+}
+
+fn show(collector: &Collector, state: &mut TuiState) -> std::io::Result<()> {
+    enable_raw_mode()?;
+    let mut stdout = std::io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+    let result = event_loop(&mut terminal, collector, state);
+
+    // Always give the terminal back, even if drawing failed
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+    result
+}
+
+// Leaves raw mode and the alternate screen before the panic message prints, so
+// the message stays readable and the shell is usable afterwards
+fn restore_terminal_on_panic() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let _ = disable_raw_mode();
+        let _ = execute!(std::io::stdout(), LeaveAlternateScreen);
+        default_hook(info);
+    }));
+}
+
+fn event_loop<B: Backend>(terminal: &mut Terminal<B>, collector: &Collector, state: &mut TuiState) -> std::io::Result<()> {
+    loop {
+        if let Some(message) = state.errors.try_iter().last() {
+            state.status = Some((chrono::Local::now(), message));
+        }
+        terminal.draw(|f| draw(f, collector, state))?;
+        if !event::poll(Duration::from_millis(250))? {
+            continue;
+        }
+        match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Ok(()),
+                KeyCode::Char('p') => {
+                    state.paused = !state.paused;
+                    state.paused_packets = if state.paused { recent_packets(&state.raw_packets) } else { Vec::new() };
+                }
+                KeyCode::Char('a') => {
+                    let mut am = collector.alert_manager.lock().unwrap();
+                    let firing: Vec<u64> = am.active_alerts.iter().filter(|a| a.state == AlertState::Firing).map(|a| a.id).collect();
+                    for id in firing {
+                        am.acknowledge(id);
+                    }
+                }
+                // Redraw from scratch, e.g. after a library wrote to the terminal
+                KeyCode::Char('r') => terminal.clear()?,
+                _ => {}
+            },
+            Event::Resize(_, _) => terminal.clear()?,
+            _ => {}
+        }
+    }
+}
+
+fn draw(f: &mut Frame, collector: &Collector, state: &TuiState) {
+    let metrics = collector.metrics.lock().unwrap().clone();
+    let (cpu_history, mem_history) = {
+        let history = collector.history.lock().unwrap();
+        let width = f.size().width as usize;
+        let recent = history.raw().iter().rev().take(width).rev();
+        let cpu: Vec<u64> = recent.clone().map(|m| m.cpu_total as u64).collect();
+        let mem: Vec<u64> = recent.map(|m| m.mem_used * 100 / m.mem_total.max(1)).collect();
+        (cpu, mem)
+    };
+
+    let core_lines = metrics.cpu_usage.len().div_ceil(CORES_PER_LINE) as u16;
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(1),
+            Constraint::Length(core_lines.max(2) + 6),
+            Constraint::Min(8),
+            Constraint::Length(12),
+            Constraint::Length(1),
+        ])
+        .split(f.size());
+
+    let help = if state.paused { "q quit · a acknowledge alerts · p resume packet log · r redraw" } else { "q quit · a acknowledge alerts · p pause packet log · r redraw" };
+    f.render_widget(
+        Paragraph::new(Line::from(vec![
+            Span::styled(" SysPort ", Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED)),
+            Span::raw(format!(" {}  ", metrics.timestamp.with_timezone(&chrono::Local).format("%H:%M:%S"))),
+            Span::styled(help, Style::default().fg(Color::DarkGray)),
+        ])),
+        rows[0],
+    );
+
+    let top = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(rows[1]);
+    draw_cpu(f, top[0], &metrics, &cpu_history);
+    draw_memory(f, top[1], &metrics, &mem_history);
+
+    let middle = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(rows[2]);
+    draw_disks(f, middle[0], &metrics);
+    draw_network(f, middle[1], &metrics);
+
+    let bottom = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(35), Constraint::Percentage(65)])
+        .split(rows[3]);
+    draw_alerts(f, bottom[0], &collector.alert_manager);
+    draw_packets(f, bottom[1], state);
+
+    if let Some((at, message)) = &state.status {
+        f.render_widget(
+            Paragraph::new(format!(" {}  {}", at.format("%H:%M:%S"), message)).style(Style::default().fg(Color::Red)),
+            rows[4],
+        );
+    }
+}
+
+fn draw_cpu(f: &mut Frame, area: Rect, metrics: &Metrics, history: &[u64]) {
+    let block = Block::default().borders(Borders::ALL).title("CPU Usage");
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    let parts = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(1), Constraint::Length(3), Constraint::Min(0)])
+        .split(inner);
+    f.render_widget(
+        Gauge::default()
+            .gauge_style(Style::default().fg(Color::LightBlue))
+            .ratio((metrics.cpu_total as f64 / 100.0).clamp(0.0, 1.0))
+            .label(format!("Total: {:.1}%", metrics.cpu_total)),
+        parts[0],
+    );
+    f.render_widget(Sparkline::default().data(history).max(100).style(Style::default().fg(Color::LightBlue)), parts[1]);
+    let bar_width = (parts[2].width as usize / CORES_PER_LINE).saturating_sub(12).max(1);
+    let lines: Vec<Line> = metrics
+        .cpu_usage
+        .chunks(CORES_PER_LINE)
+        .enumerate()
+        .map(|(row, chunk)| {
+            let text: String = chunk
+                .iter()
+                .enumerate()
+                .map(|(i, usage)| format!("{:>2} {} {:>3.0}% ", row * CORES_PER_LINE + i, bar(*usage as f64 / 100.0, bar_width), usage))
+                .collect();
+            Line::from(text)
+        })
+        .collect();
+    f.render_widget(Paragraph::new(lines), parts[2]);
+}
+
+fn draw_memory(f: &mut Frame, area: Rect, metrics: &Metrics, history: &[u64]) {
+    let block = Block::default().borders(Borders::ALL).title("Memory Usage");
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    let parts = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(1), Constraint::Length(1), Constraint::Min(0)])
+        .split(inner);
+    let used_gb = metrics.mem_used as f64 / 1024.0 / 1024.0;
+    let total_gb = metrics.mem_total as f64 / 1024.0 / 1024.0;
+    let ratio = metrics.mem_used as f64 / metrics.mem_total.max(1) as f64;
+    f.render_widget(Paragraph::new(format!("{:.2} GB / {:.2} GB", used_gb, total_gb)), parts[0]);
+    f.render_widget(
+        Gauge::default()
+            .gauge_style(Style::default().fg(Color::LightGreen))
+            .ratio(ratio.clamp(0.0, 1.0))
+            .label(format!("{:.0}%", ratio * 100.0)),
+        parts[1],
+    );
+    f.render_widget(Sparkline::default().data(history).max(100).style(Style::default().fg(Color::LightGreen)), parts[2]);
+}
+
+fn draw_disks(f: &mut Frame, area: Rect, metrics: &Metrics) {
+    let header = Row::new(["Mount", "Used", "Size", "Use%", "Read MB/s", "Write MB/s"]).style(Style::default().add_modifier(Modifier::BOLD));
+    let rows = metrics.disks.iter().map(|disk| {
+        let used = disk.total - disk.available;
+        let percent = used as f64 / disk.total.max(1) as f64 * 100.0;
+        let style = if percent >= 90.0 { Style::default().fg(Color::Red) } else { Style::default() };
+        Row::new(vec![
+            Cell::from(disk.mount_point.clone()),
+            Cell::from(format!("{:.1} GB", used as f64 / 1024.0 / 1024.0 / 1024.0)),
+            Cell::from(format!("{:.1} GB", disk.total as f64 / 1024.0 / 1024.0 / 1024.0)),
+            Cell::from(format!("{:.1}", percent)),
+            Cell::from(format!("{:.2}", disk.read_bytes as f64 / 1024.0 / 1024.0)),
+            Cell::from(format!("{:.2}", disk.write_bytes as f64 / 1024.0 / 1024.0)),
+        ])
+        .style(style)
+    });
+    let widths = [
+        Constraint::Min(10),
+        Constraint::Length(10),
+        Constraint::Length(10),
+        Constraint::Length(6),
+        Constraint::Length(10),
+        Constraint::Length(10),
+    ];
+    f.render_widget(
+        Table::new(rows, widths).header(header).block(Block::default().borders(Borders::ALL).title("Disk Usage")),
+        area,
+    );
+}
+
+fn draw_network(f: &mut Frame, area: Rect, metrics: &Metrics) {
+    let scope = metrics.selected_interface.clone().unwrap_or_else(|| "all interfaces".to_string());
+    let block = Block::default().borders(Borders::ALL).title(format!("Network Throughput ({})", scope));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    let parts = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(2), Constraint::Min(0)])
+        .split(inner);
+    f.render_widget(
+        Paragraph::new(vec![
+            Line::from(format!("Download: {:.2} KB/s", metrics.net_rx as f64 / 1024.0)),
+            Line::from(format!("Upload:   {:.2} KB/s", metrics.net_tx as f64 / 1024.0)),
+        ]),
+        parts[0],
+    );
+    let header = Row::new(["Interface", "State", "RX KB/s", "TX KB/s", "Err rx/tx", "Drop rx/tx"]).style(Style::default().add_modifier(Modifier::BOLD));
+    let rows = metrics
+        .net_per_interface
+        .iter()
+        .filter(|iface| metrics.selected_interface.as_ref().is_none_or(|sel| sel == &iface.name))
+        .map(|iface| {
+            Row::new(vec![
+                iface.name.clone(),
+                match iface.is_up {
+                    Some(true) => "up",
+                    Some(false) => "down",
+                    None => "?",
+                }
+                .to_string(),
+                format!("{:.2}", iface.rx as f64 / 1024.0),
+                format!("{:.2}", iface.tx as f64 / 1024.0),
+                format!("{}/{}", iface.rx_errors, iface.tx_errors),
+                format!("{}/{}", iface.rx_dropped, iface.tx_dropped),
+            ])
+        });
+    let widths = [
+        Constraint::Min(10),
+        Constraint::Length(5),
+        Constraint::Length(10),
+        Constraint::Length(10),
+        Constraint::Length(10),
+        Constraint::Length(10),
+    ];
+    f.render_widget(Table::new(rows, widths).header(header), parts[1]);
+}
+
+fn draw_alerts(f: &mut Frame, area: Rect, alert_manager: &Arc<Mutex<AlertManager>>) {
+    let alerts = alert_manager.lock().unwrap().active_alerts.clone();
+    let items: Vec<ListItem> = if alerts.is_empty() {
+        vec![ListItem::new("No active alerts").style(Style::default().fg(Color::DarkGray))]
+    } else {
+        alerts
+            .iter()
+            .map(|alert| {
+                let color = match alert.level {
+                    AlertLevel::Critical => Color::Red,
+                    AlertLevel::Warning => Color::Yellow,
+                    AlertLevel::Info => Color::Cyan,
+                };
+                let style = match alert.state {
+                    AlertState::Firing => Style::default().fg(color).add_modifier(Modifier::BOLD),
+                    _ => Style::default().fg(color),
+                };
+                ListItem::new(format!("[{:?}] {} ({:?})", alert.level, alert.message, alert.state)).style(style)
+            })
+            .collect()
+    };
+    f.render_widget(
+        List::new(items).block(Block::default().borders(Borders::ALL).title(format!("Alerts ({})", alerts.len()))),
+        area,
+    );
+}
+
+fn draw_packets(f: &mut Frame, area: Rect, state: &TuiState) {
+    let total = state.raw_packets.lock().unwrap().len();
+    let live;
+    let packets = if state.paused {
+        &state.paused_packets
+    } else {
+        live = recent_packets(&state.raw_packets);
+        &live
+    };
+    let items: Vec<ListItem> = packets
+        .iter()
+        .map(|pkt| {
+            let color = match pkt.protocol.as_str() {
+                "TCP" => Color::LightBlue,
+                "UDP" => Color::LightGreen,
+                "ICMP" => Color::Yellow,
+                "ARP" => Color::Red,
+                _ => Color::Gray,
+            };
+            ListItem::new(format!(
+                "{:>4}s {}:{} → {}:{} [{}] {}",
+                pkt.timestamp.elapsed().as_secs(),
+                pkt.src.map(|ip| ip.to_string()).unwrap_or("?".to_string()),
+                pkt.src_port.map(|p| p.to_string()).unwrap_or("?".to_string()),
+                pkt.dst.map(|ip| ip.to_string()).unwrap_or("?".to_string()),
+                pkt.dst_port.map(|p| p.to_string()).unwrap_or("?".to_string()),
+                pkt.protocol,
+                pkt.country.as_deref().unwrap_or("")
+            ))
+            .style(Style::default().fg(color))
+        })
+        .collect();
+    let title = format!("Live Packet Log ({} captured{})", total, if state.paused { ", paused" } else { "" });
+    f.render_widget(List::new(items).block(Block::default().borders(Borders::ALL).title(title)), area);
+}
+
+// Newest packets first, as in the GUI packet log
+fn recent_packets(raw_packets: &Arc<Mutex<VecDeque<RawPacketInfo>>>) -> Vec<RawPacketInfo> {
+    raw_packets.lock().unwrap().iter().rev().take(PACKET_LOG_LEN).cloned().collect()
+}
+
+fn bar(ratio: f64, width: usize) -> String {
+    let filled = ((ratio.clamp(0.0, 1.0) * width as f64).round() as usize).min(width);
+    format!("[{}{}]", "|".repeat(filled), " ".repeat(width - filled))
+}